@@ -0,0 +1,6 @@
+-- Per-subreddit high-water mark for incremental fetching
+-- Remembers the newest post seen in each subreddit so the next poll of /r/{sub}/new
+-- can stop as soon as it reaches posts we already have
+
+ALTER TABLE subreddits ADD COLUMN last_seen_post_id TEXT;        -- Reddit ID of the newest post seen
+ALTER TABLE subreddits ADD COLUMN last_seen_created_utc INTEGER; -- created_utc of the newest post seen
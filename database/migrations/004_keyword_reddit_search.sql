@@ -0,0 +1,5 @@
+-- Opt-in Reddit-wide search per keyword
+-- Keywords normally only match posts from joined subreddits; when this flag is set
+-- the keyword is also sent to Reddit's /search endpoint to discover posts elsewhere
+
+ALTER TABLE keywords ADD COLUMN search_reddit BOOLEAN NOT NULL DEFAULT FALSE;
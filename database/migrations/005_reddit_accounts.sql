@@ -0,0 +1,54 @@
+-- Multiple Reddit accounts
+-- Each account has its own tokens, joined subreddits and user actions. Rows that
+-- existed before this migration belong to the default account (id 1).
+
+-- Table: reddit_accounts
+-- Reddit accounts connected on this machine
+CREATE TABLE reddit_accounts (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    username TEXT NOT NULL UNIQUE COLLATE NOCASE, -- Reddit username (without u/)
+    is_active BOOLEAN NOT NULL DEFAULT TRUE,      -- Whether the background service polls this account
+    created_at INTEGER NOT NULL,                  -- When the account was connected
+    updated_at INTEGER NOT NULL                   -- Last update timestamp
+);
+
+CREATE INDEX idx_reddit_accounts_is_active ON reddit_accounts(is_active);
+
+INSERT INTO reddit_accounts (id, username, is_active, created_at, updated_at) VALUES
+    (1, 'default', TRUE, strftime('%s', 'now'), strftime('%s', 'now'));
+
+-- Table: subreddits
+-- Rebuilt so subreddit names are unique per account instead of globally
+CREATE TABLE subreddits_new (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    account_id INTEGER NOT NULL DEFAULT 1,   -- Reference to reddit_accounts.id
+    name TEXT NOT NULL,                      -- Subreddit name (without r/)
+    is_active BOOLEAN NOT NULL DEFAULT TRUE, -- Whether we're currently monitoring this subreddit
+    last_fetched_at INTEGER,                 -- Last time we fetched posts from this subreddit
+    last_seen_post_id TEXT,                  -- Reddit ID of the newest post seen
+    last_seen_created_utc INTEGER,           -- created_utc of the newest post seen
+    created_at INTEGER NOT NULL,             -- When we started monitoring this subreddit
+    updated_at INTEGER NOT NULL,             -- Last update timestamp
+
+    UNIQUE(account_id, name),
+    FOREIGN KEY (account_id) REFERENCES reddit_accounts(id) ON DELETE CASCADE
+);
+
+INSERT INTO subreddits_new (id, account_id, name, is_active, last_fetched_at, last_seen_post_id,
+                            last_seen_created_utc, created_at, updated_at)
+SELECT id, 1, name, is_active, last_fetched_at, last_seen_post_id, last_seen_created_utc,
+       created_at, updated_at
+FROM subreddits;
+
+DROP TABLE subreddits;
+ALTER TABLE subreddits_new RENAME TO subreddits;
+
+CREATE INDEX idx_subreddits_account_id ON subreddits(account_id);
+CREATE INDEX idx_subreddits_is_active ON subreddits(is_active);
+CREATE INDEX idx_subreddits_last_fetched_at ON subreddits(last_fetched_at);
+
+-- Table: user_actions
+-- Actions are recorded per account; existing actions belong to the default account
+ALTER TABLE user_actions ADD COLUMN account_id INTEGER NOT NULL DEFAULT 1; -- Reference to reddit_accounts.id
+
+CREATE INDEX idx_user_actions_account_id ON user_actions(account_id);
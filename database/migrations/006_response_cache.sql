@@ -0,0 +1,27 @@
+-- HTTP response cache
+-- Endpoints with a cache TTL are answered from the cache while the entry is fresh,
+-- and revalidated with ETag/Last-Modified once it goes stale.
+
+-- How long a response for this endpoint pattern stays fresh; 0 disables caching
+ALTER TABLE api_endpoint_configs ADD COLUMN cache_ttl_seconds INTEGER NOT NULL DEFAULT 0 CHECK (cache_ttl_seconds >= 0);
+
+UPDATE api_endpoint_configs SET cache_ttl_seconds = 60, updated_at = strftime('%s', 'now')
+    WHERE endpoint_pattern IN ('/r/*/hot', '/r/*/new');
+UPDATE api_endpoint_configs SET cache_ttl_seconds = 300, updated_at = strftime('%s', 'now')
+    WHERE endpoint_pattern = '/r/*/top';
+UPDATE api_endpoint_configs SET cache_ttl_seconds = 3600, updated_at = strftime('%s', 'now')
+    WHERE endpoint_pattern = '/r/*/about';
+
+-- Table: http_response_cache
+-- Cached GET responses keyed by endpoint and query string
+CREATE TABLE http_response_cache (
+    cache_key TEXT PRIMARY KEY,     -- Endpoint plus sorted query string
+    status_code INTEGER NOT NULL,   -- Status of the cached response
+    body BLOB NOT NULL,             -- Raw response body
+    etag TEXT,                      -- ETag validator, if the server sent one
+    last_modified TEXT,             -- Last-Modified validator, if the server sent one
+    stored_at INTEGER NOT NULL,     -- When the response was fetched or last revalidated
+    expires_at INTEGER NOT NULL     -- When the entry stops being fresh
+);
+
+CREATE INDEX idx_http_response_cache_expires_at ON http_response_cache(expires_at);
@@ -0,0 +1,8 @@
+-- Durable request queue
+-- Queued requests are reloaded after a restart, so everything needed to send them
+-- again has to be stored. Access tokens are deliberately not persisted.
+
+ALTER TABLE request_queue ADD COLUMN subreddit TEXT;           -- Subreddit the request is for, if any
+ALTER TABLE request_queue ADD COLUMN timeout_seconds INTEGER;  -- Per-request timeout
+
+CREATE INDEX idx_request_queue_status_scheduled ON request_queue(status, scheduled_for);
@@ -0,0 +1,7 @@
+-- Request queue accounts
+-- Queued requests are restored with the token of the account that queued them.
+-- Rows that existed before this migration belong to the default account (id 1).
+
+ALTER TABLE request_queue ADD COLUMN account_id INTEGER NOT NULL DEFAULT 1; -- Reference to reddit_accounts.id
+
+CREATE INDEX idx_request_queue_account ON request_queue(account_id, status);
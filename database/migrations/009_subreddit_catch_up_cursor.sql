@@ -0,0 +1,7 @@
+-- Subreddit catch-up cursor
+-- A poll capped by max_posts that stops before the high-water mark records where it
+-- stopped, so the next poll continues from there instead of starting over at the top
+
+ALTER TABLE subreddits ADD COLUMN resume_after TEXT;            -- Fullname of the last post returned by the capped poll
+ALTER TABLE subreddits ADD COLUMN pending_post_id TEXT;         -- Newest post seen since the catch-up started
+ALTER TABLE subreddits ADD COLUMN pending_created_utc INTEGER;  -- created_utc of that post
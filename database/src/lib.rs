@@ -1,6 +1,17 @@
-use likeminded_core::{AppConfig, CoreError, Keyword, RedditPost};
-use sqlx::{migrate::MigrateDatabase, sqlite::SqlitePool, Sqlite};
-use std::collections::HashMap;
+use likeminded_core::{
+    AppConfig, CoreError, DatabaseError, Keyword, RedditPost, SubredditHighWaterMark,
+};
+use sqlx::migrate::{Migrate, MigrateDatabase, MigrateError, Migrator};
+use sqlx::{sqlite::SqlitePool, Sqlite};
+use std::collections::{HashMap, HashSet};
+
+/// Schema migrations from `migrations/`. Each one that has run is recorded
+/// in `_sqlx_migrations`, so `run_migrations` only applies the new ones.
+static MIGRATOR: Migrator = sqlx::migrate!("./migrations");
+
+/// Account that owns every subreddit and user action recorded before
+/// multiple accounts were supported
+pub const DEFAULT_ACCOUNT_ID: i64 = 1;
 
 pub struct Database {
     pool: Option<SqlitePool>,
@@ -10,6 +21,7 @@ pub struct Database {
 #[derive(Debug, Clone)]
 pub struct UserAction {
     pub id: Option<i64>,
+    pub account_id: i64,
     pub post_id: String,
     pub action_type: String,
     pub created_at: i64,
@@ -18,13 +30,116 @@ pub struct UserAction {
 #[derive(Debug, Clone)]
 pub struct SubredditInfo {
     pub id: Option<i64>,
+    pub account_id: i64,
     pub name: String,
     pub is_active: bool,
     pub last_fetched_at: Option<i64>,
+    pub last_seen_post_id: Option<String>,
+    pub last_seen_created_utc: Option<i64>,
+    pub resume_after: Option<String>,
+    pub pending_post_id: Option<String>,
+    pub pending_created_utc: Option<i64>,
     pub created_at: i64,
     pub updated_at: i64,
 }
 
+#[derive(Debug, Clone)]
+pub struct RedditAccount {
+    pub id: i64,
+    pub username: String,
+    pub is_active: bool,
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+/// What `sync_subscribed_subreddits` changed
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SubredditSyncReport {
+    pub added: Vec<String>,
+    pub reactivated: Vec<String>,
+    pub deactivated: Vec<String>,
+}
+
+impl SubredditSyncReport {
+    pub fn is_unchanged(&self) -> bool {
+        self.added.is_empty() && self.reactivated.is_empty() && self.deactivated.is_empty()
+    }
+}
+
+impl SubredditInfo {
+    pub fn high_water_mark(&self) -> SubredditHighWaterMark {
+        SubredditHighWaterMark {
+            post_id: self.last_seen_post_id.clone(),
+            created_utc: self.last_seen_created_utc,
+            resume_after: self.resume_after.clone(),
+            pending_post_id: self.pending_post_id.clone(),
+            pending_created_utc: self.pending_created_utc,
+        }
+    }
+}
+
+/// Databases created before migrations were recorded only ever ran the
+/// initial schema. Mark it as applied so the runner continues from the next
+/// migration instead of failing on its `CREATE TABLE`s.
+async fn adopt_unversioned_schema(pool: &SqlitePool) -> Result<(), CoreError> {
+    let tables: Vec<String> = sqlx::query_scalar(
+        "SELECT name FROM sqlite_master WHERE type = 'table' AND name IN ('_sqlx_migrations', 'posts')",
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(sql_error)?;
+
+    if tables.iter().any(|name| name == "_sqlx_migrations")
+        || !tables.iter().any(|name| name == "posts")
+    {
+        return Ok(());
+    }
+
+    let initial = MIGRATOR.iter().next().ok_or_else(|| {
+        CoreError::Database(DatabaseError::MigrationFailed {
+            migration: "no initial migration to adopt".to_string(),
+        })
+    })?;
+    let mut conn = pool.acquire().await.map_err(sql_error)?;
+    conn.ensure_migrations_table()
+        .await
+        .map_err(migration_error)?;
+    sqlx::query(
+        "INSERT INTO _sqlx_migrations (version, description, success, checksum, execution_time)
+         VALUES (?, ?, TRUE, ?, 0)",
+    )
+    .bind(initial.version)
+    .bind(&*initial.description)
+    .bind(&*initial.checksum)
+    .execute(&mut *conn)
+    .await
+    .map_err(sql_error)?;
+
+    Ok(())
+}
+
+fn sql_error(e: sqlx::Error) -> CoreError {
+    CoreError::Database(DatabaseError::Sql(e))
+}
+
+fn not_connected() -> CoreError {
+    CoreError::Database(DatabaseError::ConnectionFailed {
+        reason: "Database not connected".to_string(),
+    })
+}
+
+fn connection_error(context: &str, e: sqlx::Error) -> CoreError {
+    CoreError::Database(DatabaseError::ConnectionFailed {
+        reason: format!("{}: {}", context, e),
+    })
+}
+
+fn migration_error(e: MigrateError) -> CoreError {
+    CoreError::Database(DatabaseError::MigrationFailed {
+        migration: e.to_string(),
+    })
+}
+
 impl Database {
     pub fn new(database_url: String) -> Self {
         Self {
@@ -37,46 +152,35 @@ impl Database {
         // Create database if it doesn't exist
         if !Sqlite::database_exists(&self.database_url)
             .await
-            .map_err(|e| CoreError::Configuration(format!("Database check failed: {}", e)))?
+            .map_err(|e| connection_error("Database check failed", e))?
         {
             Sqlite::create_database(&self.database_url)
                 .await
-                .map_err(|e| {
-                    CoreError::Configuration(format!("Database creation failed: {}", e))
-                })?;
+                .map_err(|e| connection_error("Database creation failed", e))?;
         }
 
         // Connect to database
         let pool = SqlitePool::connect(&self.database_url)
             .await
-            .map_err(|e| CoreError::Configuration(format!("Database connection failed: {}", e)))?;
+            .map_err(|e| connection_error("Database connection failed", e))?;
 
         self.pool = Some(pool);
         Ok(())
     }
 
     pub async fn run_migrations(&self) -> Result<(), CoreError> {
-        let pool = self
-            .pool
-            .as_ref()
-            .ok_or_else(|| CoreError::Configuration("Database not connected".to_string()))?;
-
-        let migration_sql = include_str!("../migrations/001_initial_schema.sql");
+        let pool = self.pool.as_ref().ok_or_else(not_connected)?;
 
-        sqlx::raw_sql(migration_sql)
-            .execute(pool)
-            .await
-            .map_err(|e| CoreError::Configuration(format!("Migration failed: {}", e)))?;
+        adopt_unversioned_schema(pool).await?;
+        MIGRATOR.run(pool).await.map_err(migration_error)?;
 
         Ok(())
     }
 
     pub async fn save_post(&self, post: &RedditPost) -> Result<(), CoreError> {
-        let pool = self
-            .pool
-            .as_ref()
-            .ok_or_else(|| CoreError::Configuration("Database not connected".to_string()))?;
+        let pool = self.pool.as_ref().ok_or_else(not_connected)?;
 
+        let now = chrono::Utc::now().timestamp();
         sqlx::query!(
             r#"
             INSERT OR REPLACE INTO posts (id, title, content, subreddit, url, author, score, created_utc, fetched_at)
@@ -87,45 +191,54 @@ impl Database {
             post.content,
             post.subreddit,
             post.url,
-            "unknown", // We'll need to add author to RedditPost struct
-            0, // We'll need to add score to RedditPost struct
+            post.author,
+            post.score,
             post.created_utc,
-            chrono::Utc::now().timestamp()
+            now
         )
         .execute(pool)
         .await
-        .map_err(|e| CoreError::Configuration(format!("Failed to save post: {}", e)))?;
+        .map_err(sql_error)?;
 
         Ok(())
     }
 
     pub async fn get_posts(&self, limit: Option<i32>) -> Result<Vec<RedditPost>, CoreError> {
-        let pool = self
-            .pool
-            .as_ref()
-            .ok_or_else(|| CoreError::Configuration("Database not connected".to_string()))?;
+        let pool = self.pool.as_ref().ok_or_else(not_connected)?;
 
         let limit = limit.unwrap_or(50);
         let rows = sqlx::query!(
-            "SELECT id, title, content, subreddit, url, created_utc 
+            r#"SELECT id as "id!", title, content, subreddit, url, author, score, created_utc
              FROM posts 
              ORDER BY created_utc DESC 
-             LIMIT ?",
+             LIMIT ?"#,
             limit
         )
         .fetch_all(pool)
         .await
-        .map_err(|e| CoreError::Configuration(format!("Failed to fetch posts: {}", e)))?;
+        .map_err(sql_error)?;
 
         let posts = rows
             .into_iter()
             .map(|row| RedditPost {
                 id: row.id,
                 title: row.title,
+                is_self: row.content.is_some(),
                 content: row.content,
                 subreddit: row.subreddit,
+                permalink: row.url.clone(),
                 url: row.url,
+                author: row.author,
                 created_utc: row.created_utc,
+                score: row.score as i32,
+                // Listing details that are not stored
+                num_comments: 0,
+                upvote_ratio: None,
+                over_18: false,
+                stickied: false,
+                locked: false,
+                domain: String::new(),
+                thumbnail: None,
             })
             .collect();
 
@@ -133,55 +246,47 @@ impl Database {
     }
 
     pub async fn save_keyword(&self, keyword: &Keyword) -> Result<i64, CoreError> {
-        let pool = self
-            .pool
-            .as_ref()
-            .ok_or_else(|| CoreError::Configuration("Database not connected".to_string()))?;
+        let pool = self.pool.as_ref().ok_or_else(not_connected)?;
 
         let now = chrono::Utc::now().timestamp();
         let result = sqlx::query!(
             r#"
-            INSERT INTO keywords (text, created_at, updated_at)
-            VALUES (?, ?, ?)
+            INSERT INTO keywords (text, search_reddit, created_at, updated_at)
+            VALUES (?, ?, ?, ?)
             "#,
             keyword.text,
+            keyword.search_reddit,
             now,
             now
         )
         .execute(pool)
         .await
-        .map_err(|e| CoreError::Configuration(format!("Failed to save keyword: {}", e)))?;
+        .map_err(sql_error)?;
 
         Ok(result.last_insert_rowid())
     }
 
     pub async fn get_keywords(&self) -> Result<Vec<Keyword>, CoreError> {
-        let pool = self
-            .pool
-            .as_ref()
-            .ok_or_else(|| CoreError::Configuration("Database not connected".to_string()))?;
+        let pool = self.pool.as_ref().ok_or_else(not_connected)?;
 
         let rows = sqlx::query!(
-            "SELECT id, text, embedding, created_at FROM keywords WHERE is_active = TRUE ORDER BY created_at DESC"
+            r#"SELECT id as "id!", text, embedding, search_reddit, created_at FROM keywords WHERE is_active = TRUE ORDER BY created_at DESC"#
         )
         .fetch_all(pool)
         .await
-        .map_err(|e| CoreError::Configuration(format!("Failed to fetch keywords: {}", e)))?;
+        .map_err(sql_error)?;
 
         let keywords = rows
             .into_iter()
             .map(|row| {
-                let embedding = if let Some(blob) = row.embedding {
-                    // TODO: Deserialize embedding blob to Vec<f32>
-                    Some(Vec::new()) // Placeholder
-                } else {
-                    None
-                };
+                // TODO: Deserialize embedding blob to Vec<f32>
+                let embedding = row.embedding.map(|_blob| Vec::new()); // Placeholder
 
                 Keyword {
                     id: Some(row.id),
                     text: row.text,
                     embedding,
+                    search_reddit: row.search_reddit,
                     created_at: row.created_at,
                 }
             })
@@ -191,10 +296,7 @@ impl Database {
     }
 
     pub async fn save_setting(&self, key: &str, value: &str) -> Result<(), CoreError> {
-        let pool = self
-            .pool
-            .as_ref()
-            .ok_or_else(|| CoreError::Configuration("Database not connected".to_string()))?;
+        let pool = self.pool.as_ref().ok_or_else(not_connected)?;
 
         let now = chrono::Utc::now().timestamp();
         sqlx::query!(
@@ -210,35 +312,29 @@ impl Database {
         )
         .execute(pool)
         .await
-        .map_err(|e| CoreError::Configuration(format!("Failed to save setting: {}", e)))?;
+        .map_err(sql_error)?;
 
         Ok(())
     }
 
     pub async fn get_setting(&self, key: &str) -> Result<Option<String>, CoreError> {
-        let pool = self
-            .pool
-            .as_ref()
-            .ok_or_else(|| CoreError::Configuration("Database not connected".to_string()))?;
+        let pool = self.pool.as_ref().ok_or_else(not_connected)?;
 
         let row = sqlx::query!("SELECT value FROM settings WHERE key = ?", key)
             .fetch_optional(pool)
             .await
-            .map_err(|e| CoreError::Configuration(format!("Failed to fetch setting: {}", e)))?;
+            .map_err(sql_error)?;
 
         Ok(row.map(|r| r.value))
     }
 
     pub async fn get_all_settings(&self) -> Result<HashMap<String, String>, CoreError> {
-        let pool = self
-            .pool
-            .as_ref()
-            .ok_or_else(|| CoreError::Configuration("Database not connected".to_string()))?;
+        let pool = self.pool.as_ref().ok_or_else(not_connected)?;
 
         let rows = sqlx::query!("SELECT key, value FROM settings")
             .fetch_all(pool)
             .await
-            .map_err(|e| CoreError::Configuration(format!("Failed to fetch settings: {}", e)))?;
+            .map_err(sql_error)?;
 
         let mut settings = HashMap::new();
         for row in rows {
@@ -292,77 +388,184 @@ impl Database {
     }
 
     pub async fn save_api_key(&self, provider: &str, encrypted_key: &str) -> Result<(), CoreError> {
-        let pool = self
-            .pool
-            .as_ref()
-            .ok_or_else(|| CoreError::Configuration("Database not connected".to_string()))?;
+        let pool = self.pool.as_ref().ok_or_else(not_connected)?;
 
         let now = chrono::Utc::now().timestamp();
+        let encrypted_key = encrypted_key.as_bytes();
         sqlx::query!(
             r#"
             INSERT OR REPLACE INTO api_keys (provider, encrypted_key, created_at, updated_at)
             VALUES (?, ?, COALESCE((SELECT created_at FROM api_keys WHERE provider = ?), ?), ?)
             "#,
             provider,
-            encrypted_key.as_bytes(),
+            encrypted_key,
             provider,
             now,
             now
         )
         .execute(pool)
         .await
-        .map_err(|e| CoreError::Configuration(format!("Failed to save API key: {}", e)))?;
+        .map_err(sql_error)?;
+
+        Ok(())
+    }
+
+    /// Add a Reddit account, or reactivate it if it was connected before.
+    /// Returns the account ID.
+    pub async fn add_reddit_account(&self, username: &str) -> Result<i64, CoreError> {
+        let pool = self.pool.as_ref().ok_or_else(not_connected)?;
+
+        let now = chrono::Utc::now().timestamp();
+        sqlx::query!(
+            "INSERT INTO reddit_accounts (username, is_active, created_at, updated_at)
+             VALUES (?, TRUE, ?, ?)
+             ON CONFLICT(username) DO UPDATE SET is_active = TRUE, updated_at = excluded.updated_at",
+            username,
+            now,
+            now
+        )
+        .execute(pool)
+        .await
+        .map_err(sql_error)?;
+
+        // Looked up separately: a RETURNING row read with fetch_one leaves the
+        // statement open, and with it SQLite's write lock on that connection
+        let row = sqlx::query!(
+            r#"SELECT id as "id!" FROM reddit_accounts WHERE username = ?"#,
+            username
+        )
+        .fetch_one(pool)
+        .await
+        .map_err(sql_error)?;
+
+        Ok(row.id)
+    }
+
+    pub async fn get_reddit_accounts(&self) -> Result<Vec<RedditAccount>, CoreError> {
+        let pool = self.pool.as_ref().ok_or_else(not_connected)?;
+
+        let rows = sqlx::query!(
+            "SELECT id, username, is_active, created_at, updated_at FROM reddit_accounts ORDER BY id"
+        )
+        .fetch_all(pool)
+        .await
+        .map_err(sql_error)?;
+
+        let accounts = rows
+            .into_iter()
+            .map(|row| RedditAccount {
+                id: row.id,
+                username: row.username,
+                is_active: row.is_active,
+                created_at: row.created_at,
+                updated_at: row.updated_at,
+            })
+            .collect();
+
+        Ok(accounts)
+    }
+
+    /// Pause or resume polling for an account without forgetting its data
+    pub async fn set_reddit_account_active(
+        &self,
+        account_id: i64,
+        is_active: bool,
+    ) -> Result<(), CoreError> {
+        let pool = self.pool.as_ref().ok_or_else(not_connected)?;
+
+        let now = chrono::Utc::now().timestamp();
+        sqlx::query!(
+            "UPDATE reddit_accounts SET is_active = ?, updated_at = ? WHERE id = ?",
+            is_active,
+            now,
+            account_id
+        )
+        .execute(pool)
+        .await
+        .map_err(sql_error)?;
+
+        Ok(())
+    }
+
+    /// Delete an account together with its subreddits and user actions
+    pub async fn remove_reddit_account(&self, account_id: i64) -> Result<(), CoreError> {
+        let pool = self.pool.as_ref().ok_or_else(not_connected)?;
+
+        let mut tx = pool.begin().await.map_err(sql_error)?;
+
+        sqlx::query!("DELETE FROM user_actions WHERE account_id = ?", account_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(sql_error)?;
+        sqlx::query!("DELETE FROM subreddits WHERE account_id = ?", account_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(sql_error)?;
+        sqlx::query!("DELETE FROM reddit_accounts WHERE id = ?", account_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(sql_error)?;
+
+        tx.commit().await.map_err(sql_error)?;
 
         Ok(())
     }
 
     pub async fn record_user_action(
         &self,
+        account_id: i64,
         post_id: &str,
         action_type: &str,
     ) -> Result<(), CoreError> {
-        let pool = self
-            .pool
-            .as_ref()
-            .ok_or_else(|| CoreError::Configuration("Database not connected".to_string()))?;
+        let pool = self.pool.as_ref().ok_or_else(not_connected)?;
 
         let now = chrono::Utc::now().timestamp();
         sqlx::query!(
-            "INSERT INTO user_actions (post_id, action_type, created_at) VALUES (?, ?, ?)",
+            "INSERT INTO user_actions (account_id, post_id, action_type, created_at) VALUES (?, ?, ?, ?)",
+            account_id,
             post_id,
             action_type,
             now
         )
         .execute(pool)
         .await
-        .map_err(|e| CoreError::Configuration(format!("Failed to record user action: {}", e)))?;
+        .map_err(sql_error)?;
 
         Ok(())
     }
 
-    pub async fn get_active_subreddits(&self) -> Result<Vec<SubredditInfo>, CoreError> {
-        let pool = self
-            .pool
-            .as_ref()
-            .ok_or_else(|| CoreError::Configuration("Database not connected".to_string()))?;
+    pub async fn get_active_subreddits(
+        &self,
+        account_id: i64,
+    ) -> Result<Vec<SubredditInfo>, CoreError> {
+        let pool = self.pool.as_ref().ok_or_else(not_connected)?;
 
         let rows = sqlx::query!(
-            "SELECT id, name, is_active, last_fetched_at, created_at, updated_at 
+            r#"SELECT id as "id!", account_id, name, is_active, last_fetched_at, last_seen_post_id,
+                    last_seen_created_utc, resume_after, pending_post_id, pending_created_utc,
+                    created_at, updated_at
              FROM subreddits 
-             WHERE is_active = TRUE 
-             ORDER BY name"
+             WHERE account_id = ? AND is_active = TRUE 
+             ORDER BY name"#,
+            account_id
         )
         .fetch_all(pool)
         .await
-        .map_err(|e| CoreError::Configuration(format!("Failed to fetch subreddits: {}", e)))?;
+        .map_err(sql_error)?;
 
         let subreddits = rows
             .into_iter()
             .map(|row| SubredditInfo {
                 id: Some(row.id),
+                account_id: row.account_id,
                 name: row.name,
                 is_active: row.is_active,
                 last_fetched_at: row.last_fetched_at,
+                last_seen_post_id: row.last_seen_post_id,
+                last_seen_created_utc: row.last_seen_created_utc,
+                resume_after: row.resume_after,
+                pending_post_id: row.pending_post_id,
+                pending_created_utc: row.pending_created_utc,
                 created_at: row.created_at,
                 updated_at: row.updated_at,
             })
@@ -371,27 +574,183 @@ impl Database {
         Ok(subreddits)
     }
 
-    pub async fn update_subreddit_fetch_time(&self, subreddit: &str) -> Result<(), CoreError> {
-        let pool = self
-            .pool
-            .as_ref()
-            .ok_or_else(|| CoreError::Configuration("Database not connected".to_string()))?;
+    pub async fn update_subreddit_fetch_time(
+        &self,
+        account_id: i64,
+        subreddit: &str,
+    ) -> Result<(), CoreError> {
+        let pool = self.pool.as_ref().ok_or_else(not_connected)?;
 
         let now = chrono::Utc::now().timestamp();
         sqlx::query!(
-            "UPDATE subreddits SET last_fetched_at = ?, updated_at = ? WHERE name = ?",
+            "UPDATE subreddits SET last_fetched_at = ?, updated_at = ? WHERE account_id = ? AND name = ?",
             now,
             now,
+            account_id,
             subreddit
         )
         .execute(pool)
         .await
-        .map_err(|e| {
-            CoreError::Configuration(format!("Failed to update subreddit fetch time: {}", e))
-        })?;
+        .map_err(sql_error)?;
 
         Ok(())
     }
+
+    pub async fn get_subreddit_high_water_mark(
+        &self,
+        account_id: i64,
+        subreddit: &str,
+    ) -> Result<SubredditHighWaterMark, CoreError> {
+        let pool = self.pool.as_ref().ok_or_else(not_connected)?;
+
+        let row = sqlx::query!(
+            "SELECT last_seen_post_id, last_seen_created_utc, resume_after, pending_post_id,
+                    pending_created_utc
+             FROM subreddits
+             WHERE account_id = ? AND name = ?",
+            account_id,
+            subreddit
+        )
+        .fetch_optional(pool)
+        .await
+        .map_err(sql_error)?;
+
+        Ok(row
+            .map(|row| SubredditHighWaterMark {
+                post_id: row.last_seen_post_id,
+                created_utc: row.last_seen_created_utc,
+                resume_after: row.resume_after,
+                pending_post_id: row.pending_post_id,
+                pending_created_utc: row.pending_created_utc,
+            })
+            .unwrap_or_default())
+    }
+
+    /// Store the newest post seen in a subreddit, along with any unfinished
+    /// catch-up cursor, and bump its fetch time
+    pub async fn update_subreddit_high_water_mark(
+        &self,
+        account_id: i64,
+        subreddit: &str,
+        mark: &SubredditHighWaterMark,
+    ) -> Result<(), CoreError> {
+        let pool = self.pool.as_ref().ok_or_else(not_connected)?;
+
+        let now = chrono::Utc::now().timestamp();
+        sqlx::query!(
+            "UPDATE subreddits
+             SET last_seen_post_id = ?, last_seen_created_utc = ?, resume_after = ?,
+                 pending_post_id = ?, pending_created_utc = ?, last_fetched_at = ?, updated_at = ?
+             WHERE account_id = ? AND name = ?",
+            mark.post_id,
+            mark.created_utc,
+            mark.resume_after,
+            mark.pending_post_id,
+            mark.pending_created_utc,
+            now,
+            now,
+            account_id,
+            subreddit
+        )
+        .execute(pool)
+        .await
+        .map_err(sql_error)?;
+
+        Ok(())
+    }
+
+    /// Reconcile an account's `subreddits` rows with its Reddit subscriptions.
+    ///
+    /// Names are compared case-insensitively. New subscriptions are added,
+    /// inactive rows the user has rejoined are reactivated, and active rows
+    /// the user has left are deactivated rather than deleted so their
+    /// high-water marks survive a later rejoin.
+    pub async fn sync_subscribed_subreddits(
+        &self,
+        account_id: i64,
+        subscribed: &[&str],
+    ) -> Result<SubredditSyncReport, CoreError> {
+        let pool = self.pool.as_ref().ok_or_else(not_connected)?;
+
+        let mut tx = pool.begin().await.map_err(sql_error)?;
+
+        let rows = sqlx::query!(
+            "SELECT name, is_active FROM subreddits WHERE account_id = ?",
+            account_id
+        )
+        .fetch_all(&mut *tx)
+        .await
+        .map_err(sql_error)?;
+
+        let existing: HashMap<String, (String, bool)> = rows
+            .into_iter()
+            .map(|row| (row.name.to_lowercase(), (row.name, row.is_active)))
+            .collect();
+
+        let now = chrono::Utc::now().timestamp();
+        let mut report = SubredditSyncReport::default();
+        let mut wanted = HashSet::new();
+
+        for name in subscribed {
+            let name = name.trim();
+            let key = name.to_lowercase();
+            if name.is_empty() || !wanted.insert(key.clone()) {
+                continue;
+            }
+
+            match existing.get(&key) {
+                Some((_, true)) => {}
+                Some((stored_name, false)) => {
+                    sqlx::query!(
+                        "UPDATE subreddits SET is_active = TRUE, updated_at = ?
+                         WHERE account_id = ? AND name = ?",
+                        now,
+                        account_id,
+                        stored_name
+                    )
+                    .execute(&mut *tx)
+                    .await
+                    .map_err(sql_error)?;
+                    report.reactivated.push(stored_name.clone());
+                }
+                None => {
+                    sqlx::query!(
+                        "INSERT INTO subreddits (account_id, name, is_active, created_at, updated_at)
+                         VALUES (?, ?, TRUE, ?, ?)",
+                        account_id,
+                        name,
+                        now,
+                        now
+                    )
+                    .execute(&mut *tx)
+                    .await
+                    .map_err(sql_error)?;
+                    report.added.push(name.to_string());
+                }
+            }
+        }
+
+        for (key, (stored_name, is_active)) in &existing {
+            if *is_active && !wanted.contains(key) {
+                sqlx::query!(
+                    "UPDATE subreddits SET is_active = FALSE, updated_at = ?
+                     WHERE account_id = ? AND name = ?",
+                    now,
+                    account_id,
+                    stored_name
+                )
+                .execute(&mut *tx)
+                .await
+                .map_err(sql_error)?;
+                report.deactivated.push(stored_name.clone());
+            }
+        }
+        report.deactivated.sort();
+
+        tx.commit().await.map_err(sql_error)?;
+
+        Ok(report)
+    }
 }
 
 #[cfg(test)]
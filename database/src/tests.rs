@@ -1,43 +1,296 @@
-#[cfg(test)]
-mod tests {
-    use crate::Database;
-    use std::env;
-    use tokio;
-
-    async fn setup_test_db() -> Database {
-        let db_path = env::temp_dir().join(format!("test_likeminded_{}.db", uuid::Uuid::new_v4()));
-        let db_url = format!("sqlite://{}", db_path.display());
-
-        let mut db = Database::new(db_url);
-        db.connect()
-            .await
-            .expect("Failed to connect to test database");
-        db.run_migrations().await.expect("Failed to run migrations");
+use crate::{Database, DEFAULT_ACCOUNT_ID, MIGRATOR};
+use likeminded_core::{Keyword, SubredditHighWaterMark};
+use std::env;
 
-        db
-    }
+async fn connect_test_db() -> Database {
+    let db_path = env::temp_dir().join(format!("test_likeminded_{}.db", uuid::Uuid::new_v4()));
+    let db_url = format!("sqlite://{}", db_path.display());
 
-    #[tokio::test]
-    async fn test_database_connection_and_migrations() {
-        let _db = setup_test_db().await;
+    let mut db = Database::new(db_url);
+    db.connect()
+        .await
+        .expect("Failed to connect to test database");
 
-        // If we get here, the database connection and migrations worked
-        // This is a basic smoke test to ensure the database layer is functional
-        assert!(true);
-    }
+    db
+}
+
+async fn setup_test_db() -> Database {
+    let db = connect_test_db().await;
+    db.run_migrations().await.expect("Failed to run migrations");
+
+    db
+}
+
+/// Versions recorded in `_sqlx_migrations`, and those in `migrations/`
+async fn migration_versions(db: &Database) -> (Vec<i64>, Vec<i64>) {
+    let applied = sqlx::query_scalar(
+        "SELECT version FROM _sqlx_migrations WHERE success = TRUE ORDER BY version",
+    )
+    .fetch_all(db.pool.as_ref().unwrap())
+    .await
+    .expect("Failed to read applied migrations");
+    let available = MIGRATOR.iter().map(|migration| migration.version).collect();
+
+    (applied, available)
+}
+
+#[tokio::test]
+async fn test_database_connection_and_migrations() {
+    let _db = setup_test_db().await;
+
+    // If we get here, the database connection and migrations worked
+    // This is a basic smoke test to ensure the database layer is functional
+}
+
+#[tokio::test]
+async fn test_migrations_only_run_once() {
+    let db = setup_test_db().await;
+
+    let (applied, available) = migration_versions(&db).await;
+    assert_eq!(applied, available);
+
+    db.run_migrations()
+        .await
+        .expect("Failed to run migrations a second time");
+    assert_eq!(migration_versions(&db).await.0, applied);
+    db.save_setting("test_key", "test_value")
+        .await
+        .expect("Failed to save setting");
+}
+
+#[tokio::test]
+async fn test_migrations_upgrade_unversioned_database() {
+    let db = connect_test_db().await;
 
-    #[tokio::test]
-    async fn test_basic_functionality() {
-        let db = setup_test_db().await;
+    // Databases from before migrations were recorded only have the initial schema
+    sqlx::raw_sql(include_str!("../migrations/001_initial_schema.sql"))
+        .execute(db.pool.as_ref().unwrap())
+        .await
+        .expect("Failed to create initial schema");
+    db.save_setting("kept_key", "kept_value")
+        .await
+        .expect("Failed to save setting");
 
-        // Test basic setting operations without using query macros
-        db.save_setting("test_key", "test_value")
+    db.run_migrations().await.expect("Failed to run migrations");
+    db.run_migrations()
+        .await
+        .expect("Failed to run migrations a second time");
+
+    let (applied, available) = migration_versions(&db).await;
+    assert_eq!(applied, available);
+    assert_eq!(
+        db.get_setting("kept_key")
             .await
-            .expect("Failed to save setting");
-        let value = db
-            .get_setting("test_key")
+            .expect("Failed to get setting"),
+        Some("kept_value".to_string())
+    );
+    let subreddits = db
+        .get_active_subreddits(DEFAULT_ACCOUNT_ID)
+        .await
+        .expect("Failed to get subreddits");
+    assert!(subreddits.iter().any(|s| s.name == "rust"));
+
+    let mark = db
+        .get_subreddit_high_water_mark(DEFAULT_ACCOUNT_ID, "rust")
+        .await
+        .expect("Failed to get high-water mark");
+    assert!(mark.is_empty());
+}
+
+#[tokio::test]
+async fn test_basic_functionality() {
+    let db = setup_test_db().await;
+
+    // Test basic setting operations without using query macros
+    db.save_setting("test_key", "test_value")
+        .await
+        .expect("Failed to save setting");
+    let value = db
+        .get_setting("test_key")
+        .await
+        .expect("Failed to get setting");
+    assert_eq!(value, Some("test_value".to_string()));
+}
+
+#[tokio::test]
+async fn test_subreddit_high_water_mark() {
+    let db = setup_test_db().await;
+
+    // Default subreddits start without a mark
+    let mark = db
+        .get_subreddit_high_water_mark(DEFAULT_ACCOUNT_ID, "rust")
+        .await
+        .expect("Failed to get high-water mark");
+    assert!(mark.is_empty());
+
+    let mark = SubredditHighWaterMark::new("abc123".to_string(), 1_700_000_000);
+    db.update_subreddit_high_water_mark(DEFAULT_ACCOUNT_ID, "rust", &mark)
+        .await
+        .expect("Failed to update high-water mark");
+
+    let stored = db
+        .get_subreddit_high_water_mark(DEFAULT_ACCOUNT_ID, "rust")
+        .await
+        .expect("Failed to get high-water mark");
+    assert_eq!(stored, mark);
+
+    let subreddits = db
+        .get_active_subreddits(DEFAULT_ACCOUNT_ID)
+        .await
+        .expect("Failed to get subreddits");
+    let rust = subreddits.iter().find(|s| s.name == "rust").unwrap();
+    assert_eq!(rust.high_water_mark(), mark);
+    assert!(rust.last_fetched_at.is_some());
+
+    // A catch-up cursor survives the round trip
+    let mark = SubredditHighWaterMark {
+        resume_after: Some("t3_def456".to_string()),
+        pending_post_id: Some("ghi789".to_string()),
+        pending_created_utc: Some(1_700_000_600),
+        ..mark
+    };
+    db.update_subreddit_high_water_mark(DEFAULT_ACCOUNT_ID, "rust", &mark)
+        .await
+        .expect("Failed to update high-water mark");
+    let stored = db
+        .get_subreddit_high_water_mark(DEFAULT_ACCOUNT_ID, "rust")
+        .await
+        .expect("Failed to get high-water mark");
+    assert_eq!(stored, mark);
+}
+
+#[tokio::test]
+async fn test_sync_subscribed_subreddits() {
+    let db = setup_test_db().await;
+
+    // Defaults are rust, programming and MachineLearning
+    let report = db
+        .sync_subscribed_subreddits(DEFAULT_ACCOUNT_ID, &["Rust", "machinelearning", "golang"])
+        .await
+        .expect("Failed to sync subreddits");
+    assert_eq!(report.added, vec!["golang".to_string()]);
+    assert!(report.reactivated.is_empty());
+    assert_eq!(report.deactivated, vec!["programming".to_string()]);
+
+    let active: Vec<String> = db
+        .get_active_subreddits(DEFAULT_ACCOUNT_ID)
+        .await
+        .expect("Failed to get subreddits")
+        .into_iter()
+        .map(|s| s.name)
+        .collect();
+    assert_eq!(active, vec!["MachineLearning", "golang", "rust"]);
+
+    // Rejoining reactivates the existing row
+    let report = db
+        .sync_subscribed_subreddits(
+            DEFAULT_ACCOUNT_ID,
+            &["rust", "MachineLearning", "golang", "programming"],
+        )
+        .await
+        .expect("Failed to sync subreddits");
+    assert_eq!(report.reactivated, vec!["programming".to_string()]);
+    assert!(report.added.is_empty());
+    assert!(report.deactivated.is_empty());
+
+    let report = db
+        .sync_subscribed_subreddits(
+            DEFAULT_ACCOUNT_ID,
+            &["rust", "MachineLearning", "golang", "programming"],
+        )
+        .await
+        .expect("Failed to sync subreddits");
+    assert!(report.is_unchanged());
+}
+
+#[tokio::test]
+async fn test_accounts_have_separate_subreddits() {
+    let db = setup_test_db().await;
+
+    let alice = db
+        .add_reddit_account("alice")
+        .await
+        .expect("Failed to add account");
+    assert_ne!(alice, DEFAULT_ACCOUNT_ID);
+    assert_eq!(
+        db.add_reddit_account("Alice")
             .await
-            .expect("Failed to get setting");
-        assert_eq!(value, Some("test_value".to_string()));
+            .expect("Failed to add account"),
+        alice
+    );
+
+    db.sync_subscribed_subreddits(alice, &["rust", "golang"])
+        .await
+        .expect("Failed to sync subreddits");
+    let mark = SubredditHighWaterMark::new("abc123".to_string(), 1_700_000_000);
+    db.update_subreddit_high_water_mark(alice, "rust", &mark)
+        .await
+        .expect("Failed to update high-water mark");
+
+    // The default account keeps its own rows and marks
+    let default_names: Vec<String> = db
+        .get_active_subreddits(DEFAULT_ACCOUNT_ID)
+        .await
+        .expect("Failed to get subreddits")
+        .into_iter()
+        .map(|s| s.name)
+        .collect();
+    assert_eq!(
+        default_names,
+        vec!["MachineLearning", "programming", "rust"]
+    );
+    assert!(db
+        .get_subreddit_high_water_mark(DEFAULT_ACCOUNT_ID, "rust")
+        .await
+        .expect("Failed to get high-water mark")
+        .is_empty());
+
+    let alice_subreddits = db
+        .get_active_subreddits(alice)
+        .await
+        .expect("Failed to get subreddits");
+    assert_eq!(alice_subreddits.len(), 2);
+    assert!(alice_subreddits.iter().all(|s| s.account_id == alice));
+
+    db.remove_reddit_account(alice)
+        .await
+        .expect("Failed to remove account");
+    assert!(db
+        .get_active_subreddits(alice)
+        .await
+        .expect("Failed to get subreddits")
+        .is_empty());
+    let accounts = db
+        .get_reddit_accounts()
+        .await
+        .expect("Failed to get accounts");
+    assert_eq!(accounts.len(), 1);
+    assert_eq!(accounts[0].id, DEFAULT_ACCOUNT_ID);
+}
+
+#[tokio::test]
+async fn test_keyword_reddit_search_flag() {
+    let db = setup_test_db().await;
+
+    for (text, search_reddit) in [("rust async", true), ("tokio", false)] {
+        db.save_keyword(&Keyword {
+            id: None,
+            text: text.to_string(),
+            embedding: None,
+            search_reddit,
+            created_at: 0,
+        })
+        .await
+        .expect("Failed to save keyword");
     }
+
+    let keywords = db.get_keywords().await.expect("Failed to get keywords");
+    let flag = |text: &str| {
+        keywords
+            .iter()
+            .find(|keyword| keyword.text == text)
+            .map(|keyword| keyword.search_reddit)
+    };
+    assert_eq!(flag("rust async"), Some(true));
+    assert_eq!(flag("tokio"), Some(false));
 }
@@ -74,6 +74,11 @@ pub enum RedditApiError {
     #[error("Invalid OAuth token")]
     InvalidToken,
 
+    /// The refresh token was revoked or has expired; the user must connect
+    /// their Reddit account again
+    #[error("Refresh token revoked or expired")]
+    RefreshTokenRevoked,
+
     #[error("API endpoint unavailable: {endpoint}")]
     EndpointUnavailable { endpoint: String },
 
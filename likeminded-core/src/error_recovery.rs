@@ -2,10 +2,18 @@
 //!
 //! This module provides utilities for recovering from errors in a systematic way,
 //! including retry mechanisms, fallback strategies, and graceful degradation.
-
-use crate::{CoreError, ErrorExt};
+//! Retries go through the same [`RetryPolicy`] machinery the API clients use;
+//! fallbacks and degraded modes are registered per call site with
+//! [`RecoveryHandlers`].
+
+use crate::{
+    retry_with_options, Backoff, CoreError, RetryOptions, RetryPolicy, StandardRetryPolicy,
+};
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
 use std::time::Duration;
-use tracing::info;
+use tracing::{info, warn};
 
 /// Recovery strategy for handling errors
 #[derive(Debug, Clone)]
@@ -77,6 +85,96 @@ impl<T> RecoveryResult<T> {
     }
 }
 
+/// Boxed future returned by a recovery handler
+pub type RecoveryFuture<T> = Pin<Box<dyn Future<Output = Result<T, CoreError>> + Send>>;
+
+type RecoveryHandler<T> = Box<dyn Fn(&CoreError) -> RecoveryFuture<T> + Send + Sync>;
+
+/// Fallbacks and degraded-mode producers for one call site.
+///
+/// A fallback stands in for the operation with an equivalent result, e.g. a
+/// cached listing when Reddit is down. A degraded producer gives a lesser
+/// one, e.g. keyword-only matching when embeddings fail. Handlers get the
+/// error that made the operation give up.
+pub struct RecoveryHandlers<T> {
+    fallbacks: Vec<(String, RecoveryHandler<T>)>,
+    degraded: Vec<(String, RecoveryHandler<T>)>,
+}
+
+impl<T> RecoveryHandlers<T> {
+    pub fn new() -> Self {
+        Self {
+            fallbacks: Vec::new(),
+            degraded: Vec::new(),
+        }
+    }
+
+    /// Add a fallback. Fallbacks are tried in the order they were added.
+    pub fn with_fallback<F, Fut>(mut self, name: &str, fallback: F) -> Self
+    where
+        F: Fn(&CoreError) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<T, CoreError>> + Send + 'static,
+    {
+        self.fallbacks.push((
+            name.to_string(),
+            Box::new(move |error| Box::pin(fallback(error))),
+        ));
+        self
+    }
+
+    /// Add a degraded-mode producer, tried after every fallback has failed
+    pub fn with_degraded<F, Fut>(mut self, name: &str, producer: F) -> Self
+    where
+        F: Fn(&CoreError) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<T, CoreError>> + Send + 'static,
+    {
+        self.degraded.push((
+            name.to_string(),
+            Box::new(move |error| Box::pin(producer(error))),
+        ));
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.fallbacks.is_empty() && self.degraded.is_empty()
+    }
+
+    /// Run the handlers in turn until one succeeds. Gives back `error` if
+    /// none do.
+    async fn recover(&self, error: CoreError) -> RecoveryResult<T> {
+        for (name, handler) in self.fallbacks.iter().chain(&self.degraded) {
+            match handler(&error).await {
+                Ok(value) => {
+                    info!("Recovered from '{}' with {}", error, name);
+                    return RecoveryResult::Degraded(value);
+                }
+                Err(handler_error) => {
+                    warn!("Recovery handler {} failed: {}", name, handler_error);
+                }
+            }
+        }
+        RecoveryResult::Failed(error)
+    }
+}
+
+impl<T> Default for RecoveryHandlers<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> fmt::Debug for RecoveryHandlers<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let names = |handlers: &[(String, RecoveryHandler<T>)]| -> Vec<String> {
+            handlers.iter().map(|(name, _)| name.clone()).collect()
+        };
+        f.debug_struct("RecoveryHandlers")
+            .field("fallbacks", &names(&self.fallbacks))
+            .field("degraded", &names(&self.degraded))
+            .finish()
+    }
+}
+
 /// Error recovery handler that provides strategies for different error types
 pub struct ErrorRecovery;
 
@@ -168,99 +266,81 @@ impl ErrorRecovery {
         }
     }
 
-    /// Apply the recovery strategy to an operation
+    /// Apply the recovery strategy to an operation, without fallbacks or
+    /// degraded modes
     pub async fn apply_strategy<F, T, Fut>(
+        strategy: RecoveryStrategy,
+        operation: F,
+    ) -> RecoveryResult<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: std::future::Future<Output = Result<T, CoreError>> + Send,
+        T: Send,
+    {
+        Self::apply_strategy_with(strategy, operation, &RecoveryHandlers::new()).await
+    }
+
+    /// Apply the recovery strategy to an operation. When the operation
+    /// fails for good under `RetryWithBackoff`, `Fallback` or `Degrade`, the
+    /// registered handlers are run and a value they produce is reported as
+    /// [`RecoveryResult::Degraded`].
+    pub async fn apply_strategy_with<F, T, Fut>(
         strategy: RecoveryStrategy,
         mut operation: F,
+        handlers: &RecoveryHandlers<T>,
     ) -> RecoveryResult<T>
     where
         F: FnMut() -> Fut,
         Fut: std::future::Future<Output = Result<T, CoreError>> + Send,
         T: Send,
     {
-        match strategy {
+        let result = match strategy {
             RecoveryStrategy::RetryWithBackoff {
                 max_attempts,
                 initial_delay,
                 max_delay,
-            } => Self::retry_with_backoff(operation, max_attempts, initial_delay, max_delay).await,
-            RecoveryStrategy::Fallback => {
-                // For now, we'll treat fallback as fail since we don't have specific fallback logic
-                // In a real implementation, this would try alternative approaches
-                RecoveryResult::Failed(CoreError::Internal {
-                    message: "Fallback strategy not implemented".to_string(),
-                })
-            }
-            RecoveryStrategy::Skip => RecoveryResult::Skipped,
-            RecoveryStrategy::Degrade => {
-                // For now, we'll treat degrade as fail since we don't have specific degradation logic
-                // In a real implementation, this would provide reduced functionality
-                RecoveryResult::Failed(CoreError::Internal {
-                    message: "Degradation strategy not implemented".to_string(),
-                })
+            } => {
+                // `max_delay` also caps delays the server asks for
+                let policy = StandardRetryPolicy::new(
+                    max_attempts as u32,
+                    Backoff::Exponential {
+                        base: initial_delay,
+                        max: max_delay,
+                        multiplier: 2.0,
+                        jitter_factor: 0.0,
+                    },
+                )
+                .with_max_delay(max_delay);
+                Self::retry_with_policy(&policy, operation).await
             }
+            RecoveryStrategy::Skip => return RecoveryResult::Skipped,
             RecoveryStrategy::Fail => {
                 // Execute the operation once and fail if it errors
-                match operation().await {
+                return match operation().await {
                     Ok(value) => RecoveryResult::Recovered(value),
                     Err(error) => RecoveryResult::Failed(error),
-                }
+                };
             }
+            RecoveryStrategy::Fallback | RecoveryStrategy::Degrade => operation().await,
+        };
+
+        match result {
+            Ok(value) => RecoveryResult::Recovered(value),
+            Err(error) => handlers.recover(error).await,
         }
     }
 
-    /// Retry an operation with exponential backoff
-    async fn retry_with_backoff<F, T, Fut>(
-        mut operation: F,
-        max_attempts: usize,
-        initial_delay: Duration,
-        max_delay: Duration,
-    ) -> RecoveryResult<T>
+    /// Run an operation until it succeeds or `policy` gives up
+    pub async fn retry_with_policy<F, T, Fut>(
+        policy: &dyn RetryPolicy,
+        operation: F,
+    ) -> Result<T, CoreError>
     where
         F: FnMut() -> Fut,
         Fut: std::future::Future<Output = Result<T, CoreError>> + Send,
         T: Send,
     {
-        let mut attempt = 0;
-        let mut delay = initial_delay;
-
-        loop {
-            match operation().await {
-                Ok(result) => return RecoveryResult::Recovered(result),
-                Err(error) => {
-                    attempt += 1;
-
-                    // If we've exhausted all attempts or the error is not retryable, fail
-                    if attempt >= max_attempts || !error.is_retryable() {
-                        return RecoveryResult::Failed(error);
-                    }
-
-                    // Use the error's suggested retry delay if available
-                    if let Some(retry_delay) = error.retry_after() {
-                        delay = retry_delay;
-                    }
-
-                    // Cap the delay at the maximum
-                    if delay > max_delay {
-                        delay = max_delay;
-                    }
-
-                    info!(
-                        "Recovery attempt {}/{} failed. Retrying after {:?}: {}",
-                        attempt,
-                        max_attempts,
-                        delay,
-                        error.user_friendly_message()
-                    );
-
-                    // Wait before retrying
-                    tokio::time::sleep(delay).await;
-
-                    // Exponential backoff (double the delay, capped at max_delay)
-                    delay = std::cmp::min(delay * 2, max_delay);
-                }
-            }
-        }
+        retry_with_options(operation, policy, RetryOptions::new()).await
     }
 }
 
@@ -286,6 +366,29 @@ mod tests {
         assert!(result.is_failed());
     }
 
+    #[tokio::test]
+    async fn test_retry_with_backoff_caps_server_delay() {
+        let strategy = RecoveryStrategy::RetryWithBackoff {
+            max_attempts: 2,
+            initial_delay: Duration::from_millis(1),
+            max_delay: Duration::from_millis(10),
+        };
+
+        // An hour-long retry_after waits max_delay instead
+        let result: RecoveryResult<&str> = tokio::time::timeout(
+            Duration::from_secs(1),
+            ErrorRecovery::apply_strategy(strategy, || async {
+                Err(CoreError::RedditApi(RedditApiError::RateLimitExceeded {
+                    retry_after: 3600,
+                }))
+            }),
+        )
+        .await
+        .unwrap();
+
+        assert!(result.is_failed());
+    }
+
     #[tokio::test]
     async fn test_skip_strategy() {
         let strategy = RecoveryStrategy::Skip;
@@ -299,6 +402,54 @@ mod tests {
         assert!(result.is_skipped());
     }
 
+    #[tokio::test]
+    async fn test_fallback_reports_degraded() {
+        let handlers = RecoveryHandlers::new()
+            .with_fallback("cache", |_| async {
+                Err(CoreError::NotFound {
+                    resource: "cached listing".to_string(),
+                })
+            })
+            .with_degraded("keyword matching", |error| {
+                let reason = error.to_string();
+                async move { Ok(format!("keywords only ({})", reason)) }
+            });
+
+        let result = ErrorRecovery::apply_strategy_with(
+            RecoveryStrategy::Fallback,
+            || async { Err(CoreError::RedditApi(RedditApiError::RequestTimeout)) },
+            &handlers,
+        )
+        .await;
+        assert!(result.is_degraded());
+        assert_eq!(
+            result.unwrap(),
+            "keywords only (Reddit API error: Request timeout)"
+        );
+
+        let result = ErrorRecovery::apply_strategy_with(
+            RecoveryStrategy::Degrade,
+            || async { Ok("live".to_string()) },
+            &handlers,
+        )
+        .await;
+        assert!(result.is_recovered());
+    }
+
+    #[tokio::test]
+    async fn test_degrade_without_handlers_keeps_error() {
+        let result: RecoveryResult<&str> =
+            ErrorRecovery::apply_strategy(RecoveryStrategy::Degrade, || async {
+                Err(CoreError::RedditApi(RedditApiError::InvalidToken))
+            })
+            .await;
+
+        assert!(matches!(
+            result.err(),
+            Some(CoreError::RedditApi(RedditApiError::InvalidToken))
+        ));
+    }
+
     #[tokio::test]
     async fn test_determine_strategy() {
         // Test with a simple IO error since it's easier to create
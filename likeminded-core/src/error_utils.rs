@@ -1,5 +1,8 @@
 use crate::error::*;
-use std::time::Duration;
+use crate::retry_policy::{Backoff, RetryPolicy, RetryState};
+use std::future::Future;
+use std::time::{Duration, Instant};
+pub use tokio_util::sync::CancellationToken;
 use tracing::{error, info, warn};
 
 pub trait ErrorExt {
@@ -171,6 +174,10 @@ impl ErrorExt for RedditApiError {
             RedditApiError::InvalidToken => {
                 "Reddit authentication token is invalid. Please re-authenticate.".to_string()
             }
+            RedditApiError::RefreshTokenRevoked => {
+                "Reddit access was revoked or has expired. Please reconnect your Reddit account."
+                    .to_string()
+            }
             RedditApiError::RequestTimeout => {
                 "Request to Reddit timed out. Please try again.".to_string()
             }
@@ -186,6 +193,7 @@ impl ErrorExt for RedditApiError {
             RedditApiError::SubredditNotFound { .. } => "REDDIT_SUBREDDIT_NOT_FOUND".to_string(),
             RedditApiError::PostNotFound { .. } => "REDDIT_POST_NOT_FOUND".to_string(),
             RedditApiError::InvalidToken => "REDDIT_INVALID_TOKEN".to_string(),
+            RedditApiError::RefreshTokenRevoked => "REDDIT_REFRESH_TOKEN_REVOKED".to_string(),
             RedditApiError::EndpointUnavailable { .. } => "REDDIT_ENDPOINT_UNAVAILABLE".to_string(),
             RedditApiError::RequestTimeout => "REDDIT_TIMEOUT".to_string(),
             RedditApiError::InvalidResponse { .. } => "REDDIT_INVALID_RESPONSE".to_string(),
@@ -502,41 +510,186 @@ impl Default for ErrorReporter {
     }
 }
 
-pub async fn retry_with_backoff<F, T, E>(
+/// What happened on one attempt of [`retry_with_options`]
+#[derive(Debug)]
+pub struct RetryAttempt<'a> {
+    /// Attempt number, starting at 1
+    pub attempt: u32,
+    pub error: &'a CoreError,
+    /// Delay before the next attempt, or `None` when giving up
+    pub next_delay: Option<Duration>,
+}
+
+type AttemptCallback = Box<dyn Fn(&RetryAttempt<'_>) + Send + Sync>;
+
+/// Hooks for [`retry_with_options`]. When and how often to retry is up to the
+/// [`RetryPolicy`] passed alongside.
+#[derive(Default)]
+pub struct RetryOptions {
+    cancellation: Option<CancellationToken>,
+    on_attempt: Option<AttemptCallback>,
+}
+
+impl RetryOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Stop retrying once `token` is cancelled. An attempt already running
+    /// is left to finish; the last error is returned.
+    pub fn with_cancellation(mut self, token: CancellationToken) -> Self {
+        self.cancellation = Some(token);
+        self
+    }
+
+    /// Call `on_attempt` after every failed attempt
+    pub fn with_on_attempt<F>(mut self, on_attempt: F) -> Self
+    where
+        F: Fn(&RetryAttempt<'_>) + Send + Sync + 'static,
+    {
+        self.on_attempt = Some(Box::new(on_attempt));
+        self
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.cancellation
+            .as_ref()
+            .is_some_and(|token| token.is_cancelled())
+    }
+}
+
+impl std::fmt::Debug for RetryOptions {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RetryOptions")
+            .field("cancellation", &self.cancellation)
+            .field("on_attempt", &self.on_attempt.is_some())
+            .finish()
+    }
+}
+
+/// Retry an async operation up to `max_retries` times with delays doubling
+/// from `initial_delay`, capped at 60s, with 10% jitter. Works with any error
+/// type implementing [`ErrorExt`]: errors that are not `is_retryable` are
+/// returned at once, and a `retry_after` replaces the backoff delay, capped
+/// at 60s too.
+pub async fn retry_with_backoff<F, Fut, T, E>(
     mut operation: F,
     max_retries: usize,
     initial_delay: Duration,
 ) -> Result<T, E>
 where
-    F: FnMut() -> Result<T, E>,
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, E>>,
     E: ErrorExt,
 {
+    let max_delay = Duration::from_secs(60);
+    let backoff = Backoff::Exponential {
+        base: initial_delay,
+        max: max_delay,
+        multiplier: 2.0,
+        jitter_factor: 0.1,
+    };
+    let mut previous_delay = None;
     let mut attempt = 0;
-    let mut delay = initial_delay;
 
     loop {
-        match operation() {
+        let error = match operation().await {
             Ok(result) => return Ok(result),
-            Err(error) => {
-                if attempt >= max_retries || !error.is_retryable() {
-                    return Err(error);
-                }
+            Err(error) => error,
+        };
+        if attempt >= max_retries || !error.is_retryable() {
+            return Err(error);
+        }
 
-                if let Some(retry_delay) = error.retry_after() {
-                    delay = retry_delay;
-                }
+        let delay = error
+            .retry_after()
+            .unwrap_or_else(|| backoff.delay(attempt as u32, previous_delay))
+            .min(max_delay);
+        attempt += 1;
 
-                info!(
-                    "Retrying operation (attempt {}/{}) after {:?}",
-                    attempt + 1,
-                    max_retries,
-                    delay
-                );
+        info!(
+            "Retrying operation (attempt {}/{}) after {:?}: {}",
+            attempt,
+            max_retries,
+            delay,
+            error.user_friendly_message()
+        );
 
-                tokio::time::sleep(delay).await;
-                delay = std::cmp::min(delay * 2, Duration::from_secs(60)); // Exponential backoff with max 60s
-                attempt += 1;
+        tokio::time::sleep(delay).await;
+        previous_delay = Some(delay);
+    }
+}
+
+/// Run an async operation until it succeeds, `policy` gives up or the
+/// cancellation token in `options` fires
+pub async fn retry_with_options<F, Fut, T>(
+    mut operation: F,
+    policy: &dyn RetryPolicy,
+    options: RetryOptions,
+) -> Result<T, CoreError>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, CoreError>>,
+{
+    let max_attempts = policy.max_attempts().max(1);
+    let start_time = Instant::now();
+    let mut previous_delay = None;
+    let mut attempt = 0;
+
+    loop {
+        let error = match operation().await {
+            Ok(result) => {
+                policy.record_success();
+                return Ok(result);
+            }
+            Err(error) => error,
+        };
+        attempt += 1;
+
+        let next_delay = if attempt >= max_attempts || options.is_cancelled() {
+            None
+        } else {
+            let state = RetryState {
+                attempt,
+                elapsed: start_time.elapsed(),
+                previous_delay,
+            };
+            policy.next_delay(&state, &error)
+        };
+
+        if let Some(ref on_attempt) = options.on_attempt {
+            on_attempt(&RetryAttempt {
+                attempt,
+                error: &error,
+                next_delay,
+            });
+        }
+
+        let Some(next_delay) = next_delay else {
+            return Err(error);
+        };
+
+        info!(
+            "Attempt {}/{} failed. Retrying after {:?}: {}",
+            attempt,
+            max_attempts,
+            next_delay,
+            error.user_friendly_message()
+        );
+
+        match options.cancellation {
+            Some(ref token) => {
+                tokio::select! {
+                    _ = token.cancelled() => {
+                        info!("Retry cancelled after {} attempts", attempt);
+                        return Err(error);
+                    }
+                    _ = tokio::time::sleep(next_delay) => {}
+                }
             }
+            None => tokio::time::sleep(next_delay).await,
         }
+
+        previous_delay = Some(next_delay);
     }
 }
@@ -1,9 +1,11 @@
 pub mod error;
 pub mod error_recovery;
 pub mod error_utils;
+pub mod retry_policy;
 pub mod types;
 
 pub use error::*;
 pub use error_recovery::*;
 pub use error_utils::*;
+pub use retry_policy::*;
 pub use types::*;
@@ -0,0 +1,426 @@
+//! Retry policies shared by the API clients.
+//!
+//! A [`RetryPolicy`] decides, after each failed attempt, whether to try again
+//! and how long to wait first. Callers count attempts themselves and ask the
+//! policy for [`RetryPolicy::max_attempts`] only as a default.
+//!
+//! [`StandardRetryPolicy`] covers the common cases: exponential or
+//! decorrelated jitter backoff, an overall deadline, a [`RetryBudget`] shared
+//! between callers so a failing dependency cannot have its traffic multiplied
+//! by retries, and per-error overrides on top of a classifier.
+
+use crate::{CoreError, ErrorExt, LlmError, RedditApiError};
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::{debug, warn};
+
+/// Retry strategy based on error type
+#[derive(Debug, Clone, PartialEq)]
+pub enum RetryStrategy {
+    /// Retry with the policy's backoff
+    Retry,
+    /// Retry after a specific delay (for rate limits with a retry-after)
+    RetryWithDelay(Duration),
+    /// Don't retry (for permanent failures)
+    NoRetry,
+}
+
+/// Classify an error with [`ErrorExt::is_retryable`]. Rate limits wait for
+/// the delay the server asked for; everything else uses the backoff.
+pub fn default_retry_strategy(error: &CoreError) -> RetryStrategy {
+    match error {
+        _ if !error.is_retryable() => RetryStrategy::NoRetry,
+        CoreError::RedditApi(RedditApiError::RateLimitExceeded { .. })
+        | CoreError::Llm(LlmError::RateLimitExceeded { .. })
+        | CoreError::RateLimited {
+            retry_after: Some(_),
+            ..
+        } => error
+            .retry_after()
+            .map_or(RetryStrategy::Retry, RetryStrategy::RetryWithDelay),
+        _ => RetryStrategy::Retry,
+    }
+}
+
+/// Where a retry loop stands when an attempt has failed
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RetryState {
+    /// Attempts made so far, including the one that just failed
+    pub attempt: u32,
+    /// Time since the first attempt started
+    pub elapsed: Duration,
+    /// Delay slept before the attempt that just failed, if it was a retry
+    pub previous_delay: Option<Duration>,
+}
+
+pub trait RetryPolicy: fmt::Debug + Send + Sync {
+    /// Attempts per operation, counting the first, unless the caller
+    /// overrides it
+    fn max_attempts(&self) -> u32;
+
+    /// How long to wait before retrying after `error`, or `None` to give up
+    fn next_delay(&self, state: &RetryState, error: &CoreError) -> Option<Duration>;
+
+    /// Called when an attempt succeeds
+    fn record_success(&self) {}
+}
+
+/// How the delay grows between retries
+#[derive(Debug, Clone, PartialEq)]
+pub enum Backoff {
+    /// `base * multiplier^retry` capped at `max`, plus up to `jitter_factor`
+    /// of that delay at random
+    Exponential {
+        base: Duration,
+        max: Duration,
+        multiplier: f64,
+        jitter_factor: f64,
+    },
+    /// A random delay between `base` and three times the previous delay,
+    /// capped at `max`. Spreads out clients that failed at the same moment
+    /// better than proportional jitter does.
+    DecorrelatedJitter { base: Duration, max: Duration },
+}
+
+impl Backoff {
+    /// Delay before retry number `retry`, counting from 0
+    pub fn delay(&self, retry: u32, previous_delay: Option<Duration>) -> Duration {
+        match *self {
+            Backoff::Exponential {
+                base,
+                max,
+                multiplier,
+                jitter_factor,
+            } => {
+                let delay_ms = base.as_millis() as f64 * multiplier.powi(retry as i32);
+                let delay = Duration::from_millis(delay_ms.min(max.as_millis() as f64) as u64);
+
+                let jitter_range = (delay.as_millis() as f64 * jitter_factor) as u64;
+                let jitter = Duration::from_millis(fastrand::u64(0..=jitter_range));
+                (delay + jitter).min(max)
+            }
+            Backoff::DecorrelatedJitter { base, max } => {
+                let base_ms = base.as_millis() as u64;
+                let previous_ms = previous_delay.unwrap_or(base).as_millis() as u64;
+                let upper_ms = previous_ms.saturating_mul(3).max(base_ms);
+                Duration::from_millis(fastrand::u64(base_ms..=upper_ms)).min(max)
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+struct BudgetState {
+    tokens: f64,
+    last_refill: Instant,
+}
+
+/// Token bucket limiting retries across everything that shares it. Each
+/// retry spends a token; tokens come back over time and with every success.
+#[derive(Debug)]
+pub struct RetryBudget {
+    capacity: f64,
+    refill_per_second: f64,
+    deposit_per_success: f64,
+    state: Mutex<BudgetState>,
+}
+
+impl RetryBudget {
+    /// A full budget of `capacity` retries, refilled at `refill_per_second`
+    pub fn new(capacity: u32, refill_per_second: f64) -> Self {
+        Self {
+            capacity: capacity as f64,
+            refill_per_second,
+            deposit_per_success: 0.0,
+            state: Mutex::new(BudgetState {
+                tokens: capacity as f64,
+                last_refill: Instant::now(),
+            }),
+        }
+    }
+
+    /// Also return `tokens` to the budget for every successful attempt, so
+    /// retries stay proportional to healthy traffic
+    pub fn with_success_deposit(mut self, tokens: f64) -> Self {
+        self.deposit_per_success = tokens;
+        self
+    }
+
+    fn refill(&self, state: &mut BudgetState) {
+        let elapsed = state.last_refill.elapsed().as_secs_f64();
+        state.tokens = (state.tokens + elapsed * self.refill_per_second).min(self.capacity);
+        state.last_refill = Instant::now();
+    }
+
+    /// Spend a token for one retry, if there is one
+    pub fn try_withdraw(&self) -> bool {
+        let mut state = self.state.lock().unwrap();
+        self.refill(&mut state);
+        if state.tokens >= 1.0 {
+            state.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+
+    pub fn deposit(&self) {
+        let mut state = self.state.lock().unwrap();
+        self.refill(&mut state);
+        state.tokens = (state.tokens + self.deposit_per_success).min(self.capacity);
+    }
+
+    /// Retries the budget would allow right now
+    pub fn available(&self) -> u32 {
+        let mut state = self.state.lock().unwrap();
+        self.refill(&mut state);
+        state.tokens as u32
+    }
+}
+
+/// Matches the errors a [`StandardRetryPolicy::with_override`] applies to
+pub type ErrorMatcher = fn(&CoreError) -> bool;
+
+#[derive(Debug, Clone)]
+pub struct StandardRetryPolicy {
+    max_attempts: u32,
+    backoff: Backoff,
+    max_delay: Option<Duration>,
+    deadline: Option<Duration>,
+    deadline_at: Option<Instant>,
+    budget: Option<Arc<RetryBudget>>,
+    classifier: fn(&CoreError) -> RetryStrategy,
+    overrides: Vec<(ErrorMatcher, RetryStrategy)>,
+}
+
+impl StandardRetryPolicy {
+    /// Classifies errors with [`default_retry_strategy`]
+    pub fn new(max_attempts: u32, backoff: Backoff) -> Self {
+        Self {
+            max_attempts: max_attempts.max(1),
+            backoff,
+            max_delay: None,
+            deadline: None,
+            deadline_at: None,
+            budget: None,
+            classifier: default_retry_strategy,
+            overrides: Vec::new(),
+        }
+    }
+
+    /// Never wait longer than `max_delay`, even when the classifier asks for
+    /// a longer delay, e.g. a server's `retry_after`
+    pub fn with_max_delay(mut self, max_delay: Duration) -> Self {
+        self.max_delay = Some(max_delay);
+        self
+    }
+
+    /// Give up once an operation has spent `deadline` on attempts and
+    /// delays, counting the delay that would come next
+    pub fn with_deadline(mut self, deadline: Duration) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    /// Give up on any retry that would start after `deadline_at`, e.g. the
+    /// end of a poll cycle
+    pub fn with_deadline_at(mut self, deadline_at: Instant) -> Self {
+        self.deadline_at = Some(deadline_at);
+        self
+    }
+
+    pub fn with_budget(mut self, budget: Arc<RetryBudget>) -> Self {
+        self.budget = Some(budget);
+        self
+    }
+
+    pub fn with_classifier(mut self, classifier: fn(&CoreError) -> RetryStrategy) -> Self {
+        self.classifier = classifier;
+        self
+    }
+
+    /// Use `strategy` for errors matching `matcher` instead of asking the
+    /// classifier. Overrides are checked in the order they were added.
+    pub fn with_override(mut self, matcher: ErrorMatcher, strategy: RetryStrategy) -> Self {
+        self.overrides.push((matcher, strategy));
+        self
+    }
+
+    pub fn backoff(&self) -> &Backoff {
+        &self.backoff
+    }
+
+    pub fn strategy_for(&self, error: &CoreError) -> RetryStrategy {
+        self.overrides
+            .iter()
+            .find(|(matcher, _)| matcher(error))
+            .map(|(_, strategy)| strategy.clone())
+            .unwrap_or_else(|| (self.classifier)(error))
+    }
+}
+
+impl RetryPolicy for StandardRetryPolicy {
+    fn max_attempts(&self) -> u32 {
+        self.max_attempts
+    }
+
+    fn next_delay(&self, state: &RetryState, error: &CoreError) -> Option<Duration> {
+        let delay = match self.strategy_for(error) {
+            RetryStrategy::NoRetry => return None,
+            RetryStrategy::RetryWithDelay(delay) => delay,
+            RetryStrategy::Retry => self
+                .backoff
+                .delay(state.attempt.saturating_sub(1), state.previous_delay),
+        };
+        let delay = self
+            .max_delay
+            .map_or(delay, |max_delay| delay.min(max_delay));
+
+        if let Some(deadline) = self.deadline {
+            if state.elapsed + delay > deadline {
+                debug!(
+                    "Not retrying: {:?} elapsed plus {:?} delay passes the {:?} deadline",
+                    state.elapsed, delay, deadline
+                );
+                return None;
+            }
+        }
+        if let Some(deadline_at) = self.deadline_at {
+            if Instant::now() + delay > deadline_at {
+                debug!("Not retrying: a {:?} delay passes the deadline", delay);
+                return None;
+            }
+        }
+
+        if let Some(ref budget) = self.budget {
+            if !budget.try_withdraw() {
+                warn!("Not retrying: retry budget exhausted");
+                return None;
+            }
+        }
+
+        Some(delay)
+    }
+
+    fn record_success(&self) {
+        if let Some(ref budget) = self.budget {
+            budget.deposit();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn failed(attempt: u32, elapsed: Duration) -> RetryState {
+        RetryState {
+            attempt,
+            elapsed,
+            previous_delay: None,
+        }
+    }
+
+    fn fixed_backoff() -> Backoff {
+        Backoff::Exponential {
+            base: Duration::from_millis(100),
+            max: Duration::from_secs(10),
+            multiplier: 2.0,
+            jitter_factor: 0.0,
+        }
+    }
+
+    #[test]
+    fn test_decorrelated_jitter_stays_in_range() {
+        let backoff = Backoff::DecorrelatedJitter {
+            base: Duration::from_millis(100),
+            max: Duration::from_secs(1),
+        };
+
+        for _ in 0..100 {
+            let delay = backoff.delay(3, Some(Duration::from_millis(200)));
+            assert!(delay >= Duration::from_millis(100));
+            assert!(delay <= Duration::from_millis(600));
+        }
+        assert!(backoff.delay(5, Some(Duration::from_secs(5))) <= Duration::from_secs(1));
+    }
+
+    #[test]
+    fn test_deadline_and_overrides() {
+        let timeout = CoreError::RedditApi(RedditApiError::RequestTimeout);
+        let rate_limited =
+            CoreError::RedditApi(RedditApiError::RateLimitExceeded { retry_after: 5 });
+        let server_error = CoreError::RedditApi(RedditApiError::ServerError { status_code: 503 });
+        let policy = StandardRetryPolicy::new(5, fixed_backoff())
+            .with_deadline(Duration::from_secs(1))
+            .with_override(
+                |error| {
+                    matches!(
+                        error,
+                        CoreError::RedditApi(RedditApiError::ServerError { .. })
+                    )
+                },
+                RetryStrategy::NoRetry,
+            );
+
+        assert_eq!(
+            policy.next_delay(&failed(2, Duration::ZERO), &timeout),
+            Some(Duration::from_millis(200))
+        );
+        assert_eq!(
+            policy.next_delay(&failed(1, Duration::ZERO), &server_error),
+            None
+        );
+        // Waiting out the rate limit would pass the deadline
+        assert_eq!(
+            policy.next_delay(&failed(1, Duration::ZERO), &rate_limited),
+            None
+        );
+        assert_eq!(
+            policy.next_delay(&failed(3, Duration::from_millis(900)), &timeout),
+            None
+        );
+    }
+
+    #[test]
+    fn test_max_delay_caps_server_delay() {
+        let rate_limited =
+            CoreError::RedditApi(RedditApiError::RateLimitExceeded { retry_after: 3600 });
+        let policy = StandardRetryPolicy::new(5, fixed_backoff());
+        assert_eq!(
+            policy.next_delay(&failed(1, Duration::ZERO), &rate_limited),
+            Some(Duration::from_secs(3600))
+        );
+
+        let policy = policy.with_max_delay(Duration::from_secs(30));
+        assert_eq!(
+            policy.next_delay(&failed(1, Duration::ZERO), &rate_limited),
+            Some(Duration::from_secs(30))
+        );
+    }
+
+    #[test]
+    fn test_retry_budget_caps_retries() {
+        let budget = Arc::new(RetryBudget::new(2, 0.0).with_success_deposit(0.5));
+        let policy = StandardRetryPolicy::new(10, fixed_backoff()).with_budget(budget.clone());
+        let error = CoreError::RedditApi(RedditApiError::RequestTimeout);
+
+        assert!(policy
+            .next_delay(&failed(1, Duration::ZERO), &error)
+            .is_some());
+        assert!(policy
+            .next_delay(&failed(2, Duration::ZERO), &error)
+            .is_some());
+        assert!(policy
+            .next_delay(&failed(3, Duration::ZERO), &error)
+            .is_none());
+
+        policy.record_success();
+        policy.record_success();
+        assert_eq!(budget.available(), 1);
+        assert!(policy
+            .next_delay(&failed(1, Duration::ZERO), &error)
+            .is_some());
+    }
+}
@@ -21,11 +21,124 @@ pub struct RedditPost {
     pub thumbnail: Option<String>,
 }
 
+/// A comment on a post, together with whichever replies were loaded
+#[derive(Debug, Clone)]
+pub struct RedditComment {
+    pub id: String,
+    pub post_id: String,
+    /// Fullname of the parent: `t3_` for top-level comments, `t1_` for replies
+    pub parent_id: String,
+    pub author: String,
+    pub body: String,
+    pub score: i32,
+    pub created_utc: i64,
+    /// Nesting level, 0 for top-level comments
+    pub depth: u32,
+    pub stickied: bool,
+    pub replies: Vec<RedditComment>,
+}
+
+impl RedditComment {
+    pub fn is_top_level(&self) -> bool {
+        self.parent_id.starts_with("t3_")
+    }
+
+    /// Number of comments in this thread, including this one
+    pub fn thread_size(&self) -> usize {
+        1 + self
+            .replies
+            .iter()
+            .map(|reply| reply.thread_size())
+            .sum::<usize>()
+    }
+}
+
+/// Newest post seen in a subreddit, so the next poll only fetches newer posts
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SubredditHighWaterMark {
+    pub post_id: Option<String>,
+    pub created_utc: Option<i64>,
+    /// Fullname of the last post returned by a poll that stopped before
+    /// reaching the mark; the next poll continues from there
+    pub resume_after: Option<String>,
+    /// Newest post seen since the catch-up started, which becomes the mark
+    /// once the catch-up reaches it
+    pub pending_post_id: Option<String>,
+    pub pending_created_utc: Option<i64>,
+}
+
+impl SubredditHighWaterMark {
+    pub fn new(post_id: String, created_utc: i64) -> Self {
+        Self {
+            post_id: Some(post_id),
+            created_utc: Some(created_utc),
+            ..Self::default()
+        }
+    }
+
+    /// True if nothing has been seen in this subreddit yet
+    pub fn is_empty(&self) -> bool {
+        self.post_id.is_none() && self.created_utc.is_none()
+    }
+
+    /// Move the mark forward if `post` is newer than the current one
+    pub fn observe(&mut self, post: &RedditPost) {
+        if post.stickied {
+            return;
+        }
+
+        let is_newer = self
+            .created_utc
+            .is_none_or(|created_utc| post.created_utc > created_utc);
+        if is_newer {
+            self.post_id = Some(post.id.clone());
+            self.created_utc = Some(post.created_utc);
+        }
+    }
+
+    /// True while polls are still working through posts between the mark
+    /// and `resume_after`
+    pub fn is_catching_up(&self) -> bool {
+        self.resume_after.is_some()
+    }
+
+    /// Remember `post` as the next mark if it is the newest one seen since
+    /// the catch-up started
+    pub fn observe_pending(&mut self, post: &RedditPost) {
+        if post.stickied {
+            return;
+        }
+
+        let is_newer = self
+            .pending_created_utc
+            .is_none_or(|created_utc| post.created_utc > created_utc);
+        if is_newer {
+            self.pending_post_id = Some(post.id.clone());
+            self.pending_created_utc = Some(post.created_utc);
+        }
+    }
+
+    /// The catch-up reached the mark: move the mark to the newest post seen
+    /// along the way and forget the cursor
+    pub fn finish_catch_up(&mut self) {
+        if let (Some(post_id), Some(created_utc)) =
+            (self.pending_post_id.take(), self.pending_created_utc.take())
+        {
+            self.post_id = Some(post_id);
+            self.created_utc = Some(created_utc);
+        }
+        self.resume_after = None;
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Keyword {
     pub id: Option<i64>,
     pub text: String,
     pub embedding: Option<Vec<f32>>,
+    /// Also run a Reddit-wide search for this keyword, not just match
+    /// posts from joined subreddits
+    pub search_reddit: bool,
     pub created_at: i64,
 }
 
@@ -1,7 +1,10 @@
 use likeminded_core::{
-    ConfigError, CoreError, DatabaseError, EmbeddingError, ErrorExt, ErrorReporter, LlmError,
-    RedditApiError,
+    retry_with_backoff, retry_with_options, Backoff, CancellationToken, ConfigError, CoreError,
+    DatabaseError, EmbeddingError, ErrorExt, ErrorReporter, LlmError, RedditApiError, RetryOptions,
+    StandardRetryPolicy,
 };
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
 use std::time::Duration;
 
 #[test]
@@ -68,6 +71,14 @@ fn test_user_friendly_messages() {
     assert!(message.contains("api_key"));
 }
 
+#[test]
+fn test_revoked_refresh_token() {
+    let error = RedditApiError::RefreshTokenRevoked;
+    assert!(!error.is_retryable());
+    assert_eq!(error.error_code(), "REDDIT_REFRESH_TOKEN_REVOKED");
+    assert!(error.user_friendly_message().contains("reconnect"));
+}
+
 #[test]
 fn test_error_reporter() {
     let reporter = ErrorReporter::new()
@@ -79,3 +90,146 @@ fn test_error_reporter() {
     reporter.report_error(&error);
     reporter.report_warning(&error);
 }
+
+#[tokio::test]
+async fn test_retry_with_options_retries_async_operation() {
+    let calls = Arc::new(AtomicUsize::new(0));
+    let attempts = Arc::new(AtomicUsize::new(0));
+
+    let attempts_seen = attempts.clone();
+    let policy = StandardRetryPolicy::new(
+        4,
+        Backoff::Exponential {
+            base: Duration::from_millis(1),
+            max: Duration::from_millis(5),
+            multiplier: 2.0,
+            jitter_factor: 0.5,
+        },
+    );
+    let options = RetryOptions::new().with_on_attempt(move |attempt| {
+        assert!(attempt.next_delay.unwrap() <= Duration::from_millis(5));
+        attempts_seen.fetch_add(1, Ordering::SeqCst);
+    });
+
+    let result = retry_with_options(
+        || {
+            let calls = calls.clone();
+            async move {
+                if calls.fetch_add(1, Ordering::SeqCst) < 2 {
+                    Err(CoreError::Database(DatabaseError::DatabaseLocked))
+                } else {
+                    Ok("written")
+                }
+            }
+        },
+        &policy,
+        options,
+    )
+    .await;
+
+    assert_eq!(result.unwrap(), "written");
+    assert_eq!(calls.load(Ordering::SeqCst), 3);
+    assert_eq!(attempts.load(Ordering::SeqCst), 2);
+}
+
+#[tokio::test]
+async fn test_retry_with_options_stops_when_cancelled() {
+    let token = CancellationToken::new();
+    let calls = Arc::new(AtomicUsize::new(0));
+
+    let canceller = token.clone();
+    tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        canceller.cancel();
+    });
+
+    let policy = StandardRetryPolicy::new(
+        6,
+        Backoff::Exponential {
+            base: Duration::from_secs(10),
+            max: Duration::from_secs(60),
+            multiplier: 2.0,
+            jitter_factor: 0.0,
+        },
+    );
+    let result: Result<(), CoreError> = retry_with_options(
+        || {
+            calls.fetch_add(1, Ordering::SeqCst);
+            async { Err(CoreError::RedditApi(RedditApiError::RequestTimeout)) }
+        },
+        &policy,
+        RetryOptions::new().with_cancellation(token),
+    )
+    .await;
+
+    assert!(matches!(
+        result,
+        Err(CoreError::RedditApi(RedditApiError::RequestTimeout))
+    ));
+    assert_eq!(calls.load(Ordering::SeqCst), 1);
+}
+
+/// An error type of another crate, classified through `ErrorExt`
+#[derive(Debug, PartialEq)]
+enum UploadError {
+    Busy,
+    Rejected,
+}
+
+impl ErrorExt for UploadError {
+    fn log_error(&self) -> &Self {
+        self
+    }
+
+    fn log_warn(&self) -> &Self {
+        self
+    }
+
+    fn is_retryable(&self) -> bool {
+        *self == UploadError::Busy
+    }
+
+    fn retry_after(&self) -> Option<Duration> {
+        None
+    }
+
+    fn user_friendly_message(&self) -> String {
+        format!("{:?}", self)
+    }
+
+    fn error_code(&self) -> String {
+        "UPLOAD".to_string()
+    }
+}
+
+#[tokio::test]
+async fn test_retry_with_backoff_classifies_any_error_ext() {
+    let calls = AtomicUsize::new(0);
+    let result = retry_with_backoff(
+        || async {
+            match calls.fetch_add(1, Ordering::SeqCst) {
+                0 | 1 => Err(UploadError::Busy),
+                _ => Ok("uploaded"),
+            }
+        },
+        3,
+        Duration::from_millis(1),
+    )
+    .await;
+    assert_eq!(result, Ok("uploaded"));
+    assert_eq!(calls.load(Ordering::SeqCst), 3);
+
+    // Errors that are not retryable come straight back
+    let calls = AtomicUsize::new(0);
+    let result: Result<(), _> = retry_with_backoff(
+        || async {
+            calls.fetch_add(1, Ordering::SeqCst);
+            Err(UploadError::Rejected)
+        },
+        3,
+        Duration::from_millis(1),
+    )
+    .await;
+    assert_eq!(result, Err(UploadError::Rejected));
+    assert_eq!(calls.load(Ordering::SeqCst), 1);
+}
@@ -1,3 +1,4 @@
+use reddit_client::oauth_callback::DEFAULT_CALLBACK_TIMEOUT;
 use reddit_client::{RedditClient, RedditOAuth2Config};
 use std::fs::File;
 use std::io::{self, Write};
@@ -89,42 +90,20 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let scopes = RedditClient::get_required_scopes();
     println!("📋 Required scopes: {:?}\n", scopes);
 
-    let (auth_url, csrf_token) = client.generate_auth_url(&scopes)?;
-    println!("🔗 Authentication URL generated:");
-    println!("{}\n", auth_url);
-
-    println!(
-        "🔍 Authentication state after URL generation: {:?}",
-        client.get_auth_state()
-    );
-    println!("🔒 CSRF Token: {}\n", csrf_token.secret());
-
     println!("📝 Instructions:");
-    println!("1. Copy the URL above and open it in your browser");
+    println!("1. Open the URL below in your browser");
     println!("2. Log in to Reddit and authorize the application");
-    println!("3. You'll be redirected to localhost:8080/callback with a code");
-    println!("4. Copy the ENTIRE callback URL and paste it here");
-    println!("   Example: http://localhost:8080/callback?state=...&code=...\n");
-
-    print!("Enter the callback URL: ");
-    io::stdout().flush()?;
-    let mut callback_url = String::new();
-    io::stdin().read_line(&mut callback_url)?;
-    let callback_url = callback_url.trim();
-
-    if callback_url.is_empty() {
-        println!("❌ Callback URL cannot be empty");
-        return Ok(());
-    }
-
-    if !callback_url.starts_with("http://localhost:8080/callback") {
-        println!("⚠️  Warning: URL doesn't look like a proper callback URL");
-        println!("   Expected format: http://localhost:8080/callback?state=...&code=...");
-    }
-
-    // Handle OAuth callback
-    println!("\n🔄 Processing OAuth callback...");
-    match client.handle_callback(callback_url, &csrf_token).await {
+    println!("3. Reddit redirects back to localhost:8080/callback, which this test catches\n");
+
+    // Wait for the OAuth redirect on the loopback listener
+    println!("🔄 Waiting for Reddit to redirect back...");
+    let result = client
+        .authorize_with_loopback(&scopes, DEFAULT_CALLBACK_TIMEOUT, |auth_url| {
+            println!("🔗 Authentication URL generated:");
+            println!("{}\n", auth_url);
+        })
+        .await;
+    match result {
         Ok(token) => {
             println!("✅ Authentication successful!");
             println!("🎫 Access token: {}...", &token.access_token[..20]);
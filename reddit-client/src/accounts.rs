@@ -0,0 +1,121 @@
+//! Several Reddit accounts polled side by side.
+//!
+//! Every account gets its own [`RedditClient`], and with it its own tokens
+//! and rate limiter, so one busy account cannot spend another's budget.
+//! Account IDs are the `reddit_accounts` row IDs from the database.
+
+use crate::RedditClient;
+use futures::future::{join_all, BoxFuture};
+use likeminded_core::CoreError;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// What happened to one account during [`AccountPool::poll`]
+#[derive(Debug)]
+pub enum AccountPollOutcome<T> {
+    Polled(Result<T, CoreError>),
+    /// The account's rate limit budget is spent; try again after `retry_after`
+    Skipped {
+        retry_after: Duration,
+    },
+}
+
+#[derive(Default)]
+pub struct AccountPool {
+    clients: BTreeMap<i64, RedditClient>,
+}
+
+impl AccountPool {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add or replace the client for `account_id`. Clients must not share an
+    /// API client with another account, since that would merge their rate
+    /// limit budgets.
+    pub fn insert(
+        &mut self,
+        account_id: i64,
+        client: RedditClient,
+    ) -> Result<Option<RedditClient>, CoreError> {
+        let api_client = client.api_client();
+        let shared = self
+            .clients
+            .iter()
+            .any(|(id, other)| *id != account_id && Arc::ptr_eq(&other.api_client(), &api_client));
+        if shared {
+            return Err(CoreError::InvalidInput {
+                message: format!(
+                    "Account {} shares an API client with another account",
+                    account_id
+                ),
+            });
+        }
+
+        Ok(self.clients.insert(account_id, client))
+    }
+
+    pub fn remove(&mut self, account_id: i64) -> Option<RedditClient> {
+        self.clients.remove(&account_id)
+    }
+
+    pub fn get_mut(&mut self, account_id: i64) -> Option<&mut RedditClient> {
+        self.clients.get_mut(&account_id)
+    }
+
+    pub fn account_ids(&self) -> Vec<i64> {
+        self.clients.keys().copied().collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.clients.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.clients.is_empty()
+    }
+
+    /// Run `poll_account` for every account that has rate limit budget left.
+    /// Accounts are polled concurrently; each one only draws on its own
+    /// limiter. Outcomes are returned in account ID order.
+    pub async fn poll<T, F>(&mut self, mut poll_account: F) -> Vec<(i64, AccountPollOutcome<T>)>
+    where
+        F: for<'a> FnMut(i64, &'a mut RedditClient) -> BoxFuture<'a, Result<T, CoreError>>,
+    {
+        let mut skipped = Vec::new();
+        let mut polls = Vec::new();
+
+        for (account_id, client) in self.clients.iter_mut() {
+            let status = client.get_rate_limit_status().await;
+            match status.estimated_wait_time {
+                Some(retry_after) => {
+                    tracing::debug!(
+                        "Skipping account {}: rate limit budget spent for {:?}",
+                        account_id,
+                        retry_after
+                    );
+                    skipped.push((*account_id, AccountPollOutcome::Skipped { retry_after }));
+                }
+                None => {
+                    let account_id = *account_id;
+                    let poll = poll_account(account_id, client);
+                    polls.push(async move { (account_id, AccountPollOutcome::Polled(poll.await)) });
+                }
+            }
+        }
+
+        let mut outcomes = join_all(polls).await;
+        outcomes.extend(skipped);
+        outcomes.sort_by_key(|(account_id, _)| *account_id);
+        outcomes
+    }
+}
+
+impl std::fmt::Debug for AccountPool {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AccountPool")
+            .field("account_ids", &self.account_ids())
+            .finish()
+    }
+}
@@ -1,16 +1,23 @@
 #[cfg(feature = "database")]
 use crate::api_tracker::ApiTracker;
+use crate::cache::{cache_key, CachedResponse, ResponseCache};
+use crate::comments::{CommentFetchOptions, CommentThread, CommentTreeBuilder};
+use crate::endpoint_router::{endpoint_pattern, EndpointRouter};
 use crate::metrics::{MetricsCollector, RequestMetrics};
-use crate::rate_limiter::{RateLimitConfig, RateLimiter};
-use crate::retry::{RetryConfig, RetryExecutor};
-use likeminded_core::{CoreError, RedditApiError, RedditPost};
-use reqwest::{Client, Method, Response};
+use crate::rate_limiter::{RateLimitConfig, RateLimiter, ServerRateLimit};
+use crate::retry::{circuit_key, CircuitBreakerState, RetryConfig, RetryExecutor};
+use crate::search::SearchQuery;
+use likeminded_core::{CoreError, RedditApiError, RedditPost, RetryPolicy};
+use reqwest::{Client, Method, Response, StatusCode};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::sync::Arc;
-use std::time::{Duration, Instant};
+#[cfg(feature = "database")]
+use std::sync::OnceLock;
+use std::time::{Duration, Instant, SystemTime};
 use tracing::{debug, error, info, warn};
 
-const REDDIT_API_BASE: &str = "https://oauth.reddit.com";
+pub const REDDIT_API_BASE: &str = "https://oauth.reddit.com";
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RedditListing<T> {
@@ -93,12 +100,17 @@ pub struct RedditApiClient {
     rate_limiter: Arc<RateLimiter>,
     metrics: Arc<MetricsCollector>,
     retry_executor: Arc<RetryExecutor>,
+    /// Set at most once, possibly after the client is shared, see
+    /// [`Self::attach_api_tracker`]
     #[cfg(feature = "database")]
-    api_tracker: Option<Arc<ApiTracker>>,
+    api_tracker: OnceLock<Arc<ApiTracker>>,
     #[cfg(not(feature = "database"))]
     #[allow(dead_code)]
     api_tracker: Option<()>, // Stub when database feature is disabled
+    response_cache: Option<ResponseCache>,
+    endpoint_router: Option<EndpointRouter>,
     user_agent: String,
+    base_url: String,
 }
 
 impl RedditApiClient {
@@ -120,8 +132,11 @@ impl RedditApiClient {
             rate_limiter,
             metrics,
             retry_executor,
-            api_tracker: None,
+            api_tracker: Default::default(),
+            response_cache: None,
+            endpoint_router: None,
             user_agent,
+            base_url: REDDIT_API_BASE.to_string(),
         }
     }
 
@@ -143,17 +158,70 @@ impl RedditApiClient {
             rate_limiter,
             metrics,
             retry_executor,
-            api_tracker: None,
+            api_tracker: Default::default(),
+            response_cache: None,
+            endpoint_router: None,
             user_agent,
+            base_url: REDDIT_API_BASE.to_string(),
         }
     }
 
+    /// Point the client at a different API host, e.g. a local mock server
+    pub fn with_base_url(mut self, base_url: String) -> Self {
+        self.base_url = base_url.trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Decide retries with `policy`, e.g. one with a deadline or a retry
+    /// budget shared with other clients
+    pub fn with_retry_policy(mut self, policy: Arc<dyn RetryPolicy>) -> Self {
+        let config = self.retry_executor.config().clone();
+        self.retry_executor = Arc::new(RetryExecutor::new(config).with_policy(policy));
+        self
+    }
+
     #[cfg(feature = "database")]
     pub fn with_api_tracker(mut self, api_tracker: Arc<ApiTracker>) -> Self {
-        self.api_tracker = Some(api_tracker);
+        self.api_tracker = OnceLock::from(api_tracker);
+        self
+    }
+
+    /// Start recording calls in `api_tracker` on a client that may already
+    /// be shared. Returns `false`, leaving the client as it was, if it
+    /// already has a tracker.
+    #[cfg(feature = "database")]
+    pub fn attach_api_tracker(&self, api_tracker: Arc<ApiTracker>) -> bool {
+        self.api_tracker.set(api_tracker).is_ok()
+    }
+
+    /// Serve GETs for endpoints with a TTL in the cache's policy from the
+    /// cache. Entries are scoped to the account set with
+    /// [`ResponseCache::for_account`], or else to a fingerprint of the access
+    /// token, so accounts sharing a store never see each other's responses.
+    pub fn with_response_cache(mut self, response_cache: ResponseCache) -> Self {
+        self.response_cache = Some(response_cache);
+        self
+    }
+
+    /// Enforce per-pattern rate limits, timeouts and retry counts on top of
+    /// the global limiter
+    pub fn with_endpoint_router(mut self, endpoint_router: EndpointRouter) -> Self {
+        self.endpoint_router = Some(endpoint_router);
         self
     }
 
+    pub fn endpoint_router(&self) -> Option<&EndpointRouter> {
+        self.endpoint_router.as_ref()
+    }
+
+    /// Drop every cached response, e.g. when the user asks for a refresh
+    pub async fn clear_response_cache(&self) -> Result<(), CoreError> {
+        match self.response_cache {
+            Some(ref cache) => cache.store().clear().await,
+            None => Ok(()),
+        }
+    }
+
     /// Make a request with retry logic
     pub async fn make_request(
         &self,
@@ -175,8 +243,40 @@ impl RedditApiClient {
         operation_type: Option<&str>,
         subreddit: Option<&str>,
         priority: i32,
+    ) -> Result<Response, CoreError> {
+        self.send_request(
+            method,
+            endpoint,
+            access_token,
+            query_params,
+            &[],
+            operation_type,
+            subreddit,
+            priority,
+        )
+        .await
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    async fn send_request(
+        &self,
+        method: Method,
+        endpoint: &str,
+        access_token: &str,
+        query_params: Option<&[(&str, &str)]>,
+        headers: &[(&'static str, String)],
+        operation_type: Option<&str>,
+        subreddit: Option<&str>,
+        priority: i32,
     ) -> Result<Response, CoreError> {
         let operation_name = format!("{} {}", method, endpoint);
+        let max_attempts = self
+            .endpoint_router
+            .as_ref()
+            .and_then(|router| router.route(endpoint))
+            .map(|route| route.limits().max_attempts())
+            .unwrap_or_else(|| self.retry_executor.max_attempts());
+        let sources = self.circuit_sources(endpoint, subreddit);
 
         // Clone values for use in closure
         let method_clone = method.clone();
@@ -187,7 +287,7 @@ impl RedditApiClient {
         let subreddit_clone = subreddit.map(|s| s.to_string());
 
         self.retry_executor
-            .execute(&operation_name, || {
+            .execute_for_sources(&operation_name, &sources, max_attempts, || {
                 let method = method_clone.clone();
                 let endpoint = endpoint_clone.clone();
                 let access_token = access_token_clone.clone();
@@ -201,6 +301,7 @@ impl RedditApiClient {
                         &endpoint,
                         &access_token,
                         query_params.as_deref(),
+                        headers,
                         operation_type.as_deref(),
                         subreddit.as_deref(),
                         priority,
@@ -211,6 +312,37 @@ impl RedditApiClient {
             .await
     }
 
+    /// Pattern `endpoint` is grouped under: its route's pattern in the
+    /// router, or [`endpoint_pattern`] with names and IDs replaced by `*`
+    pub fn endpoint_pattern(&self, endpoint: &str) -> String {
+        match &self.endpoint_router {
+            Some(router) => router.pattern_for(endpoint),
+            None => endpoint_pattern(endpoint),
+        }
+    }
+
+    /// Circuit breaker keys for a request: the endpoint's pattern plus the
+    /// subreddit it is for. A multireddit (`a+b+c`) gets one key per member,
+    /// so a batch counts towards each member's breaker.
+    fn circuit_sources(&self, endpoint: &str, subreddit: Option<&str>) -> Vec<String> {
+        let pattern = self.endpoint_pattern(endpoint);
+        match subreddit {
+            Some(subreddit) => subreddit
+                .split('+')
+                .map(|member| circuit_key(&pattern, Some(member)))
+                .collect(),
+            None => vec![circuit_key(&pattern, None)],
+        }
+    }
+
+    /// Whether requests for `endpoint` (and `subreddit`, if the endpoint is
+    /// scoped to one) would get past their circuit breakers right now
+    pub fn is_source_available(&self, endpoint: &str, subreddit: Option<&str>) -> bool {
+        self.circuit_sources(endpoint, subreddit)
+            .iter()
+            .all(|source| self.retry_executor.is_source_available(source))
+    }
+
     /// Internal request method without retry logic
     async fn make_request_internal(
         &self,
@@ -218,6 +350,7 @@ impl RedditApiClient {
         endpoint: &str,
         access_token: &str,
         query_params: Option<&[(&str, &str)]>,
+        headers: &[(&'static str, String)],
         #[cfg_attr(not(feature = "database"), allow(unused_variables))]
         operation_type: Option<&str>,
         #[cfg_attr(not(feature = "database"), allow(unused_variables))]
@@ -225,14 +358,11 @@ impl RedditApiClient {
         #[cfg_attr(not(feature = "database"), allow(unused_variables))]
         priority: i32,
     ) -> Result<Response, CoreError> {
-        let url = format!("{}{}", REDDIT_API_BASE, endpoint);
+        let url = format!("{}{}", self.base_url, endpoint);
         let start_time = Instant::now();
         let mut success = false;
-        #[allow(unused_assignments)]
         let mut status_code = None;
-        #[allow(unused_assignments)]
         let mut error_type = None;
-        #[allow(unused_assignments)]
         let mut rate_limited = false;
 
         // Get rate limit status before request
@@ -240,9 +370,20 @@ impl RedditApiClient {
         #[cfg_attr(not(feature = "database"), allow(unused_variables))]
         let tokens_before = rate_status_before.available_tokens;
 
+        // Wait on the endpoint's own budget first so we do not sit on a
+        // global permit meanwhile
+        let route = self
+            .endpoint_router
+            .as_ref()
+            .and_then(|router| router.route(endpoint));
+        let endpoint_wait_time = match route {
+            Some(route) => route.acquire().await,
+            None => Duration::ZERO,
+        };
+
         // Acquire rate limit permit
         let permit = self.rate_limiter.acquire_permit().await;
-        let queue_wait_time = permit.queue_wait_time;
+        let queue_wait_time = endpoint_wait_time + permit.queue_wait_time;
         debug!(
             "Acquired rate limit permit for {} {} (waited {:?})",
             method, endpoint, queue_wait_time
@@ -258,16 +399,35 @@ impl RedditApiClient {
         if let Some(params) = query_params {
             request_builder = request_builder.query(params);
         }
+        for (name, value) in headers {
+            request_builder = request_builder.header(*name, value);
+        }
+        if let Some(route) = route {
+            request_builder = request_builder.timeout(route.limits().timeout);
+        }
 
         // Execute request
         info!("Making Reddit API request: {} {}", method, endpoint);
-        let response = match request_builder.send().await {
+        let outcome = match request_builder.send().await {
             Ok(response) => {
                 status_code = Some(response.status().as_u16());
 
-                if response.status().is_success() {
+                // Let the limiter adapt to the budget Reddit reports
+                if let Some(server_limit) = ServerRateLimit::from_headers(response.headers()) {
+                    self.rate_limiter.update_from_server(server_limit).await;
+                }
+
+                // A 304 answers a conditional request from the response cache
+                let revalidated = response.status() == StatusCode::NOT_MODIFIED
+                    && headers.iter().any(|(name, _)| {
+                        name.eq_ignore_ascii_case("if-none-match")
+                            || name.eq_ignore_ascii_case("if-modified-since")
+                    });
+
+                if response.status().is_success() || revalidated {
                     success = true;
                     debug!("Request successful: {} {}", response.status(), endpoint);
+                    Ok(response)
                 } else {
                     error!(
                         "Request failed with status: {} for {}",
@@ -276,69 +436,56 @@ impl RedditApiClient {
                     );
 
                     if response.status().as_u16() == 429 {
-                        #[allow(unused_assignments)]
-                        { rate_limited = true; }
-                        #[allow(unused_assignments)]
-                        { error_type = Some("rate_limited".to_string()); }
+                        rate_limited = true;
+                        error_type = Some("rate_limited".to_string());
 
                         // Extract retry-after header if present
-                        if let Some(retry_after) = response.headers().get("retry-after") {
-                            if let Ok(retry_seconds) =
-                                retry_after.to_str().unwrap_or("60").parse::<u64>()
-                            {
-                                warn!("Rate limited, retry after {} seconds", retry_seconds);
-                                return Err(CoreError::RedditApi(
-                                    RedditApiError::RateLimitExceeded {
-                                        retry_after: retry_seconds,
-                                    },
-                                ));
-                            }
-                        }
-
-                        return Err(CoreError::RedditApi(RedditApiError::RateLimitExceeded {
-                            retry_after: 60,
-                        }));
+                        let retry_after = response
+                            .headers()
+                            .get("retry-after")
+                            .and_then(|retry_after| retry_after.to_str().ok())
+                            .and_then(|retry_after| retry_after.parse::<u64>().ok())
+                            .unwrap_or(60);
+                        warn!("Rate limited, retry after {} seconds", retry_after);
+                        Err(CoreError::RedditApi(RedditApiError::RateLimitExceeded {
+                            retry_after,
+                        }))
                     } else if response.status().as_u16() == 401 {
-                        #[allow(unused_assignments)]
-                        { error_type = Some("unauthorized".to_string()); }
-                        return Err(CoreError::RedditApi(RedditApiError::InvalidToken));
+                        error_type = Some("unauthorized".to_string());
+                        Err(CoreError::RedditApi(RedditApiError::InvalidToken))
                     } else if response.status().as_u16() == 403 {
-                        #[allow(unused_assignments)]
-                        { error_type = Some("forbidden".to_string()); }
-                        return Err(CoreError::RedditApi(RedditApiError::Forbidden {
+                        error_type = Some("forbidden".to_string());
+                        Err(CoreError::RedditApi(RedditApiError::Forbidden {
                             resource: endpoint.to_string(),
-                        }));
+                        }))
                     } else if response.status().as_u16() == 404 {
-                        #[allow(unused_assignments)]
-                        { error_type = Some("not_found".to_string()); }
-                        return Err(CoreError::RedditApi(RedditApiError::InvalidResponse {
+                        error_type = Some("not_found".to_string());
+                        Err(CoreError::RedditApi(RedditApiError::InvalidResponse {
                             details: "Resource not found".to_string(),
-                        }));
+                        }))
                     } else if response.status().is_server_error() {
-                        #[allow(unused_assignments)]
-                        { error_type = Some("server_error".to_string()); }
-                        return Err(CoreError::RedditApi(RedditApiError::ServerError {
+                        error_type = Some("server_error".to_string());
+                        Err(CoreError::RedditApi(RedditApiError::ServerError {
                             status_code: response.status().as_u16(),
-                        }));
+                        }))
+                    } else {
+                        Ok(response)
                     }
                 }
-
-                response
             }
             Err(e) => {
                 error!("Network error for {} {}: {}", method, endpoint, e);
-                #[allow(unused_assignments)]
-                { error_type = Some("network_error".to_string()); }
+                error_type = Some("network_error".to_string());
 
                 if e.is_timeout() {
-                    return Err(CoreError::RedditApi(RedditApiError::RequestTimeout));
+                    Err(CoreError::RedditApi(RedditApiError::RequestTimeout))
                 } else {
-                    return Err(CoreError::Network(e));
+                    Err(CoreError::Network(e))
                 }
             }
         };
 
-        // Record metrics
+        // Record metrics, for failed requests too
         let response_time = start_time.elapsed();
         let request_metrics = RequestMetrics {
             endpoint: endpoint.to_string(),
@@ -354,14 +501,14 @@ impl RedditApiClient {
 
         // Record API call in tracker if available
         #[cfg(feature = "database")]
-        if let Some(ref tracker) = self.api_tracker {
+        if let Some(tracker) = self.api_tracker.get() {
             let rate_status_after = self.rate_limiter.get_rate_limit_status().await;
             let tokens_after = rate_status_after.available_tokens;
 
             if let Err(e) = tracker
                 .record_api_call(
                     endpoint,
-                    &method.to_string(),
+                    method.as_str(),
                     status_code,
                     response_time,
                     rate_limited,
@@ -378,7 +525,110 @@ impl RedditApiClient {
             }
         }
 
-        Ok(response)
+        outcome
+    }
+
+    /// GET `endpoint` and return the body. Endpoints with a TTL in the
+    /// response cache are served from it while fresh, without acquiring a
+    /// rate limit permit; stale entries are revalidated with a conditional
+    /// request and reused on `304 Not Modified`.
+    async fn get_body(
+        &self,
+        endpoint: &str,
+        access_token: &str,
+        query_params: Option<&[(&str, &str)]>,
+        operation_type: Option<&str>,
+        subreddit: Option<&str>,
+        priority: i32,
+    ) -> Result<Vec<u8>, CoreError> {
+        let cache_ttl = self
+            .response_cache
+            .as_ref()
+            .and_then(|cache| cache.policy().ttl_for(endpoint).map(|ttl| (cache, ttl)));
+        let Some((cache, ttl)) = cache_ttl else {
+            let response = self
+                .make_request_with_context(
+                    Method::GET,
+                    endpoint,
+                    access_token,
+                    query_params,
+                    operation_type,
+                    subreddit,
+                    priority,
+                )
+                .await?;
+            return Ok(response.bytes().await?.to_vec());
+        };
+
+        let key = cache_key(&cache.scope(access_token), endpoint, query_params);
+        let cached = cache.store().get(&key).await.unwrap_or_else(|e| {
+            warn!("Failed to read response cache for {}: {}", key, e);
+            None
+        });
+
+        if let Some(ref entry) = cached {
+            if entry.is_fresh(SystemTime::now()) {
+                debug!("Serving {} from response cache", key);
+                self.metrics.record_cache_hit().await;
+                return Ok(entry.body.clone());
+            }
+        }
+
+        let headers = cached
+            .as_ref()
+            .map(CachedResponse::conditional_headers)
+            .unwrap_or_default();
+        let response = self
+            .send_request(
+                Method::GET,
+                endpoint,
+                access_token,
+                query_params,
+                &headers,
+                operation_type,
+                subreddit,
+                priority,
+            )
+            .await?;
+
+        let now = SystemTime::now();
+        let entry = match (response.status(), cached) {
+            (StatusCode::NOT_MODIFIED, Some(mut entry)) => {
+                debug!("Response for {} not modified, extending cache entry", key);
+                entry.stored_at = now;
+                entry.expires_at = now + ttl;
+                entry
+            }
+            (status, _) if status.is_success() => {
+                let header = |name| {
+                    response
+                        .headers()
+                        .get(name)
+                        .and_then(|value| value.to_str().ok())
+                        .map(str::to_string)
+                };
+                let etag = header(reqwest::header::ETAG);
+                let last_modified = header(reqwest::header::LAST_MODIFIED);
+                CachedResponse {
+                    status: status.as_u16(),
+                    body: response.bytes().await?.to_vec(),
+                    etag,
+                    last_modified,
+                    stored_at: now,
+                    expires_at: now + ttl,
+                }
+            }
+            (status, _) => {
+                return Err(CoreError::RedditApi(RedditApiError::InvalidResponse {
+                    details: format!("Unexpected status {} for {}", status, endpoint),
+                }));
+            }
+        };
+
+        if let Err(e) = cache.store().put(&key, &entry).await {
+            warn!("Failed to write response cache for {}: {}", key, e);
+        }
+        Ok(entry.body)
     }
 
     pub async fn get_user_info(&self, access_token: &str) -> Result<RedditUserData, CoreError> {
@@ -474,9 +724,8 @@ impl RedditApiClient {
             Some(params.as_slice())
         };
 
-        let response = self
-            .make_request_with_context(
-                Method::GET,
+        let body = self
+            .get_body(
                 &endpoint,
                 access_token,
                 query_params,
@@ -486,7 +735,7 @@ impl RedditApiClient {
             )
             .await?;
 
-        let listing: RedditListing<RedditPostData> = response.json().await.map_err(|e| {
+        let listing: RedditListing<RedditPostData> = serde_json::from_slice(&body).map_err(|e| {
             error!("Failed to parse subreddit posts: {}", e);
             CoreError::RedditApi(RedditApiError::InvalidResponse {
                 details: format!("Failed to parse posts for r/{}", subreddit),
@@ -521,20 +770,25 @@ impl RedditApiClient {
 
         info!("Fetching posts from {} subreddits", subreddits.len());
 
-        // Create futures for all subreddit requests
+        // Create futures for all subreddit requests, skipping subreddits
+        // whose circuit breaker is open
         let futures = subreddits.iter().map(|subreddit| {
             let subreddit_name = subreddit.to_string();
             async move {
-                let result = self
-                    .get_subreddit_posts_with_time_filter(
-                        access_token,
-                        &subreddit_name,
-                        sort,
-                        time_filter,
-                        limit,
-                        after,
-                    )
-                    .await;
+                let result = match self.unavailable_listing(&subreddit_name, sort) {
+                    Some(e) => Err(e),
+                    None => {
+                        self.get_subreddit_posts_with_time_filter(
+                            access_token,
+                            &subreddit_name,
+                            sort,
+                            time_filter,
+                            limit,
+                            after,
+                        )
+                        .await
+                    }
+                };
                 (subreddit_name, result)
             }
         });
@@ -552,6 +806,126 @@ impl RedditApiClient {
         Ok(results)
     }
 
+    /// Fetch posts from multiple subreddits using combined `/r/a+b+c` listings.
+    ///
+    /// Subreddits are grouped by [`crate::batching::plan_batches`] and each
+    /// batch costs a single request. Results are split back out per
+    /// subreddit in the same shape as [`Self::get_multiple_subreddit_posts`].
+    /// Circuit breakers are kept per subreddit: subreddits whose breaker is
+    /// open are left out of the batches, and when a batch fails its
+    /// subreddits are fetched one by one so only the broken one's breaker
+    /// trips. `limit` applies to the combined listing, so busy subreddits can
+    /// crowd out quiet ones within a batch.
+    pub async fn get_batched_subreddit_posts(
+        &self,
+        access_token: &str,
+        subreddits: &[&str],
+        sort: Option<&str>,
+        time_filter: Option<&str>,
+        limit: Option<u32>,
+        max_url_length: usize,
+    ) -> Result<Vec<(String, Result<RedditListing<RedditPostData>, CoreError>)>, CoreError> {
+        use crate::batching::{plan_batches, split_listing};
+        use futures::future::join_all;
+
+        let mut results = Vec::new();
+        let mut available = Vec::new();
+        for subreddit in subreddits {
+            let name = subreddit.trim().trim_start_matches("r/");
+            match self.unavailable_listing(name, sort) {
+                Some(e) => results.push((name.to_string(), Err(e))),
+                None => available.push(*subreddit),
+            }
+        }
+
+        let batches = plan_batches(&available, sort.unwrap_or("hot"), max_url_length);
+        if batches.is_empty() {
+            return Ok(results);
+        }
+
+        info!(
+            "Fetching posts from {} subreddits in {} batched requests",
+            available.len(),
+            batches.len()
+        );
+
+        let futures = batches.iter().map(|batch| async move {
+            let result = self
+                .get_subreddit_posts_with_time_filter(
+                    access_token,
+                    &batch.multireddit_name(),
+                    sort,
+                    time_filter,
+                    limit,
+                    None,
+                )
+                .await;
+            (batch, result)
+        });
+
+        for (batch, result) in join_all(futures).await {
+            match result {
+                Ok(listing) => {
+                    results.extend(
+                        split_listing(batch, listing)
+                            .into_iter()
+                            .map(|(subreddit, listing)| (subreddit, Ok(listing))),
+                    );
+                }
+                Err(e) if batch.subreddits.len() > 1 => {
+                    warn!(
+                        "Batched request for r/{} failed, fetching its subreddits separately: {}",
+                        batch.multireddit_name(),
+                        e
+                    );
+                    let members: Vec<&str> = batch.subreddits.iter().map(String::as_str).collect();
+                    results.extend(
+                        self.get_multiple_subreddit_posts(
+                            access_token,
+                            &members,
+                            sort,
+                            time_filter,
+                            limit,
+                            None,
+                        )
+                        .await?,
+                    );
+                }
+                Err(e) => results.push((batch.subreddits[0].clone(), Err(e))),
+            }
+        }
+
+        // Back in the order the subreddits were asked for
+        let position = |name: &str| {
+            subreddits
+                .iter()
+                .position(|subreddit| {
+                    subreddit
+                        .trim()
+                        .trim_start_matches("r/")
+                        .eq_ignore_ascii_case(name)
+                })
+                .unwrap_or(usize::MAX)
+        };
+        results.sort_by_key(|(name, _)| position(name));
+
+        Ok(results)
+    }
+
+    /// The error to report instead of fetching a listing whose circuit
+    /// breaker is open
+    fn unavailable_listing(&self, subreddit: &str, sort: Option<&str>) -> Option<CoreError> {
+        let endpoint = format!("/r/{}/{}", subreddit, sort.unwrap_or("hot"));
+        if self.is_source_available(&endpoint, Some(subreddit)) {
+            return None;
+        }
+
+        debug!("Skipping {}: circuit breaker is open", endpoint);
+        Some(CoreError::RedditApi(RedditApiError::EndpointUnavailable {
+            endpoint,
+        }))
+    }
+
     /// Check if a subreddit exists and is accessible
     pub async fn check_subreddit_access(
         &self,
@@ -561,8 +935,7 @@ impl RedditApiClient {
         let endpoint = format!("/r/{}/about", subreddit);
 
         match self
-            .make_request_with_context(
-                Method::GET,
+            .get_body(
                 &endpoint,
                 access_token,
                 None,
@@ -598,9 +971,8 @@ impl RedditApiClient {
     ) -> Result<RedditSubredditData, CoreError> {
         let endpoint = format!("/r/{}/about", subreddit);
 
-        let response = self
-            .make_request_with_context(
-                Method::GET,
+        let body = self
+            .get_body(
                 &endpoint,
                 access_token,
                 None,
@@ -611,7 +983,7 @@ impl RedditApiClient {
             .await?;
 
         let subreddit_response: RedditListingChild<RedditSubredditData> =
-            response.json().await.map_err(|e| {
+            serde_json::from_slice(&body).map_err(|e| {
                 error!("Failed to parse subreddit info: {}", e);
                 CoreError::RedditApi(RedditApiError::InvalidResponse {
                     details: format!("Failed to parse info for r/{}", subreddit),
@@ -622,18 +994,23 @@ impl RedditApiClient {
         Ok(subreddit_response.data)
     }
 
+    /// Fetch one page of the user's subscribed subreddits
     pub async fn get_user_subreddits(
         &self,
         access_token: &str,
         limit: Option<u32>,
+        after: Option<&str>,
     ) -> Result<RedditListing<RedditSubredditData>, CoreError> {
         let endpoint = "/subreddits/mine/subscriber";
-        let mut params = Vec::with_capacity(1);
+        let mut params = Vec::with_capacity(2);
         let limit_str = limit.map(|l| l.to_string());
 
         if let Some(ref limit_s) = limit_str {
             params.push(("limit", limit_s.as_str()));
         }
+        if let Some(after_val) = after {
+            params.push(("after", after_val));
+        }
 
         let query_params = if params.is_empty() {
             None
@@ -664,10 +1041,220 @@ impl RedditApiClient {
         Ok(listing)
     }
 
+    /// Search for posts across Reddit or within one subreddit
+    pub async fn search_posts(
+        &self,
+        access_token: &str,
+        query: &SearchQuery,
+    ) -> Result<RedditListing<RedditPostData>, CoreError> {
+        query.validate()?;
+
+        let endpoint = query.endpoint();
+        let owned_params = query.query_params();
+        let params: Vec<(&str, &str)> = owned_params
+            .iter()
+            .map(|(key, value)| (*key, value.as_str()))
+            .collect();
+
+        let response = self
+            .make_request_with_context(
+                Method::GET,
+                &endpoint,
+                access_token,
+                Some(params.as_slice()),
+                Some("search_posts"),
+                query.subreddit.as_deref(),
+                0,
+            )
+            .await?;
+
+        let listing: RedditListing<RedditPostData> = response.json().await.map_err(|e| {
+            error!("Failed to parse search results: {}", e);
+            CoreError::RedditApi(RedditApiError::InvalidResponse {
+                details: format!("Failed to parse search results for '{}'", query.query),
+            })
+        })?;
+
+        info!(
+            "Search for '{}' returned {} posts",
+            query.query,
+            listing.data.children.len()
+        );
+        Ok(listing)
+    }
+
+    /// Fetch a post with its comment tree, expanding `more` stubs through
+    /// `/api/morechildren` until the limits in `options` are reached.
+    /// Expansion calls go through the rate limiter like any other request;
+    /// if one fails the tree is returned as far as it got.
+    pub async fn get_post_comments(
+        &self,
+        access_token: &str,
+        post_id: &str,
+        options: &CommentFetchOptions,
+    ) -> Result<CommentThread, CoreError> {
+        let post_id = post_id.trim_start_matches("t3_");
+        let endpoint = format!("/comments/{}", post_id);
+        let mut params = Vec::with_capacity(3);
+
+        let limit_str = options.limit.map(|l| l.to_string());
+        if let Some(ref limit_s) = limit_str {
+            params.push(("limit", limit_s.as_str()));
+        }
+        // Reddit counts depth from 1, our limit is the deepest 0-based level
+        let depth_str = options.max_depth.map(|d| (d + 1).to_string());
+        if let Some(ref depth_s) = depth_str {
+            params.push(("depth", depth_s.as_str()));
+        }
+        if let Some(ref sort) = options.sort {
+            params.push(("sort", sort.as_str()));
+        }
+
+        let query_params = if params.is_empty() {
+            None
+        } else {
+            Some(params.as_slice())
+        };
+
+        let response = self
+            .make_request_with_context(
+                Method::GET,
+                &endpoint,
+                access_token,
+                query_params,
+                Some("get_post_comments"),
+                None,
+                0,
+            )
+            .await?;
+
+        let invalid_response = || {
+            CoreError::RedditApi(RedditApiError::InvalidResponse {
+                details: format!("Failed to parse comments for post {}", post_id),
+            })
+        };
+
+        let body: serde_json::Value = response.json().await.map_err(|e| {
+            error!("Failed to parse comments: {}", e);
+            invalid_response()
+        })?;
+
+        // The response is a pair of listings: the post, then its comments
+        let (post_listing, comment_listing) = match body.as_array().map(Vec::as_slice) {
+            Some([post_listing, comment_listing]) => (post_listing, comment_listing),
+            _ => return Err(invalid_response()),
+        };
+
+        let post: RedditPost = serde_json::from_value::<RedditListing<RedditPostData>>(
+            post_listing.clone(),
+        )
+        .ok()
+        .and_then(|listing| listing.data.children.into_iter().next())
+        .map(|child| child.data.into())
+        .ok_or_else(invalid_response)?;
+
+        let mut builder = CommentTreeBuilder::new(options.clone());
+        builder.add_listing(comment_listing);
+
+        let mut more_requests = 0;
+        while more_requests < options.max_more_requests {
+            let Some(batch) = builder.next_more_batch() else {
+                break;
+            };
+            more_requests += 1;
+
+            match self
+                .get_more_children(
+                    access_token,
+                    post_id,
+                    &batch.children,
+                    options.sort.as_deref(),
+                )
+                .await
+            {
+                Ok(things) => builder.add_things(&things),
+                Err(e) => {
+                    warn!("Failed to expand comments for post {}: {}", post_id, e);
+                    builder.skip_batch(batch);
+                    break;
+                }
+            }
+        }
+
+        let thread = builder.finish(post);
+        info!(
+            "Retrieved {} comments for post {} ({} unexpanded, {} morechildren calls)",
+            thread.comment_count(),
+            post_id,
+            thread.unexpanded_count,
+            more_requests
+        );
+        Ok(thread)
+    }
+
+    /// Load comments hidden behind a `more` stub. Returns the raw things,
+    /// flattened with parents before their replies.
+    pub async fn get_more_children(
+        &self,
+        access_token: &str,
+        post_id: &str,
+        children: &[String],
+        sort: Option<&str>,
+    ) -> Result<Vec<serde_json::Value>, CoreError> {
+        let link_id = format!("t3_{}", post_id.trim_start_matches("t3_"));
+        let children = children.join(",");
+        let mut params = vec![
+            ("api_type", "json"),
+            ("link_id", link_id.as_str()),
+            ("children", children.as_str()),
+            ("limit_children", "false"),
+        ];
+        if let Some(sort) = sort {
+            params.push(("sort", sort));
+        }
+
+        let response = self
+            .make_request_with_context(
+                Method::GET,
+                "/api/morechildren",
+                access_token,
+                Some(params.as_slice()),
+                Some("get_more_children"),
+                None,
+                -1, // Expansion is less important than fresh listings
+            )
+            .await?;
+
+        let mut body: serde_json::Value = response.json().await.map_err(|e| {
+            error!("Failed to parse morechildren response: {}", e);
+            CoreError::RedditApi(RedditApiError::InvalidResponse {
+                details: "Failed to parse morechildren response".to_string(),
+            })
+        })?;
+
+        if let Some(errors) = body.pointer("/json/errors").and_then(|e| e.as_array()) {
+            if !errors.is_empty() {
+                return Err(CoreError::RedditApi(RedditApiError::InvalidResponse {
+                    details: format!("morechildren returned errors: {:?}", errors),
+                }));
+            }
+        }
+
+        match body.pointer_mut("/json/data/things").map(serde_json::Value::take) {
+            Some(serde_json::Value::Array(things)) => Ok(things),
+            _ => Ok(Vec::new()),
+        }
+    }
+
     pub async fn get_metrics(&self) -> crate::metrics::ApiMetrics {
         self.metrics.get_metrics().await
     }
 
+    /// The collector this client records every request into
+    pub fn metrics_collector(&self) -> Arc<MetricsCollector> {
+        self.metrics.clone()
+    }
+
     pub async fn get_rate_limit_status(&self) -> crate::rate_limiter::RateLimitStatus {
         self.rate_limiter.get_rate_limit_status().await
     }
@@ -681,9 +1268,10 @@ impl RedditApiClient {
         self.retry_executor.get_metrics()
     }
 
-    /// Get circuit breaker state
-    pub fn get_circuit_breaker_state(&self) -> crate::retry::CircuitBreakerState {
-        self.retry_executor.get_circuit_breaker_state()
+    /// Get the state of every circuit breaker, keyed by endpoint pattern and
+    /// subreddit (see [`crate::retry::circuit_key`])
+    pub fn get_circuit_breaker_state(&self) -> BTreeMap<String, CircuitBreakerState> {
+        self.retry_executor.get_circuit_breaker_states()
     }
 
     /// Reset retry metrics
@@ -729,6 +1317,7 @@ mod tests {
     async fn test_api_client_creation() {
         let client = RedditApiClient::new("test-user-agent/1.0".to_string());
         assert_eq!(client.user_agent, "test-user-agent/1.0");
+        assert_eq!(client.base_url, REDDIT_API_BASE);
 
         let status = client.get_rate_limit_status().await;
         assert!(status.available_tokens > 0);
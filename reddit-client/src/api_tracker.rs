@@ -1,5 +1,4 @@
 use crate::metrics::{MetricsCollector, RequestMetrics};
-use crate::rate_limiter::RateLimitStatus;
 use likeminded_core::CoreError;
 use serde::{Deserialize, Serialize};
 use sqlx::SqlitePool;
@@ -7,7 +6,7 @@ use std::collections::HashMap;
 use std::sync::Arc;
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use tokio::sync::RwLock;
-use tracing::{debug, error, info, warn};
+use tracing::{debug, info, warn};
 use uuid::Uuid;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -154,6 +153,7 @@ impl ApiTracker {
         Ok(())
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub async fn record_api_call(
         &self,
         endpoint: &str,
@@ -184,7 +184,7 @@ impl ApiTracker {
             response_size_bytes: None,
             rate_limited,
             retry_after_seconds: None,
-            error_type: if status_code.map_or(false, |s| s >= 400) {
+            error_type: if status_code.is_some_and(|s| s >= 400) {
                 Some(self.classify_error(status_code.unwrap()).to_string())
             } else {
                 None
@@ -210,7 +210,7 @@ impl ApiTracker {
             method: method.to_string(),
             status_code,
             response_time,
-            success: status_code.map_or(false, |s| s < 400),
+            success: status_code.is_some_and(|s| s < 400),
             rate_limited,
             error_type: record.error_type.clone(),
         };
@@ -264,9 +264,11 @@ impl ApiTracker {
         let window_duration = 60; // 1 minute window
         let window_start = (record.timestamp / window_duration) * window_duration;
         let window_end = window_start + window_duration;
+        let successful = i32::from(record.status_code.is_some_and(|s| s < 400));
+        let rate_limited = i32::from(record.rate_limited);
 
         // Update or create window record
-        let result = sqlx::query!(
+        sqlx::query!(
             r#"
             INSERT INTO rate_limit_windows (
                 window_start, window_end, window_duration_seconds,
@@ -283,21 +285,13 @@ impl ApiTracker {
             window_start,
             window_end,
             window_duration,
-            if record.status_code.map_or(false, |s| s < 400) {
-                1
-            } else {
-                0
-            },
-            if record.rate_limited { 1 } else { 0 },
+            successful,
+            rate_limited,
             record.response_time_ms,
             record.timestamp,
             record.timestamp,
-            if record.status_code.map_or(false, |s| s < 400) {
-                1
-            } else {
-                0
-            },
-            if record.rate_limited { 1 } else { 0 },
+            successful,
+            rate_limited,
             record.response_time_ms,
             record.timestamp
         )
@@ -344,6 +338,7 @@ impl ApiTracker {
         Ok(())
     }
 
+    #[allow(clippy::too_many_arguments)]
     async fn create_alert(
         &self,
         alert_type: &str,
@@ -401,10 +396,10 @@ impl ApiTracker {
             r#"
             SELECT 
                 COUNT(*) as total_requests,
-                SUM(CASE WHEN status_code < 400 THEN 1 ELSE 0 END) as successful_requests,
-                SUM(CASE WHEN status_code >= 400 THEN 1 ELSE 0 END) as failed_requests,
-                SUM(CASE WHEN rate_limited THEN 1 ELSE 0 END) as rate_limited_requests,
-                AVG(response_time_ms) as avg_response_time_ms
+                SUM(CASE WHEN status_code < 400 THEN 1 ELSE 0 END) as "successful_requests?: i64",
+                SUM(CASE WHEN status_code >= 400 THEN 1 ELSE 0 END) as "failed_requests?: i64",
+                SUM(CASE WHEN rate_limited THEN 1 ELSE 0 END) as "rate_limited_requests?: i64",
+                AVG(response_time_ms) as "avg_response_time_ms?: f64"
             FROM api_call_tracking 
             WHERE timestamp > ?
             "#,
@@ -582,7 +577,7 @@ impl ApiTracker {
         )
         .execute(&*self.pool)
         .await
-        .map_err(CoreError::Database)?
+        .map_err(|e| CoreError::Database(likeminded_core::DatabaseError::Sql(e)))?
         .rows_affected();
 
         // Clean up old rate limit windows
@@ -592,7 +587,7 @@ impl ApiTracker {
         )
         .execute(&*self.pool)
         .await
-        .map_err(CoreError::Database)?
+        .map_err(|e| CoreError::Database(likeminded_core::DatabaseError::Sql(e)))?
         .rows_affected();
 
         // Clean up resolved alerts older than 7 days
@@ -608,7 +603,7 @@ impl ApiTracker {
         )
         .execute(&*self.pool)
         .await
-        .map_err(CoreError::Database)?
+        .map_err(|e| CoreError::Database(likeminded_core::DatabaseError::Sql(e)))?
         .rows_affected();
 
         info!(
@@ -635,8 +630,8 @@ mod tests {
     use super::*;
     use std::time::Duration;
 
-    #[test]
-    fn test_error_classification() {
+    #[tokio::test]
+    async fn test_error_classification() {
         let pool = Arc::new(sqlx::SqlitePool::connect_lazy("sqlite::memory:").unwrap());
         let metrics = Arc::new(MetricsCollector::new());
         let tracker = ApiTracker::new(pool, metrics);
@@ -0,0 +1,200 @@
+//! Multireddit batching for subreddit listings.
+//!
+//! Reddit serves combined listings for `/r/a+b+c/new`, so polling many
+//! subreddits doesn't have to cost one request each. The planner here groups
+//! subreddit names into batches whose request URL stays under a length cap,
+//! and [`split_listing`] hands the combined results back out per subreddit.
+//!
+//! A combined listing is still capped at 100 posts per page, so a very busy
+//! subreddit can crowd quieter ones out of a batch. Callers that need every
+//! post should pair batching with high-water marks and follow-up pagination.
+
+use crate::api::{RedditListing, RedditListingData, RedditPostData};
+use std::collections::HashSet;
+
+/// Conservative cap on the full request URL, well under what Reddit accepts
+pub const DEFAULT_MAX_URL_LENGTH: usize = 2000;
+
+/// Reddit refuses multireddits with more subreddits than this
+pub const MAX_SUBREDDITS_PER_BATCH: usize = 100;
+
+/// Room kept for the API host and the query string (`?limit=100&after=...&t=...`)
+const URL_OVERHEAD: usize = 96;
+
+/// A group of subreddits fetched with a single combined listing request
+#[derive(Debug, Clone, PartialEq)]
+pub struct SubredditBatch {
+    pub subreddits: Vec<String>,
+}
+
+impl SubredditBatch {
+    /// The `a+b+c` path segment for this batch
+    pub fn multireddit_name(&self) -> String {
+        self.subreddits.join("+")
+    }
+
+    pub fn endpoint(&self, sort: &str) -> String {
+        format!("/r/{}/{}", self.multireddit_name(), sort)
+    }
+}
+
+/// Group subreddits into combined listing requests.
+///
+/// Names are deduplicated case-insensitively and keep their input order. A
+/// batch is closed once adding the next name would push the request URL past
+/// `max_url_length` or the batch would exceed [`MAX_SUBREDDITS_PER_BATCH`].
+pub fn plan_batches(subreddits: &[&str], sort: &str, max_url_length: usize) -> Vec<SubredditBatch> {
+    let base_length = format!("/r//{}", sort).len() + URL_OVERHEAD;
+    let budget = max_url_length.saturating_sub(base_length);
+
+    let mut seen = HashSet::new();
+    let mut batches = Vec::new();
+    let mut current: Vec<String> = Vec::new();
+    let mut current_length = 0;
+
+    for name in subreddits {
+        let name = name.trim().trim_start_matches("r/");
+        if name.is_empty() || !seen.insert(name.to_lowercase()) {
+            continue;
+        }
+
+        // A name that doesn't fit the budget on its own still gets a batch
+        if !current.is_empty()
+            && (current_length + 1 + name.len() > budget
+                || current.len() >= MAX_SUBREDDITS_PER_BATCH)
+        {
+            batches.push(SubredditBatch {
+                subreddits: std::mem::take(&mut current),
+            });
+        }
+
+        current_length = if current.is_empty() {
+            name.len()
+        } else {
+            // One extra character for the `+` separator
+            current_length + 1 + name.len()
+        };
+        current.push(name.to_string());
+    }
+
+    if !current.is_empty() {
+        batches.push(SubredditBatch {
+            subreddits: current,
+        });
+    }
+
+    batches
+}
+
+/// Split a combined listing into one listing per subreddit in `batch`.
+///
+/// Every subreddit in the batch gets an entry, possibly empty. The per
+/// subreddit listings carry no `after` cursor, since the combined cursor
+/// can't be used to page a single subreddit.
+pub fn split_listing(
+    batch: &SubredditBatch,
+    listing: RedditListing<RedditPostData>,
+) -> Vec<(String, RedditListing<RedditPostData>)> {
+    let mut split: Vec<(String, RedditListing<RedditPostData>)> = batch
+        .subreddits
+        .iter()
+        .map(|name| {
+            (
+                name.clone(),
+                RedditListing {
+                    kind: listing.kind.clone(),
+                    data: RedditListingData {
+                        children: Vec::new(),
+                        after: None,
+                        before: None,
+                        modhash: listing.data.modhash.clone(),
+                        dist: Some(0),
+                    },
+                },
+            )
+        })
+        .collect();
+
+    for child in listing.data.children {
+        if let Some((_, sub_listing)) = split
+            .iter_mut()
+            .find(|(name, _)| name.eq_ignore_ascii_case(&child.data.subreddit))
+        {
+            sub_listing.data.children.push(child);
+        }
+    }
+
+    for (_, sub_listing) in split.iter_mut() {
+        sub_listing.data.dist = Some(sub_listing.data.children.len() as u32);
+    }
+
+    split
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::RedditListingChild;
+    use crate::mock_server::MockRedditServer;
+
+    #[test]
+    fn test_plan_batches_respects_url_length() {
+        let names: Vec<String> = (0..50).map(|i| format!("subreddit{:02}", i)).collect();
+        let refs: Vec<&str> = names.iter().map(|s| s.as_str()).collect();
+
+        let batches = plan_batches(&refs, "new", 300);
+        assert!(batches.len() > 1);
+
+        for batch in &batches {
+            let url_length = batch.endpoint("new").len() + URL_OVERHEAD;
+            assert!(url_length <= 300, "batch too long: {}", url_length);
+        }
+
+        let total: usize = batches.iter().map(|b| b.subreddits.len()).sum();
+        assert_eq!(total, 50);
+        assert_eq!(batches[0].subreddits[0], "subreddit00");
+    }
+
+    #[test]
+    fn test_plan_batches_dedups_and_caps_batch_size() {
+        let batches = plan_batches(&["rust", "Rust", "r/programming", " "], "new", 2000);
+        assert_eq!(batches.len(), 1);
+        assert_eq!(batches[0].multireddit_name(), "rust+programming");
+        assert_eq!(batches[0].endpoint("new"), "/r/rust+programming/new");
+
+        let names: Vec<String> = (0..150).map(|i| format!("s{}", i)).collect();
+        let refs: Vec<&str> = names.iter().map(|s| s.as_str()).collect();
+        let batches = plan_batches(&refs, "new", 100_000);
+        assert_eq!(batches.len(), 2);
+        assert_eq!(batches[0].subreddits.len(), MAX_SUBREDDITS_PER_BATCH);
+    }
+
+    #[test]
+    fn test_split_listing() {
+        let batch = SubredditBatch {
+            subreddits: vec!["rust".to_string(), "golang".to_string()],
+        };
+        let child = |subreddit: &str, id: &str| RedditListingChild {
+            kind: "t3".to_string(),
+            data: MockRedditServer::sample_post(subreddit, id, 1_700_000_000),
+        };
+        let listing = RedditListing {
+            kind: "Listing".to_string(),
+            data: RedditListingData {
+                children: vec![child("Rust", "a"), child("rust", "b")],
+                after: Some("t3_b".to_string()),
+                before: None,
+                modhash: None,
+                dist: Some(2),
+            },
+        };
+
+        let split = split_listing(&batch, listing);
+        assert_eq!(split.len(), 2);
+        assert_eq!(split[0].0, "rust");
+        assert_eq!(split[0].1.data.children.len(), 2);
+        assert_eq!(split[0].1.data.after, None);
+        assert_eq!(split[1].0, "golang");
+        assert!(split[1].1.data.children.is_empty());
+    }
+}
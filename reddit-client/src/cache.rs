@@ -0,0 +1,469 @@
+//! HTTP response cache for GET requests.
+//!
+//! A [`CachePolicy`] maps endpoint patterns such as `/r/*/about` to a time to
+//! live. Responses for matching endpoints are kept in a [`CacheStore`]:
+//! while an entry is fresh it is served without touching the rate limiter,
+//! and once it goes stale the next request carries the stored `ETag` and
+//! `Last-Modified` validators so Reddit can answer with `304 Not Modified`.
+//!
+//! Responses such as `/r/{sub}/about` carry per-user fields, so entries are
+//! also keyed by who asked: the account set with
+//! [`ResponseCache::for_account`], or otherwise a fingerprint of the access
+//! token. Several accounts can then share one store.
+
+use likeminded_core::CoreError;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::future::Future;
+use std::hash::{Hash, Hasher};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+/// Boxed future returned by [`CacheStore`] methods
+pub type CacheStoreFuture<'a, T> = Pin<Box<dyn Future<Output = Result<T, CoreError>> + Send + 'a>>;
+
+/// Whether `endpoint` matches `pattern`. A `*` matches exactly one path
+/// segment, so `/r/*/about` matches `/r/rust/about` but not `/r/about`.
+pub fn pattern_matches(pattern: &str, endpoint: &str) -> bool {
+    let pattern_segments = pattern.trim_matches('/').split('/');
+    let endpoint_segments = endpoint.trim_matches('/').split('/');
+
+    pattern_segments.clone().count() == endpoint_segments.clone().count()
+        && pattern_segments
+            .zip(endpoint_segments)
+            .all(|(pattern, segment)| pattern == "*" || pattern.eq_ignore_ascii_case(segment))
+}
+
+#[derive(Debug, Clone)]
+struct CacheRule {
+    pattern: String,
+    ttl: Duration,
+}
+
+impl CacheRule {
+    fn wildcards(&self) -> usize {
+        self.pattern
+            .split('/')
+            .filter(|segment| *segment == "*")
+            .count()
+    }
+}
+
+/// Time to live per endpoint pattern
+#[derive(Debug, Clone, Default)]
+pub struct CachePolicy {
+    rules: Vec<CacheRule>,
+}
+
+impl CachePolicy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The TTLs seeded into `api_endpoint_configs`
+    pub fn reddit_defaults() -> Self {
+        Self::new()
+            .with_ttl("/r/*/hot", Duration::from_secs(60))
+            .with_ttl("/r/*/new", Duration::from_secs(60))
+            .with_ttl("/r/*/top", Duration::from_secs(300))
+            .with_ttl("/r/*/about", Duration::from_secs(3600))
+    }
+
+    /// Cache responses for endpoints matching `pattern` for `ttl`. A zero
+    /// TTL turns caching off for the pattern, overriding broader patterns.
+    pub fn with_ttl(mut self, pattern: &str, ttl: Duration) -> Self {
+        self.rules.retain(|rule| rule.pattern != pattern);
+        self.rules.push(CacheRule {
+            pattern: pattern.to_string(),
+            ttl,
+        });
+        self
+    }
+
+    /// TTL for `endpoint`, or `None` if it should not be cached. When
+    /// several patterns match, the one with the fewest wildcards wins.
+    pub fn ttl_for(&self, endpoint: &str) -> Option<Duration> {
+        self.rules
+            .iter()
+            .filter(|rule| pattern_matches(&rule.pattern, endpoint))
+            .min_by_key(|rule| rule.wildcards())
+            .map(|rule| rule.ttl)
+            .filter(|ttl| !ttl.is_zero())
+    }
+
+    /// Read TTLs from the `cache_ttl_seconds` column of active
+    /// `api_endpoint_configs` rows
+    #[cfg(feature = "database")]
+    pub async fn load(pool: &sqlx::SqlitePool) -> Result<Self, CoreError> {
+        use likeminded_core::DatabaseError;
+        use sqlx::Row;
+
+        let rows = sqlx::query(
+            "SELECT endpoint_pattern, cache_ttl_seconds FROM api_endpoint_configs WHERE is_active = TRUE",
+        )
+        .fetch_all(pool)
+        .await
+        .map_err(|e| CoreError::Database(DatabaseError::Sql(e)))?;
+
+        let mut policy = Self::new();
+        for row in rows {
+            let pattern: String = row.get("endpoint_pattern");
+            let ttl_seconds: i64 = row.get("cache_ttl_seconds");
+            policy = policy.with_ttl(&pattern, Duration::from_secs(ttl_seconds.max(0) as u64));
+        }
+        Ok(policy)
+    }
+}
+
+/// A stored response body with its validators
+#[derive(Debug, Clone, PartialEq)]
+pub struct CachedResponse {
+    pub status: u16,
+    pub body: Vec<u8>,
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+    /// When the response was fetched or last revalidated
+    pub stored_at: SystemTime,
+    pub expires_at: SystemTime,
+}
+
+impl CachedResponse {
+    pub fn is_fresh(&self, now: SystemTime) -> bool {
+        now < self.expires_at
+    }
+
+    /// Headers that make the next request conditional on this entry
+    pub fn conditional_headers(&self) -> Vec<(&'static str, String)> {
+        let mut headers = Vec::new();
+        if let Some(ref etag) = self.etag {
+            headers.push(("If-None-Match", etag.clone()));
+        }
+        if let Some(ref last_modified) = self.last_modified {
+            headers.push(("If-Modified-Since", last_modified.clone()));
+        }
+        headers
+    }
+}
+
+pub trait CacheStore: Send + Sync + std::fmt::Debug {
+    /// The entry for `key`, fresh or stale
+    fn get<'a>(&'a self, key: &'a str) -> CacheStoreFuture<'a, Option<CachedResponse>>;
+
+    /// Insert or replace the entry for `key`
+    fn put<'a>(&'a self, key: &'a str, response: &'a CachedResponse) -> CacheStoreFuture<'a, ()>;
+
+    /// Drop every entry
+    fn clear(&self) -> CacheStoreFuture<'_, ()>;
+}
+
+/// Keeps entries in memory for the lifetime of the client
+#[derive(Debug, Default)]
+pub struct MemoryCacheStore {
+    entries: Mutex<HashMap<String, CachedResponse>>,
+}
+
+impl MemoryCacheStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.lock().unwrap().is_empty()
+    }
+}
+
+impl CacheStore for MemoryCacheStore {
+    fn get<'a>(&'a self, key: &'a str) -> CacheStoreFuture<'a, Option<CachedResponse>> {
+        let entry = self.entries.lock().unwrap().get(key).cloned();
+        Box::pin(async move { Ok(entry) })
+    }
+
+    fn put<'a>(&'a self, key: &'a str, response: &'a CachedResponse) -> CacheStoreFuture<'a, ()> {
+        self.entries
+            .lock()
+            .unwrap()
+            .insert(key.to_string(), response.clone());
+        Box::pin(async { Ok(()) })
+    }
+
+    fn clear(&self) -> CacheStoreFuture<'_, ()> {
+        self.entries.lock().unwrap().clear();
+        Box::pin(async { Ok(()) })
+    }
+}
+
+#[cfg(feature = "database")]
+pub use sqlite::SqliteCacheStore;
+
+#[cfg(feature = "database")]
+mod sqlite {
+    use super::{CacheStore, CacheStoreFuture, CachedResponse};
+    use likeminded_core::{CoreError, DatabaseError};
+    use sqlx::{Row, SqlitePool};
+    use std::sync::Arc;
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+    /// Stores entries in the `http_response_cache` table so they survive
+    /// restarts
+    #[derive(Debug, Clone)]
+    pub struct SqliteCacheStore {
+        pool: Arc<SqlitePool>,
+    }
+
+    impl SqliteCacheStore {
+        pub fn new(pool: Arc<SqlitePool>) -> Self {
+            Self { pool }
+        }
+
+        /// Delete entries that went stale more than `max_age` ago. Stale
+        /// entries are still useful for revalidation, so this is only
+        /// needed to keep the table from growing without bound.
+        pub async fn purge_stale(&self, max_age: Duration) -> Result<u64, CoreError> {
+            let cutoff = to_unix(SystemTime::now()).saturating_sub(max_age.as_secs() as i64);
+            let result = sqlx::query("DELETE FROM http_response_cache WHERE expires_at < ?")
+                .bind(cutoff)
+                .execute(self.pool.as_ref())
+                .await
+                .map_err(sql_error)?;
+            Ok(result.rows_affected())
+        }
+
+        async fn get_entry(&self, key: &str) -> Result<Option<CachedResponse>, CoreError> {
+            let row = sqlx::query(
+                "SELECT status_code, body, etag, last_modified, stored_at, expires_at
+                 FROM http_response_cache WHERE cache_key = ?",
+            )
+            .bind(key)
+            .fetch_optional(self.pool.as_ref())
+            .await
+            .map_err(sql_error)?;
+
+            Ok(row.map(|row| CachedResponse {
+                status: row.get::<i64, _>("status_code") as u16,
+                body: row.get("body"),
+                etag: row.get("etag"),
+                last_modified: row.get("last_modified"),
+                stored_at: from_unix(row.get("stored_at")),
+                expires_at: from_unix(row.get("expires_at")),
+            }))
+        }
+
+        async fn put_entry(&self, key: &str, response: &CachedResponse) -> Result<(), CoreError> {
+            sqlx::query(
+                "INSERT OR REPLACE INTO http_response_cache
+                 (cache_key, status_code, body, etag, last_modified, stored_at, expires_at)
+                 VALUES (?, ?, ?, ?, ?, ?, ?)",
+            )
+            .bind(key)
+            .bind(response.status as i64)
+            .bind(&response.body)
+            .bind(&response.etag)
+            .bind(&response.last_modified)
+            .bind(to_unix(response.stored_at))
+            .bind(to_unix(response.expires_at))
+            .execute(self.pool.as_ref())
+            .await
+            .map_err(sql_error)?;
+            Ok(())
+        }
+    }
+
+    impl CacheStore for SqliteCacheStore {
+        fn get<'a>(&'a self, key: &'a str) -> CacheStoreFuture<'a, Option<CachedResponse>> {
+            Box::pin(self.get_entry(key))
+        }
+
+        fn put<'a>(
+            &'a self,
+            key: &'a str,
+            response: &'a CachedResponse,
+        ) -> CacheStoreFuture<'a, ()> {
+            Box::pin(self.put_entry(key, response))
+        }
+
+        fn clear(&self) -> CacheStoreFuture<'_, ()> {
+            Box::pin(async move {
+                sqlx::query("DELETE FROM http_response_cache")
+                    .execute(self.pool.as_ref())
+                    .await
+                    .map_err(sql_error)?;
+                Ok(())
+            })
+        }
+    }
+
+    fn to_unix(time: SystemTime) -> i64 {
+        time.duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs() as i64
+    }
+
+    fn from_unix(secs: i64) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(secs.max(0) as u64)
+    }
+
+    fn sql_error(e: sqlx::Error) -> CoreError {
+        CoreError::Database(DatabaseError::Sql(e))
+    }
+}
+
+/// Policy plus storage, attached to [`crate::api::RedditApiClient`] with
+/// [`crate::api::RedditApiClient::with_response_cache`]
+#[derive(Debug, Clone)]
+pub struct ResponseCache {
+    policy: CachePolicy,
+    store: Arc<dyn CacheStore>,
+    account_id: Option<i64>,
+}
+
+impl ResponseCache {
+    pub fn new(policy: CachePolicy, store: Arc<dyn CacheStore>) -> Self {
+        Self {
+            policy,
+            store,
+            account_id: None,
+        }
+    }
+
+    /// Key entries by `account_id` (a `reddit_accounts` row ID) instead of
+    /// by access token, so they survive token refreshes
+    pub fn for_account(mut self, account_id: i64) -> Self {
+        self.account_id = Some(account_id);
+        self
+    }
+
+    /// Cache held in memory only
+    pub fn in_memory(policy: CachePolicy) -> Self {
+        Self::new(policy, Arc::new(MemoryCacheStore::new()))
+    }
+
+    pub fn policy(&self) -> &CachePolicy {
+        &self.policy
+    }
+
+    pub fn store(&self) -> &Arc<dyn CacheStore> {
+        &self.store
+    }
+
+    /// Who a response sent with `access_token` belongs to
+    pub(crate) fn scope(&self, access_token: &str) -> String {
+        match self.account_id {
+            Some(account_id) => format!("account:{}", account_id),
+            None => {
+                let mut hasher = DefaultHasher::new();
+                access_token.hash(&mut hasher);
+                format!("token:{:016x}", hasher.finish())
+            }
+        }
+    }
+}
+
+/// Key for a GET of `endpoint` with `query_params` on behalf of `scope`.
+/// Parameters are sorted so the key does not depend on the order callers add
+/// them in.
+pub(crate) fn cache_key(
+    scope: &str,
+    endpoint: &str,
+    query_params: Option<&[(&str, &str)]>,
+) -> String {
+    let mut params: Vec<_> = query_params.unwrap_or_default().to_vec();
+    if params.is_empty() {
+        return format!("{} {}", scope, endpoint);
+    }
+
+    params.sort();
+    let query = params
+        .iter()
+        .map(|(key, value)| format!("{}={}", key, value))
+        .collect::<Vec<_>>()
+        .join("&");
+    format!("{} {}?{}", scope, endpoint, query)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pattern_matches_single_segments() {
+        assert!(pattern_matches("/r/*/about", "/r/rust/about"));
+        assert!(pattern_matches("/r/*/hot", "/r/rust+golang/hot"));
+        assert!(pattern_matches("/api/v1/me", "/api/v1/me"));
+        assert!(!pattern_matches("/r/*/about", "/r/about"));
+        assert!(!pattern_matches("/r/*/about", "/r/rust/about/rules"));
+        assert!(!pattern_matches("/r/*/hot", "/r/rust/new"));
+    }
+
+    #[test]
+    fn test_most_specific_pattern_wins() {
+        let policy = CachePolicy::new()
+            .with_ttl("/r/*/*", Duration::from_secs(30))
+            .with_ttl("/r/*/about", Duration::from_secs(3600))
+            .with_ttl("/r/*/new", Duration::ZERO);
+
+        assert_eq!(
+            policy.ttl_for("/r/rust/about"),
+            Some(Duration::from_secs(3600))
+        );
+        assert_eq!(policy.ttl_for("/r/rust/hot"), Some(Duration::from_secs(30)));
+        assert_eq!(policy.ttl_for("/r/rust/new"), None);
+        assert_eq!(policy.ttl_for("/api/v1/me"), None);
+    }
+
+    #[test]
+    fn test_cache_key_ignores_param_order() {
+        assert_eq!(
+            cache_key(
+                "account:1",
+                "/r/rust/top",
+                Some(&[("t", "day"), ("limit", "25")])
+            ),
+            cache_key(
+                "account:1",
+                "/r/rust/top",
+                Some(&[("limit", "25"), ("t", "day")])
+            )
+        );
+        assert_eq!(
+            cache_key("account:1", "/r/rust/about", None),
+            "account:1 /r/rust/about"
+        );
+    }
+
+    #[test]
+    fn test_cache_scope_separates_users() {
+        let cache = ResponseCache::in_memory(CachePolicy::reddit_defaults());
+        assert_eq!(cache.scope("token-a"), cache.scope("token-a"));
+        assert_ne!(cache.scope("token-a"), cache.scope("token-b"));
+
+        // An account keeps its entries across token refreshes
+        let cache = cache.for_account(2);
+        assert_eq!(cache.scope("token-a"), "account:2");
+        assert_eq!(cache.scope("token-b"), "account:2");
+    }
+
+    #[test]
+    fn test_conditional_headers() {
+        let now = SystemTime::now();
+        let response = CachedResponse {
+            status: 200,
+            body: b"{}".to_vec(),
+            etag: Some("\"abc\"".to_string()),
+            last_modified: None,
+            stored_at: now,
+            expires_at: now + Duration::from_secs(60),
+        };
+
+        assert!(response.is_fresh(now));
+        assert!(!response.is_fresh(now + Duration::from_secs(60)));
+        assert_eq!(
+            response.conditional_headers(),
+            vec![("If-None-Match", "\"abc\"".to_string())]
+        );
+    }
+}
@@ -0,0 +1,445 @@
+//! Comment trees for posts.
+//!
+//! `/comments/{id}` returns the post followed by a tree of comments in which
+//! long or deep threads are cut off by `more` stubs that only list the ids of
+//! the missing comments. [`CommentTreeBuilder`] assembles the tree, applies
+//! the limits from [`CommentFetchOptions`] and queues the stubs so they can be
+//! expanded through `/api/morechildren`.
+
+use likeminded_core::{RedditComment, RedditPost};
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::VecDeque;
+
+/// Most comment ids `/api/morechildren` accepts in one call
+pub const MAX_MORE_CHILDREN_PER_REQUEST: usize = 100;
+
+/// Limits on how much of a comment tree is fetched
+#[derive(Debug, Clone)]
+pub struct CommentFetchOptions {
+    /// Comment sort, e.g. `top`, `best` or `new`
+    pub sort: Option<String>,
+    /// Number of comments Reddit puts in the initial response
+    pub limit: Option<u32>,
+    /// Deepest nesting level kept, 0 keeps only top-level comments
+    pub max_depth: Option<u32>,
+    /// Stop collecting once this many comments are in the tree
+    pub max_comments: Option<usize>,
+    /// Cap on `/api/morechildren` calls made to expand `more` stubs
+    pub max_more_requests: usize,
+}
+
+impl Default for CommentFetchOptions {
+    fn default() -> Self {
+        Self {
+            sort: None,
+            limit: None,
+            max_depth: None,
+            max_comments: None,
+            max_more_requests: 5,
+        }
+    }
+}
+
+impl CommentFetchOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_sort(mut self, sort: impl Into<String>) -> Self {
+        self.sort = Some(sort.into());
+        self
+    }
+
+    pub fn with_limit(mut self, limit: u32) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    pub fn with_max_depth(mut self, max_depth: u32) -> Self {
+        self.max_depth = Some(max_depth);
+        self
+    }
+
+    pub fn with_max_comments(mut self, max_comments: usize) -> Self {
+        self.max_comments = Some(max_comments);
+        self
+    }
+
+    pub fn with_max_more_requests(mut self, max_more_requests: usize) -> Self {
+        self.max_more_requests = max_more_requests;
+        self
+    }
+}
+
+/// A post with its comment tree
+#[derive(Debug, Clone)]
+pub struct CommentThread {
+    pub post: RedditPost,
+    pub comments: Vec<RedditComment>,
+    /// Comments Reddit reported behind `more` stubs that were not expanded
+    pub unexpanded_count: u32,
+}
+
+impl CommentThread {
+    /// Number of comments loaded, across all nesting levels
+    pub fn comment_count(&self) -> usize {
+        self.comments
+            .iter()
+            .map(|comment| comment.thread_size())
+            .sum()
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct CommentData {
+    id: String,
+    parent_id: String,
+    link_id: String,
+    #[serde(default)]
+    author: String,
+    #[serde(default)]
+    body: String,
+    #[serde(default)]
+    score: i32,
+    #[serde(default)]
+    created_utc: f64,
+    #[serde(default)]
+    depth: u32,
+    #[serde(default)]
+    stickied: bool,
+    /// Either a listing or an empty string when there are no replies
+    #[serde(default)]
+    replies: Value,
+}
+
+#[derive(Debug, Deserialize)]
+struct MoreData {
+    #[serde(default)]
+    count: u32,
+    parent_id: String,
+    #[serde(default)]
+    depth: u32,
+    #[serde(default)]
+    children: Vec<String>,
+}
+
+/// Comment ids taken from the queued `more` stubs for one expansion call
+#[derive(Debug, Clone)]
+pub(crate) struct MoreBatch {
+    pub children: Vec<String>,
+    pub count: u32,
+}
+
+#[derive(Debug)]
+pub(crate) struct CommentTreeBuilder {
+    options: CommentFetchOptions,
+    comments: Vec<RedditComment>,
+    pending: VecDeque<MoreData>,
+    total: usize,
+    unexpanded_count: u32,
+}
+
+impl CommentTreeBuilder {
+    pub fn new(options: CommentFetchOptions) -> Self {
+        Self {
+            options,
+            comments: Vec::new(),
+            pending: VecDeque::new(),
+            total: 0,
+            unexpanded_count: 0,
+        }
+    }
+
+    /// Add the comment listing from a `/comments/{id}` response
+    pub fn add_listing(&mut self, listing: &Value) {
+        let comments = self.parse_listing(listing);
+        self.comments.extend(comments);
+    }
+
+    /// Add the flat list of things returned by `/api/morechildren`.
+    /// Parents always come before their replies, so each comment can be
+    /// attached as soon as it is read.
+    pub fn add_things(&mut self, things: &[Value]) {
+        for thing in things {
+            match thing.get("kind").and_then(Value::as_str) {
+                Some("t1") => {
+                    let Some(comment) = self.parse_comment(thing) else {
+                        continue;
+                    };
+                    if comment.is_top_level() {
+                        self.comments.push(comment);
+                    } else {
+                        let parent = comment.parent_id.trim_start_matches("t1_").to_string();
+                        match find_comment_mut(&mut self.comments, &parent) {
+                            Some(parent) => parent.replies.push(comment),
+                            // The parent was dropped by a limit, so is its reply
+                            None => self.total -= comment.thread_size(),
+                        }
+                    }
+                }
+                Some("more") => self.queue_more(thing),
+                _ => {}
+            }
+        }
+    }
+
+    /// Take the next ids to expand, or `None` once nothing is left or the
+    /// comment cap has been reached
+    pub fn next_more_batch(&mut self) -> Option<MoreBatch> {
+        if self.is_full() {
+            return None;
+        }
+
+        let mut batch = MoreBatch {
+            children: Vec::new(),
+            count: 0,
+        };
+
+        while let Some(mut stub) = self.pending.pop_front() {
+            let room = MAX_MORE_CHILDREN_PER_REQUEST - batch.children.len();
+            if stub.children.len() > room {
+                // Split the stub and leave the rest for the next call
+                let rest = stub.children.split_off(room);
+                self.pending.push_front(MoreData {
+                    count: rest.len() as u32,
+                    parent_id: stub.parent_id.clone(),
+                    depth: stub.depth,
+                    children: rest,
+                });
+                batch.count += stub.children.len() as u32;
+            } else {
+                batch.count += stub.count.max(stub.children.len() as u32);
+            }
+            batch.children.extend(stub.children);
+
+            if batch.children.len() >= MAX_MORE_CHILDREN_PER_REQUEST {
+                break;
+            }
+        }
+
+        if batch.children.is_empty() {
+            None
+        } else {
+            Some(batch)
+        }
+    }
+
+    /// Record a batch that could not be expanded
+    pub fn skip_batch(&mut self, batch: MoreBatch) {
+        self.unexpanded_count += batch.count;
+    }
+
+    pub fn finish(self, post: RedditPost) -> CommentThread {
+        let pending_count: u32 = self
+            .pending
+            .iter()
+            .map(|stub| stub.count.max(stub.children.len() as u32))
+            .sum();
+
+        CommentThread {
+            post,
+            comments: self.comments,
+            unexpanded_count: self.unexpanded_count + pending_count,
+        }
+    }
+
+    fn is_full(&self) -> bool {
+        matches!(self.options.max_comments, Some(max) if self.total >= max)
+    }
+
+    fn parse_listing(&mut self, listing: &Value) -> Vec<RedditComment> {
+        let children = listing
+            .pointer("/data/children")
+            .and_then(Value::as_array)
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+
+        let mut comments = Vec::new();
+        for child in children {
+            match child.get("kind").and_then(Value::as_str) {
+                Some("t1") => comments.extend(self.parse_comment(child)),
+                Some("more") => self.queue_more(child),
+                _ => {}
+            }
+        }
+        comments
+    }
+
+    /// Convert a `t1` thing, including any nested replies. Returns `None` if
+    /// the comment falls outside the depth or count limits.
+    fn parse_comment(&mut self, thing: &Value) -> Option<RedditComment> {
+        let data: CommentData = serde_json::from_value(thing.get("data")?.clone()).ok()?;
+
+        if matches!(self.options.max_depth, Some(max) if data.depth > max) || self.is_full() {
+            return None;
+        }
+        self.total += 1;
+
+        let replies = if data.replies.is_object() {
+            self.parse_listing(&data.replies)
+        } else {
+            Vec::new()
+        };
+
+        Some(RedditComment {
+            id: data.id,
+            post_id: data.link_id.trim_start_matches("t3_").to_string(),
+            parent_id: data.parent_id,
+            author: data.author,
+            body: data.body,
+            score: data.score,
+            created_utc: data.created_utc as i64,
+            depth: data.depth,
+            stickied: data.stickied,
+            replies,
+        })
+    }
+
+    fn queue_more(&mut self, thing: &Value) {
+        let Some(stub) = thing
+            .get("data")
+            .and_then(|data| serde_json::from_value::<MoreData>(data.clone()).ok())
+        else {
+            return;
+        };
+
+        // "Continue this thread" stubs carry no ids and can't be expanded
+        // through morechildren; stubs past the depth limit are never wanted
+        if stub.children.is_empty()
+            || matches!(self.options.max_depth, Some(max) if stub.depth > max)
+        {
+            self.unexpanded_count += stub.count;
+        } else {
+            self.pending.push_back(stub);
+        }
+    }
+}
+
+fn find_comment_mut<'a>(
+    comments: &'a mut [RedditComment],
+    id: &str,
+) -> Option<&'a mut RedditComment> {
+    for comment in comments {
+        if comment.id == id {
+            return Some(comment);
+        }
+        if let Some(found) = find_comment_mut(&mut comment.replies, id) {
+            return Some(found);
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn comment(id: &str, parent_id: &str, depth: u32, replies: Value) -> Value {
+        json!({
+            "kind": "t1",
+            "data": {
+                "id": id,
+                "parent_id": parent_id,
+                "link_id": "t3_post",
+                "author": "someone",
+                "body": format!("comment {}", id),
+                "score": 1,
+                "created_utc": 1_700_000_000.0,
+                "depth": depth,
+                "replies": replies,
+            }
+        })
+    }
+
+    fn listing(children: Vec<Value>) -> Value {
+        json!({ "kind": "Listing", "data": { "children": children } })
+    }
+
+    fn more(parent_id: &str, depth: u32, children: &[&str]) -> Value {
+        json!({
+            "kind": "more",
+            "data": {
+                "count": children.len(),
+                "parent_id": parent_id,
+                "depth": depth,
+                "children": children,
+            }
+        })
+    }
+
+    fn sample_tree() -> Value {
+        listing(vec![
+            comment(
+                "a",
+                "t3_post",
+                0,
+                listing(vec![
+                    comment("a1", "t1_a", 1, json!("")),
+                    more("t1_a", 1, &["a2"]),
+                ]),
+            ),
+            comment("b", "t3_post", 0, json!("")),
+            more("t3_post", 0, &["c", "d"]),
+        ])
+    }
+
+    #[test]
+    fn test_builds_tree_and_expands_more_stubs() {
+        let mut builder = CommentTreeBuilder::new(CommentFetchOptions::new());
+        builder.add_listing(&sample_tree());
+
+        let batch = builder.next_more_batch().unwrap();
+        assert_eq!(batch.children, vec!["a2", "c", "d"]);
+        assert!(builder.next_more_batch().is_none());
+
+        builder.add_things(&[
+            comment("a2", "t1_a", 1, json!("")),
+            comment("c", "t3_post", 0, json!("")),
+            comment("c1", "t1_c", 1, json!("")),
+            comment("d", "t3_post", 0, json!("")),
+        ]);
+
+        let comments = builder.comments.clone();
+        assert_eq!(comments.len(), 4);
+        assert_eq!(comments[0].replies.len(), 2);
+        assert_eq!(comments[2].replies[0].id, "c1");
+        assert_eq!(comments[2].replies[0].post_id, "post");
+        assert_eq!(builder.total, 7);
+    }
+
+    #[test]
+    fn test_depth_and_count_limits() {
+        let mut builder = CommentTreeBuilder::new(CommentFetchOptions::new().with_max_depth(0));
+        builder.add_listing(&sample_tree());
+
+        assert!(builder.comments.iter().all(|c| c.replies.is_empty()));
+        let batch = builder.next_more_batch().unwrap();
+        assert_eq!(batch.children, vec!["c", "d"]);
+        assert_eq!(builder.unexpanded_count, 1);
+
+        let mut builder = CommentTreeBuilder::new(CommentFetchOptions::new().with_max_comments(2));
+        builder.add_listing(&sample_tree());
+        assert_eq!(builder.total, 2);
+        assert!(builder.next_more_batch().is_none());
+    }
+
+    #[test]
+    fn test_large_stubs_are_split_across_requests() {
+        let ids: Vec<String> = (0..150).map(|i| format!("c{}", i)).collect();
+        let refs: Vec<&str> = ids.iter().map(|s| s.as_str()).collect();
+
+        let mut builder = CommentTreeBuilder::new(CommentFetchOptions::new());
+        builder.add_listing(&listing(vec![more("t3_post", 0, &refs)]));
+
+        let first = builder.next_more_batch().unwrap();
+        assert_eq!(first.children.len(), MAX_MORE_CHILDREN_PER_REQUEST);
+        let second = builder.next_more_batch().unwrap();
+        assert_eq!(second.children.len(), 50);
+        assert_eq!(second.children[0], "c100");
+
+        builder.skip_batch(second);
+        assert_eq!(builder.unexpanded_count, 50);
+    }
+}
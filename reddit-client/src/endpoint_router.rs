@@ -0,0 +1,333 @@
+//! Per-endpoint limits from the `api_endpoint_configs` table.
+//!
+//! Each row names an endpoint pattern such as `/r/*/new` (see
+//! [`crate::cache::pattern_matches`]) with its own per-minute and per-hour
+//! request budget, request timeout and retry count. [`EndpointRouter`] keeps a
+//! token bucket per pattern; a request has to get past its pattern's buckets
+//! before it draws on the global [`crate::rate_limiter::RateLimiter`], so a
+//! noisy endpoint cannot use up the budget the others need.
+
+use crate::cache::pattern_matches;
+use crate::rate_limiter::{RateLimitConfig, TokenBucket};
+use std::time::{Duration, Instant};
+use tokio::time::sleep;
+
+/// Pattern for an endpoint no route matches: subreddit and user names and
+/// everything after `comments` become `*`, e.g. `/r/rust/comments/abc` is
+/// `/r/*/comments/*`. Keeps per-endpoint state (circuit breakers, metric
+/// labels) to one entry per kind of call instead of one per subreddit or post.
+pub fn endpoint_pattern(endpoint: &str) -> String {
+    let path = endpoint.split('?').next().unwrap_or_default();
+    let mut segments = Vec::new();
+    let mut wildcard_next = false;
+    let mut wildcard_rest = false;
+
+    for segment in path.split('/') {
+        if segment.is_empty() {
+            segments.push(segment);
+            continue;
+        }
+        if wildcard_rest || wildcard_next {
+            segments.push("*");
+            wildcard_next = false;
+            continue;
+        }
+        segments.push(segment);
+        match segment {
+            "r" | "u" | "user" => wildcard_next = true,
+            "comments" | "duplicates" => wildcard_rest = true,
+            _ => {}
+        }
+    }
+
+    segments.join("/")
+}
+
+/// Limits for one endpoint pattern
+#[derive(Debug, Clone, PartialEq)]
+pub struct EndpointLimits {
+    pub pattern: String,
+    pub rate_limit_per_minute: u32,
+    pub rate_limit_per_hour: Option<u32>,
+    pub timeout: Duration,
+    /// Retries after the first attempt
+    pub max_retries: u32,
+}
+
+impl EndpointLimits {
+    /// Limits with the table's default timeout (30s) and retry count (3)
+    pub fn new(pattern: &str, rate_limit_per_minute: u32) -> Self {
+        Self {
+            pattern: pattern.to_string(),
+            rate_limit_per_minute,
+            rate_limit_per_hour: None,
+            timeout: Duration::from_secs(30),
+            max_retries: 3,
+        }
+    }
+
+    pub fn with_hourly_limit(mut self, rate_limit_per_hour: u32) -> Self {
+        self.rate_limit_per_hour = Some(rate_limit_per_hour);
+        self
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn with_max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    /// Total attempts, counting the first one
+    pub fn max_attempts(&self) -> u32 {
+        self.max_retries + 1
+    }
+
+    fn wildcards(&self) -> usize {
+        self.pattern
+            .split('/')
+            .filter(|segment| *segment == "*")
+            .count()
+    }
+}
+
+/// An endpoint pattern with its own token buckets
+#[derive(Debug)]
+pub struct EndpointRoute {
+    limits: EndpointLimits,
+    per_minute: TokenBucket,
+    per_hour: Option<TokenBucket>,
+}
+
+impl EndpointRoute {
+    fn new(limits: EndpointLimits) -> Self {
+        let per_minute = TokenBucket::new(&RateLimitConfig {
+            max_requests: limits.rate_limit_per_minute.max(1),
+            time_window: Duration::from_secs(60),
+            burst_allowance: limits.rate_limit_per_minute.max(1),
+        });
+        let per_hour = limits.rate_limit_per_hour.map(|per_hour| {
+            TokenBucket::new(&RateLimitConfig {
+                max_requests: per_hour.max(1),
+                time_window: Duration::from_secs(3600),
+                burst_allowance: per_hour.max(1),
+            })
+        });
+
+        Self {
+            limits,
+            per_minute,
+            per_hour,
+        }
+    }
+
+    pub fn limits(&self) -> &EndpointLimits {
+        &self.limits
+    }
+
+    /// Take a token from every bucket, or report how long to wait. Tokens
+    /// are only taken when all buckets have one to give.
+    pub async fn try_acquire(&self) -> Result<(), Duration> {
+        if let Some(ref per_hour) = self.per_hour {
+            if per_hour.get_available_tokens().await < 1.0 {
+                return per_hour.acquire(1.0).await;
+            }
+        }
+        self.per_minute.acquire(1.0).await?;
+        if let Some(ref per_hour) = self.per_hour {
+            per_hour.acquire(1.0).await?;
+        }
+        Ok(())
+    }
+
+    /// Wait until the pattern's budget allows another request. Returns how
+    /// long the caller waited.
+    pub async fn acquire(&self) -> Duration {
+        let start_time = Instant::now();
+        while let Err(wait_time) = self.try_acquire().await {
+            tracing::debug!(
+                "Endpoint limit for {} reached, waiting {:?}",
+                self.limits.pattern,
+                wait_time
+            );
+            sleep(wait_time).await;
+        }
+        start_time.elapsed()
+    }
+
+    /// Requests the pattern could make right now
+    pub async fn available_tokens(&self) -> u32 {
+        let per_minute = self.per_minute.get_available_tokens().await;
+        let available = match self.per_hour {
+            Some(ref per_hour) => per_minute.min(per_hour.get_available_tokens().await),
+            None => per_minute,
+        };
+        available as u32
+    }
+}
+
+/// Finds the [`EndpointRoute`] for a request path
+#[derive(Debug, Default)]
+pub struct EndpointRouter {
+    routes: Vec<EndpointRoute>,
+}
+
+impl EndpointRouter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The rows seeded into `api_endpoint_configs`
+    pub fn reddit_defaults() -> Self {
+        Self::new()
+            .with_endpoint(EndpointLimits::new("/api/v1/me", 10).with_hourly_limit(100))
+            .with_endpoint(EndpointLimits::new("/r/*/hot", 30).with_hourly_limit(1000))
+            .with_endpoint(EndpointLimits::new("/r/*/new", 30).with_hourly_limit(1000))
+            .with_endpoint(EndpointLimits::new("/r/*/top", 30).with_hourly_limit(1000))
+            .with_endpoint(EndpointLimits::new("/r/*/about", 20).with_hourly_limit(500))
+            .with_endpoint(
+                EndpointLimits::new("/subreddits/mine/subscriber", 5).with_hourly_limit(50),
+            )
+    }
+
+    /// Add or replace the limits for a pattern
+    pub fn with_endpoint(mut self, limits: EndpointLimits) -> Self {
+        self.routes
+            .retain(|route| route.limits.pattern != limits.pattern);
+        self.routes.push(EndpointRoute::new(limits));
+        self
+    }
+
+    /// Load the active rows of `api_endpoint_configs`
+    #[cfg(feature = "database")]
+    pub async fn load(pool: &sqlx::SqlitePool) -> Result<Self, likeminded_core::CoreError> {
+        use likeminded_core::{CoreError, DatabaseError};
+        use sqlx::Row;
+
+        let rows = sqlx::query(
+            "SELECT endpoint_pattern, rate_limit_per_minute, rate_limit_per_hour, timeout_seconds, max_retries
+             FROM api_endpoint_configs WHERE is_active = TRUE",
+        )
+        .fetch_all(pool)
+        .await
+        .map_err(|e| CoreError::Database(DatabaseError::Sql(e)))?;
+
+        let mut router = Self::new();
+        for row in rows {
+            let pattern: String = row.get("endpoint_pattern");
+            let per_minute: i64 = row.get("rate_limit_per_minute");
+            let per_hour: Option<i64> = row.get("rate_limit_per_hour");
+            let timeout_seconds: i64 = row.get("timeout_seconds");
+            let max_retries: i64 = row.get("max_retries");
+
+            let mut limits = EndpointLimits::new(&pattern, per_minute.max(1) as u32)
+                .with_timeout(Duration::from_secs(timeout_seconds.max(1) as u64))
+                .with_max_retries(max_retries.max(0) as u32);
+            if let Some(per_hour) = per_hour {
+                limits = limits.with_hourly_limit(per_hour.max(1) as u32);
+            }
+            router = router.with_endpoint(limits);
+        }
+
+        tracing::debug!("Loaded limits for {} endpoint patterns", router.len());
+        Ok(router)
+    }
+
+    /// The route for `endpoint`. When several patterns match, the one with
+    /// the fewest wildcards wins.
+    pub fn route(&self, endpoint: &str) -> Option<&EndpointRoute> {
+        self.routes
+            .iter()
+            .filter(|route| pattern_matches(&route.limits.pattern, endpoint))
+            .min_by_key(|route| route.limits.wildcards())
+    }
+
+    /// The pattern of the route for `endpoint`, or [`endpoint_pattern`] when
+    /// no route matches
+    pub fn pattern_for(&self, endpoint: &str) -> String {
+        match self.route(endpoint) {
+            Some(route) => route.limits.pattern.clone(),
+            None => endpoint_pattern(endpoint),
+        }
+    }
+
+    pub fn routes(&self) -> impl Iterator<Item = &EndpointRoute> {
+        self.routes.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.routes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.routes.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_endpoint_pattern_hides_names_and_ids() {
+        assert_eq!(endpoint_pattern("/r/rust/new"), "/r/*/new");
+        assert_eq!(endpoint_pattern("/r/rust+golang/hot"), "/r/*/hot");
+        assert_eq!(endpoint_pattern("/comments/abc123"), "/comments/*");
+        assert_eq!(
+            endpoint_pattern("/r/rust/comments/abc123/some_title/"),
+            "/r/*/comments/*/*/"
+        );
+        assert_eq!(endpoint_pattern("/user/alice/about"), "/user/*/about");
+        assert_eq!(endpoint_pattern("/api/v1/me"), "/api/v1/me");
+        assert_eq!(endpoint_pattern("/search?q=rust"), "/search");
+
+        let router = EndpointRouter::new().with_endpoint(EndpointLimits::new("/r/*/*", 60));
+        assert_eq!(router.pattern_for("/r/rust/new"), "/r/*/*");
+        assert_eq!(router.pattern_for("/comments/abc123"), "/comments/*");
+    }
+
+    #[test]
+    fn test_routes_to_most_specific_pattern() {
+        let router = EndpointRouter::new()
+            .with_endpoint(EndpointLimits::new("/r/*/*", 60))
+            .with_endpoint(EndpointLimits::new("/r/*/new", 30).with_max_retries(1));
+
+        let route = router.route("/r/rust/new").unwrap();
+        assert_eq!(route.limits().pattern, "/r/*/new");
+        assert_eq!(route.limits().max_attempts(), 2);
+        assert_eq!(
+            router.route("/r/rust/hot").unwrap().limits().pattern,
+            "/r/*/*"
+        );
+        assert!(router.route("/api/v1/me").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_pattern_budget_is_enforced() {
+        let router = EndpointRouter::new()
+            .with_endpoint(EndpointLimits::new("/r/*/new", 2).with_hourly_limit(3));
+        let route = router.route("/r/rust/new").unwrap();
+
+        assert!(route.try_acquire().await.is_ok());
+        assert!(route.try_acquire().await.is_ok());
+        let wait_time = route.try_acquire().await.unwrap_err();
+        assert!(wait_time > Duration::from_secs(25));
+        assert_eq!(route.available_tokens().await, 0);
+    }
+
+    #[tokio::test]
+    async fn test_hourly_budget_caps_minute_budget() {
+        let router = EndpointRouter::new()
+            .with_endpoint(EndpointLimits::new("/api/v1/me", 10).with_hourly_limit(1));
+        let route = router.route("/api/v1/me").unwrap();
+
+        assert!(route.try_acquire().await.is_ok());
+        let wait_time = route.try_acquire().await.unwrap_err();
+        assert!(wait_time > Duration::from_secs(60));
+        // The minute bucket was not charged for the refused request
+        assert_eq!(route.per_minute.get_available_tokens().await as u32, 9);
+    }
+}
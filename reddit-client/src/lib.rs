@@ -1,17 +1,26 @@
-use likeminded_core::{CoreError, RedditApiError, RedditPost};
+use futures::stream::{BoxStream, TryStreamExt};
+use likeminded_core::{CoreError, Keyword, RedditApiError, RedditPost, SubredditHighWaterMark};
 use oauth2::{
-    basic::BasicClient, reqwest::async_http_client, AuthUrl, AuthorizationCode, ClientId,
-    ClientSecret, CsrfToken, PkceCodeChallenge, PkceCodeVerifier, RedirectUrl, RefreshToken, Scope,
-    TokenResponse, TokenUrl,
+    basic::{BasicClient, BasicErrorResponseType},
+    reqwest::async_http_client,
+    AuthUrl, AuthorizationCode, ClientId, ClientSecret, CsrfToken, PkceCodeChallenge,
+    PkceCodeVerifier, RedirectUrl, RefreshToken, RequestTokenError, Scope, TokenResponse, TokenUrl,
 };
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use std::time::{Duration, SystemTime};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
+use token_store::TokenStore;
+use tokio::task::JoinHandle;
 use url::Url;
 
-const REDDIT_AUTH_URL: &str = "https://www.reddit.com/api/v1/authorize";
-const REDDIT_TOKEN_URL: &str = "https://www.reddit.com/api/v1/access_token";
+pub const REDDIT_AUTH_URL: &str = "https://www.reddit.com/api/v1/authorize";
+pub const REDDIT_TOKEN_URL: &str = "https://www.reddit.com/api/v1/access_token";
+pub const REDDIT_REVOKE_URL: &str = "https://www.reddit.com/api/v1/revoke_token";
+
+/// Pause before retrying a background token refresh that failed
+const BACKGROUND_REFRESH_RETRY_DELAY: Duration = Duration::from_secs(30);
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RedditToken {
@@ -21,12 +30,69 @@ pub struct RedditToken {
     pub scope: Vec<String>,
 }
 
+/// Base URLs used to talk to Reddit. Defaults to the production endpoints.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RedditEndpoints {
+    pub auth_url: String,
+    pub token_url: String,
+    pub revoke_url: String,
+    pub api_base_url: String,
+}
+
+impl Default for RedditEndpoints {
+    fn default() -> Self {
+        Self {
+            auth_url: REDDIT_AUTH_URL.to_string(),
+            token_url: REDDIT_TOKEN_URL.to_string(),
+            revoke_url: REDDIT_REVOKE_URL.to_string(),
+            api_base_url: api::REDDIT_API_BASE.to_string(),
+        }
+    }
+}
+
+impl RedditEndpoints {
+    /// Serve every endpoint from a single host, e.g. a local mock server
+    pub fn with_base_url(base_url: &str) -> Self {
+        let base_url = base_url.trim_end_matches('/');
+        Self {
+            auth_url: format!("{}/api/v1/authorize", base_url),
+            token_url: format!("{}/api/v1/access_token", base_url),
+            revoke_url: format!("{}/api/v1/revoke_token", base_url),
+            api_base_url: base_url.to_string(),
+        }
+    }
+}
+
+/// Default `device_id` for the installed-client grant, as suggested by Reddit
+/// for apps that do not want to be tracked per device
+pub const DO_NOT_TRACK_DEVICE_ID: &str = "DO_NOT_TRACK_THIS_DEVICE";
+
+/// The kind of app registered at https://www.reddit.com/prefs/apps
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RedditAppType {
+    /// Confidential app using the authorization code flow
+    #[default]
+    Web,
+    /// Desktop or mobile app without a client secret
+    Installed,
+    /// Personal-use app that may use the password grant
+    Script,
+}
+
+impl RedditAppType {
+    pub fn has_client_secret(&self) -> bool {
+        !matches!(self, RedditAppType::Installed)
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct RedditOAuth2Config {
     pub client_id: String,
     pub client_secret: String,
     pub redirect_uri: String,
     pub user_agent: String,
+    pub endpoints: RedditEndpoints,
+    pub app_type: RedditAppType,
 }
 
 impl RedditOAuth2Config {
@@ -41,8 +107,26 @@ impl RedditOAuth2Config {
             client_secret,
             redirect_uri,
             user_agent,
+            endpoints: RedditEndpoints::default(),
+            app_type: RedditAppType::Web,
         }
     }
+
+    /// Config for an installed app, which has no client secret
+    pub fn installed(client_id: String, redirect_uri: String, user_agent: String) -> Self {
+        Self::new(client_id, String::new(), redirect_uri, user_agent)
+            .with_app_type(RedditAppType::Installed)
+    }
+
+    pub fn with_app_type(mut self, app_type: RedditAppType) -> Self {
+        self.app_type = app_type;
+        self
+    }
+
+    pub fn with_endpoints(mut self, endpoints: RedditEndpoints) -> Self {
+        self.endpoints = endpoints;
+        self
+    }
 }
 
 #[derive(Debug)]
@@ -60,26 +144,80 @@ pub enum AuthState {
     },
 }
 
+/// Grants that issue no refresh token and are simply repeated on expiry
+#[derive(Clone)]
+enum AppGrant {
+    Password { username: String, password: String },
+    ClientCredentials,
+    InstalledClient { device_id: String },
+}
+
+impl AppGrant {
+    fn params(&self) -> Vec<(&'static str, &str)> {
+        match self {
+            AppGrant::Password { username, password } => vec![
+                ("grant_type", "password"),
+                ("username", username),
+                ("password", password),
+            ],
+            AppGrant::ClientCredentials => vec![("grant_type", "client_credentials")],
+            AppGrant::InstalledClient { device_id } => vec![
+                (
+                    "grant_type",
+                    "https://oauth.reddit.com/grants/installed_client",
+                ),
+                ("device_id", device_id),
+            ],
+        }
+    }
+}
+
+/// Token endpoint response for grants requested without the oauth2 crate.
+/// Reddit reports failures such as a wrong password as a 200 with `error`.
+#[derive(Deserialize)]
+struct GrantTokenResponse {
+    access_token: Option<String>,
+    refresh_token: Option<String>,
+    expires_in: Option<u64>,
+    scope: Option<String>,
+    error: Option<String>,
+}
+
 pub struct RedditClient {
     config: RedditOAuth2Config,
     oauth_client: BasicClient,
-    #[allow(dead_code)]
     http_client: Client,
     auth_state: AuthState,
+    /// Shared API client so the rate limiter, retry circuit breaker and
+    /// metrics persist across calls
+    api_client: Arc<api::RedditApiClient>,
+    token_store: Option<Arc<dyn TokenStore>>,
+    /// Refresh started ahead of expiry, adopted by the next `ensure_authenticated`
+    pending_refresh: Option<JoinHandle<Result<RedditToken, CoreError>>>,
+    background_refresh_failed_at: Option<Instant>,
+    /// Grant to repeat when a token without a refresh token expires
+    app_grant: Option<AppGrant>,
 }
 
 impl RedditClient {
     pub fn new(config: RedditOAuth2Config) -> Result<Self, CoreError> {
+        if config.app_type.has_client_secret() && config.client_secret.is_empty() {
+            return Err(CoreError::Config(likeminded_core::ConfigError::MissingField {
+                field: "client_secret".to_string(),
+            }));
+        }
+
+        // Installed apps authenticate with the client ID and an empty password
         let oauth_client = BasicClient::new(
             ClientId::new(config.client_id.clone()),
             Some(ClientSecret::new(config.client_secret.clone())),
-            AuthUrl::new(REDDIT_AUTH_URL.to_string()).map_err(|e| {
+            AuthUrl::new(config.endpoints.auth_url.clone()).map_err(|e| {
                 CoreError::Config(likeminded_core::ConfigError::InvalidValue {
                     field: "auth_url".to_string(),
                     value: e.to_string(),
                 })
             })?,
-            Some(TokenUrl::new(REDDIT_TOKEN_URL.to_string()).map_err(|e| {
+            Some(TokenUrl::new(config.endpoints.token_url.clone()).map_err(|e| {
                 CoreError::Config(likeminded_core::ConfigError::InvalidValue {
                     field: "token_url".to_string(),
                     value: e.to_string(),
@@ -99,14 +237,72 @@ impl RedditClient {
             .build()
             .map_err(|e| CoreError::Network(e))?;
 
+        let api_client = Arc::new(Self::build_api_client(&config));
+
         Ok(Self {
             config,
             oauth_client,
             http_client,
             auth_state: AuthState::NotAuthenticated,
+            api_client,
+            token_store: None,
+            pending_refresh: None,
+            background_refresh_failed_at: None,
+            app_grant: None,
         })
     }
 
+    fn build_api_client(config: &RedditOAuth2Config) -> api::RedditApiClient {
+        api::RedditApiClient::new(config.user_agent.clone())
+            .with_base_url(config.endpoints.api_base_url.clone())
+    }
+
+    /// Record every API call made by this client in the given tracker. The
+    /// tracker is attached to the current API client, so settings from
+    /// [`Self::with_api_client`] are kept and other users of a shared client
+    /// are tracked as well. A client that already has a tracker keeps it.
+    #[cfg(feature = "database")]
+    pub fn with_api_tracker(self, api_tracker: Arc<api_tracker::ApiTracker>) -> Self {
+        if !self.api_client.attach_api_tracker(api_tracker) {
+            tracing::warn!("API client already records calls in a tracker; keeping that one");
+        }
+        self
+    }
+
+    /// Use an existing API client, e.g. one shared with a request queue
+    pub fn with_api_client(mut self, api_client: Arc<api::RedditApiClient>) -> Self {
+        self.api_client = api_client;
+        self
+    }
+
+    /// The API client shared by every call made through this client
+    pub fn api_client(&self) -> Arc<api::RedditApiClient> {
+        self.api_client.clone()
+    }
+
+    /// Save tokens to `token_store` after every code exchange or refresh
+    pub fn with_token_store(mut self, token_store: Arc<dyn TokenStore>) -> Self {
+        self.token_store = Some(token_store);
+        self
+    }
+
+    /// Restore the token saved by a previous run. Returns `false` if there
+    /// is no token store or it holds no token.
+    pub async fn load_persisted_token(&mut self) -> Result<bool, CoreError> {
+        let Some(token_store) = self.token_store.clone() else {
+            return Ok(false);
+        };
+
+        match token_store.load().await? {
+            Some(token) => {
+                tracing::debug!("Loaded persisted Reddit token");
+                self.set_token(token);
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
     pub fn generate_auth_url(&mut self, scopes: &[&str]) -> Result<(String, CsrfToken), CoreError> {
         let (pkce_challenge, pkce_verifier) = PkceCodeChallenge::new_random_sha256();
 
@@ -285,20 +481,173 @@ impl RedditClient {
         self.auth_state = AuthState::Authenticated {
             token: token.clone(),
         };
+        self.app_grant = None;
+        Self::persist_token(self.token_store.as_ref(), &token).await;
 
         Ok(token)
     }
 
+    /// Log in with a Reddit username and password. Only script apps may use
+    /// this grant; the credentials are kept in memory to log in again when
+    /// the token expires, since Reddit issues no refresh token here.
+    pub async fn authenticate_with_password(
+        &mut self,
+        username: &str,
+        password: &str,
+    ) -> Result<RedditToken, CoreError> {
+        if self.config.app_type != RedditAppType::Script {
+            return Err(CoreError::RedditApi(RedditApiError::AuthenticationFailed {
+                reason: "The password grant is only available to script apps".to_string(),
+            }));
+        }
+
+        let grant = AppGrant::Password {
+            username: username.to_string(),
+            password: password.to_string(),
+        };
+        let token = self.request_app_grant(&grant).await?;
+        self.app_grant = Some(grant);
+        Self::persist_token(self.token_store.as_ref(), &token).await;
+
+        Ok(token)
+    }
+
+    /// Get an application-only token for reading public listings without a
+    /// user. Installed apps use the `installed_client` grant with `device_id`
+    /// (defaulting to [`DO_NOT_TRACK_DEVICE_ID`]); other apps use
+    /// `client_credentials`. App-only tokens are not persisted.
+    pub async fn authenticate_app_only(
+        &mut self,
+        device_id: Option<&str>,
+    ) -> Result<RedditToken, CoreError> {
+        let grant = match self.config.app_type {
+            RedditAppType::Installed => AppGrant::InstalledClient {
+                device_id: device_id.unwrap_or(DO_NOT_TRACK_DEVICE_ID).to_string(),
+            },
+            RedditAppType::Web | RedditAppType::Script => AppGrant::ClientCredentials,
+        };
+        let token = self.request_app_grant(&grant).await?;
+        self.app_grant = Some(grant);
+
+        Ok(token)
+    }
+
+    async fn request_app_grant(&mut self, grant: &AppGrant) -> Result<RedditToken, CoreError> {
+        let response = self
+            .http_client
+            .post(&self.config.endpoints.token_url)
+            .basic_auth(&self.config.client_id, Some(&self.config.client_secret))
+            .form(&grant.params())
+            .send()
+            .await?;
+
+        let status = response.status();
+        let body: GrantTokenResponse = response.json().await.map_err(|e| {
+            CoreError::RedditApi(RedditApiError::AuthenticationFailed {
+                reason: format!("Token request failed with status {}: {}", status, e),
+            })
+        })?;
+
+        let access_token = match (body.error, body.access_token) {
+            (None, Some(access_token)) if status.is_success() => access_token,
+            (error, _) => {
+                return Err(CoreError::RedditApi(RedditApiError::AuthenticationFailed {
+                    reason: format!(
+                        "Token request failed: {}",
+                        error.unwrap_or_else(|| status.to_string())
+                    ),
+                }))
+            }
+        };
+
+        let token = RedditToken {
+            access_token,
+            refresh_token: body.refresh_token,
+            expires_at: SystemTime::now() + Duration::from_secs(body.expires_in.unwrap_or(3600)),
+            scope: body
+                .scope
+                .map(|scope| scope.split_whitespace().map(str::to_string).collect())
+                .unwrap_or_default(),
+        };
+
+        self.auth_state = AuthState::Authenticated {
+            token: token.clone(),
+        };
+
+        Ok(token)
+    }
+
+    /// Run the whole authorization flow without copy-pasting the redirect.
+    ///
+    /// Binds a listener on the loopback `redirect_uri`, hands the authorization
+    /// URL to `open_browser`, then exchanges the code once Reddit redirects
+    /// back. The browser tab gets a success or failure page either way.
+    pub async fn authorize_with_loopback<F>(
+        &mut self,
+        scopes: &[&str],
+        timeout: Duration,
+        open_browser: F,
+    ) -> Result<RedditToken, CoreError>
+    where
+        F: FnOnce(&str),
+    {
+        // Bind first so the port is ours before the user is sent to Reddit
+        let listener = oauth_callback::CallbackListener::bind(&self.config.redirect_uri).await?;
+        let (auth_url, csrf_token) = self.generate_auth_url(scopes)?;
+        open_browser(&auth_url);
+
+        let callback = listener.accept(timeout).await?;
+        let result = self.handle_callback(callback.url(), &csrf_token).await;
+        callback.respond(&result).await;
+        result
+    }
+
     pub async fn refresh_token(&mut self, refresh_token: &str) -> Result<RedditToken, CoreError> {
-        let token_result = self
-            .oauth_client
+        let new_token = match Self::request_token_refresh(&self.oauth_client, refresh_token).await
+        {
+            Ok(token) => token,
+            Err(CoreError::RedditApi(RedditApiError::RefreshTokenRevoked)) => {
+                self.forget_revoked_token().await;
+                return Err(CoreError::RedditApi(RedditApiError::RefreshTokenRevoked));
+            }
+            Err(e) => return Err(e),
+        };
+
+        self.auth_state = AuthState::Authenticated {
+            token: new_token.clone(),
+        };
+        Self::persist_token(self.token_store.as_ref(), &new_token).await;
+
+        Ok(new_token)
+    }
+
+    /// Drop a token whose refresh token Reddit has revoked; it can never
+    /// work again
+    async fn forget_revoked_token(&mut self) {
+        tracing::warn!("Reddit refresh token was revoked; reconnect required");
+        self.auth_state = AuthState::NotAuthenticated;
+        if let Err(e) = self.clear_persisted_token().await {
+            tracing::warn!("Failed to clear persisted Reddit token: {}", e);
+        }
+    }
+
+    async fn request_token_refresh(
+        oauth_client: &BasicClient,
+        refresh_token: &str,
+    ) -> Result<RedditToken, CoreError> {
+        let token_result = oauth_client
             .exchange_refresh_token(&RefreshToken::new(refresh_token.to_string()))
             .request_async(async_http_client)
             .await
-            .map_err(|e| {
-                CoreError::RedditApi(RedditApiError::AuthenticationFailed {
+            .map_err(|e| match &e {
+                RequestTokenError::ServerResponse(response)
+                    if *response.error() == BasicErrorResponseType::InvalidGrant =>
+                {
+                    CoreError::RedditApi(RedditApiError::RefreshTokenRevoked)
+                }
+                _ => CoreError::RedditApi(RedditApiError::AuthenticationFailed {
                     reason: format!("Token refresh failed: {}", e),
-                })
+                }),
             })?;
 
         let expires_at = SystemTime::now()
@@ -324,14 +673,85 @@ impl RedditClient {
             scope: scopes,
         };
 
-        self.auth_state = AuthState::Authenticated {
-            token: new_token.clone(),
-        };
-
         Ok(new_token)
     }
 
+    async fn persist_token(token_store: Option<&Arc<dyn TokenStore>>, token: &RedditToken) {
+        if let Some(token_store) = token_store {
+            if let Err(e) = token_store.save(token).await {
+                tracing::warn!("Failed to persist Reddit token: {}", e);
+            }
+        }
+    }
+
+    async fn clear_persisted_token(&self) -> Result<(), CoreError> {
+        match &self.token_store {
+            Some(token_store) => token_store.clear().await,
+            None => Ok(()),
+        }
+    }
+
+    /// Revoke the current tokens with Reddit, forget the persisted token and
+    /// go back to `NotAuthenticated`. Local state is cleared even if Reddit
+    /// cannot be reached; the first error is returned afterwards.
+    pub async fn logout(&mut self) -> Result<(), CoreError> {
+        if let Some(handle) = self.pending_refresh.take() {
+            handle.abort();
+        }
+        self.background_refresh_failed_at = None;
+        self.app_grant = None;
+
+        let mut result = Ok(());
+        match std::mem::replace(&mut self.auth_state, AuthState::NotAuthenticated) {
+            AuthState::Authenticated { token } | AuthState::TokenExpired { token } => {
+                // Revoking the refresh token also invalidates access tokens
+                // issued from it; the access token is revoked for app-only
+                // and password grants, which have no refresh token
+                let tokens = token
+                    .refresh_token
+                    .as_deref()
+                    .map(|refresh_token| (refresh_token, "refresh_token"))
+                    .into_iter()
+                    .chain([(token.access_token.as_str(), "access_token")]);
+                for (value, hint) in tokens {
+                    if let Err(e) = self.revoke_token(value, hint).await {
+                        tracing::warn!("Failed to revoke Reddit {}: {}", hint, e);
+                        result = result.and(Err(e));
+                    }
+                }
+            }
+            AuthState::NotAuthenticated | AuthState::PendingAuthorization { .. } => {}
+        }
+
+        result.and(self.clear_persisted_token().await)
+    }
+
+    async fn revoke_token(&self, token: &str, token_type_hint: &str) -> Result<(), CoreError> {
+        let response = self
+            .http_client
+            .post(&self.config.endpoints.revoke_url)
+            .basic_auth(&self.config.client_id, Some(&self.config.client_secret))
+            .form(&[("token", token), ("token_type_hint", token_type_hint)])
+            .send()
+            .await?;
+
+        let status = response.status();
+        if !status.is_success() {
+            return Err(CoreError::RequestFailed {
+                message: format!("Token revocation failed with status {}", status),
+                status_code: Some(status.as_u16()),
+            });
+        }
+
+        Ok(())
+    }
+
     pub fn set_token(&mut self, token: RedditToken) {
+        // A refresh of the previous token must not overwrite this one
+        if let Some(handle) = self.pending_refresh.take() {
+            handle.abort();
+        }
+
         let now = SystemTime::now();
         self.auth_state = if token.expires_at <= now {
             AuthState::TokenExpired { token }
@@ -361,37 +781,115 @@ impl RedditClient {
         }
     }
 
+    /// Make sure there is a usable access token.
+    ///
+    /// A token close to expiry is refreshed in the background while the
+    /// current one keeps being used; the new token is picked up by a later
+    /// call. Only an already expired token blocks on a refresh.
     pub async fn ensure_authenticated(&mut self) -> Result<(), CoreError> {
-        let needs_refresh = self.needs_refresh();
+        self.collect_background_refresh(false).await?;
 
-        match &self.auth_state {
+        let token = match &self.auth_state {
             AuthState::NotAuthenticated => {
-                Err(CoreError::RedditApi(RedditApiError::AuthenticationFailed {
+                return Err(CoreError::RedditApi(RedditApiError::AuthenticationFailed {
                     reason: "Not authenticated. Please authenticate first.".to_string(),
-                }))
+                }));
             }
             AuthState::PendingAuthorization { .. } => {
-                Err(CoreError::RedditApi(RedditApiError::AuthenticationFailed {
+                return Err(CoreError::RedditApi(RedditApiError::AuthenticationFailed {
                     reason: "Authentication pending. Please complete OAuth flow.".to_string(),
-                }))
+                }));
             }
-            AuthState::Authenticated { token } => {
-                if needs_refresh {
-                    if let Some(refresh_token) = token.refresh_token.clone() {
-                        self.refresh_token(&refresh_token).await?;
-                    } else {
-                        return Err(CoreError::RedditApi(RedditApiError::InvalidToken));
-                    }
+            AuthState::Authenticated { token } | AuthState::TokenExpired { token } => {
+                token.clone()
+            }
+        };
+
+        let expired = matches!(self.auth_state, AuthState::TokenExpired { .. })
+            || token.expires_at <= SystemTime::now();
+
+        if !expired {
+            if self.needs_refresh() {
+                if let Some(refresh_token) = token.refresh_token {
+                    self.start_background_refresh(refresh_token);
                 }
+            }
+            return Ok(());
+        }
+
+        if self.collect_background_refresh(true).await? {
+            return Ok(());
+        }
+
+        match (token.refresh_token, self.app_grant.clone()) {
+            (Some(refresh_token), _) => {
+                self.refresh_token(&refresh_token).await?;
                 Ok(())
             }
-            AuthState::TokenExpired { token } => {
-                if let Some(refresh_token) = token.refresh_token.clone() {
-                    self.refresh_token(&refresh_token).await?;
-                    Ok(())
-                } else {
-                    Err(CoreError::RedditApi(RedditApiError::InvalidToken))
+            (None, Some(grant)) => {
+                tracing::debug!("Requesting a new token with the original grant");
+                let token = self.request_app_grant(&grant).await?;
+                if matches!(grant, AppGrant::Password { .. }) {
+                    Self::persist_token(self.token_store.as_ref(), &token).await;
                 }
+                Ok(())
+            }
+            (None, None) => Err(CoreError::RedditApi(RedditApiError::InvalidToken)),
+        }
+    }
+
+    fn start_background_refresh(&mut self, refresh_token: String) {
+        let recently_failed = self
+            .background_refresh_failed_at
+            .is_some_and(|failed_at| failed_at.elapsed() < BACKGROUND_REFRESH_RETRY_DELAY);
+        if self.pending_refresh.is_some() || recently_failed {
+            return;
+        }
+
+        tracing::debug!("Refreshing Reddit token in the background");
+        let oauth_client = self.oauth_client.clone();
+        let token_store = self.token_store.clone();
+        self.pending_refresh = Some(tokio::spawn(async move {
+            let token = Self::request_token_refresh(&oauth_client, &refresh_token).await?;
+            Self::persist_token(token_store.as_ref(), &token).await;
+            Ok(token)
+        }));
+    }
+
+    /// Adopt the token from a background refresh. Only a finished refresh is
+    /// picked up unless `wait` is set. Returns whether a new token was adopted.
+    /// A revoked refresh token signs the client out, as in `refresh_token`.
+    async fn collect_background_refresh(&mut self, wait: bool) -> Result<bool, CoreError> {
+        let ready = self
+            .pending_refresh
+            .as_ref()
+            .is_some_and(|handle| wait || handle.is_finished());
+        if !ready {
+            return Ok(false);
+        }
+        let Some(handle) = self.pending_refresh.take() else {
+            return Ok(false);
+        };
+
+        match handle.await {
+            Ok(Ok(token)) => {
+                self.auth_state = AuthState::Authenticated { token };
+                self.background_refresh_failed_at = None;
+                Ok(true)
+            }
+            Ok(Err(CoreError::RedditApi(RedditApiError::RefreshTokenRevoked))) => {
+                self.forget_revoked_token().await;
+                Err(CoreError::RedditApi(RedditApiError::RefreshTokenRevoked))
+            }
+            Ok(Err(e)) => {
+                tracing::warn!("Background token refresh failed: {}", e);
+                self.background_refresh_failed_at = Some(Instant::now());
+                Ok(false)
+            }
+            Err(e) => {
+                tracing::warn!("Background token refresh task failed: {}", e);
+                self.background_refresh_failed_at = Some(Instant::now());
+                Ok(false)
             }
         }
     }
@@ -420,8 +918,8 @@ impl RedditClient {
         self.ensure_authenticated().await?;
 
         if let AuthState::Authenticated { token } = &self.auth_state {
-            let api_client = api::RedditApiClient::new(self.config.user_agent.clone());
-            let listing = api_client
+            let listing = self
+                .api_client
                 .get_subreddit_posts_with_time_filter(
                     &token.access_token,
                     subreddit,
@@ -447,6 +945,107 @@ impl RedditClient {
         }
     }
 
+    /// Stream posts from a subreddit, following `after` cursors until one of
+    /// the limits in `options` is reached or the listing runs out.
+    pub async fn stream_subreddit_posts(
+        &mut self,
+        subreddit: &str,
+        sort: Option<&str>,
+        time_filter: Option<&str>,
+        options: pagination::PaginationOptions,
+    ) -> Result<BoxStream<'static, Result<RedditPost, CoreError>>, CoreError> {
+        self.ensure_authenticated().await?;
+
+        if let AuthState::Authenticated { token } = &self.auth_state {
+            Ok(pagination::paginate_subreddit_posts(
+                self.api_client.clone(),
+                token.access_token.clone(),
+                subreddit.to_string(),
+                sort.map(|s| s.to_string()),
+                time_filter.map(|t| t.to_string()),
+                options,
+            ))
+        } else {
+            Err(CoreError::RedditApi(RedditApiError::AuthenticationFailed {
+                reason: "Not authenticated".to_string(),
+            }))
+        }
+    }
+
+    /// Fetch the posts in `/r/{subreddit}/new` that are newer than `mark`.
+    ///
+    /// Returns the new posts (newest first) together with the advanced mark,
+    /// which the caller should persist for the next poll. With an empty mark
+    /// only the first page is fetched, so a fresh subreddit doesn't walk its
+    /// whole history. If `max_posts` stops the walk before it reaches the
+    /// mark, the returned mark records where it stopped and the following
+    /// polls continue from there until they reach the old mark, so a busy
+    /// subreddit is worked through `max_posts` at a time without gaps.
+    pub async fn fetch_new_posts_since(
+        &mut self,
+        subreddit: &str,
+        mark: &SubredditHighWaterMark,
+        max_posts: Option<usize>,
+    ) -> Result<(Vec<RedditPost>, SubredditHighWaterMark), CoreError> {
+        let mut options = pagination::PaginationOptions::new();
+        if let Some(max_posts) = max_posts {
+            // One extra post tells a walk cut short from one that ended at the mark
+            options = options.with_max_posts(max_posts + 1);
+        }
+        if let Some(post_id) = &mark.post_id {
+            options = options.with_seen_post_id(post_id.clone());
+        }
+        if let Some(after) = &mark.resume_after {
+            options = options.with_start_after(after.clone());
+        }
+        match mark.created_utc {
+            // Posts sharing the mark's second are let through; the seen id
+            // stops the walk and duplicates are harmless to store
+            Some(created_utc) => options = options.with_created_after(created_utc - 1),
+            None if mark.post_id.is_none() && max_posts.is_none() => {
+                options = options.with_max_pages(1)
+            }
+            None => {}
+        }
+
+        let mut posts: Vec<RedditPost> = self
+            .stream_subreddit_posts(subreddit, Some("new"), None, options)
+            .await?
+            .try_collect()
+            .await?;
+
+        let cut_short = max_posts.is_some_and(|max_posts| posts.len() > max_posts);
+        if let Some(max_posts) = max_posts {
+            posts.truncate(max_posts);
+        }
+
+        let mut new_mark = mark.clone();
+        if mark.is_empty() {
+            for post in &posts {
+                new_mark.observe(post);
+            }
+        } else {
+            for post in &posts {
+                new_mark.observe_pending(post);
+            }
+            if !cut_short {
+                new_mark.finish_catch_up();
+            } else if let Some(last) = posts.last() {
+                new_mark.resume_after = Some(format!("t3_{}", last.id));
+            }
+        }
+
+        tracing::debug!(
+            "Fetched {} new posts from r/{} (mark {:?} -> {:?})",
+            posts.len(),
+            subreddit,
+            mark.post_id,
+            new_mark.post_id
+        );
+
+        Ok((posts, new_mark))
+    }
+
     pub async fn fetch_multiple_subreddit_posts(
         &mut self,
         subreddits: &[&str],
@@ -458,8 +1057,8 @@ impl RedditClient {
         self.ensure_authenticated().await?;
 
         if let AuthState::Authenticated { token } = &self.auth_state {
-            let api_client = api::RedditApiClient::new(self.config.user_agent.clone());
-            let results = api_client
+            let results = self
+                .api_client
                 .get_multiple_subreddit_posts(
                     &token.access_token,
                     subreddits,
@@ -494,12 +1093,57 @@ impl RedditClient {
         }
     }
 
+    /// Like [`Self::fetch_multiple_subreddit_posts`], but groups subreddits
+    /// into combined `/r/a+b+c` requests so many subreddits cost only a few
+    /// requests. See [`batching`] for the trade-offs.
+    pub async fn fetch_batched_subreddit_posts(
+        &mut self,
+        subreddits: &[&str],
+        sort: Option<&str>,
+        time_filter: Option<&str>,
+        limit: Option<u32>,
+    ) -> Result<Vec<(String, Result<Vec<RedditPost>, CoreError>)>, CoreError> {
+        self.ensure_authenticated().await?;
+
+        if let AuthState::Authenticated { token } = &self.auth_state {
+            let results = self
+                .api_client
+                .get_batched_subreddit_posts(
+                    &token.access_token,
+                    subreddits,
+                    sort,
+                    time_filter,
+                    limit,
+                    batching::DEFAULT_MAX_URL_LENGTH,
+                )
+                .await?;
+
+            Ok(results
+                .into_iter()
+                .map(|(subreddit, listing_result)| {
+                    let posts_result = listing_result.map(|listing| {
+                        listing
+                            .data
+                            .children
+                            .into_iter()
+                            .map(|child| child.data.into())
+                            .collect()
+                    });
+                    (subreddit, posts_result)
+                })
+                .collect())
+        } else {
+            Err(CoreError::RedditApi(RedditApiError::AuthenticationFailed {
+                reason: "Not authenticated".to_string(),
+            }))
+        }
+    }
+
     pub async fn check_subreddit_access(&mut self, subreddit: &str) -> Result<bool, CoreError> {
         self.ensure_authenticated().await?;
 
         if let AuthState::Authenticated { token } = &self.auth_state {
-            let api_client = api::RedditApiClient::new(self.config.user_agent.clone());
-            api_client
+            self.api_client
                 .check_subreddit_access(&token.access_token, subreddit)
                 .await
         } else {
@@ -509,12 +1153,100 @@ impl RedditClient {
         }
     }
 
+    /// Run a single search request. Returns the posts and the `after` cursor
+    /// for the next page, if there is one.
+    pub async fn search_posts(
+        &mut self,
+        query: &search::SearchQuery,
+    ) -> Result<(Vec<RedditPost>, Option<String>), CoreError> {
+        self.ensure_authenticated().await?;
+
+        if let AuthState::Authenticated { token } = &self.auth_state {
+            let listing = self
+                .api_client
+                .search_posts(&token.access_token, query)
+                .await?;
+
+            let posts = listing
+                .data
+                .children
+                .into_iter()
+                .map(|child| child.data.into())
+                .collect();
+
+            Ok((posts, listing.data.after))
+        } else {
+            Err(CoreError::RedditApi(RedditApiError::AuthenticationFailed {
+                reason: "Not authenticated".to_string(),
+            }))
+        }
+    }
+
+    /// Search all of Reddit for a keyword's newest posts, following pages
+    /// until `max_posts` are collected. Keywords that haven't opted into
+    /// Reddit-wide search return nothing without making a request.
+    pub async fn search_keyword(
+        &mut self,
+        keyword: &Keyword,
+        max_posts: usize,
+    ) -> Result<Vec<RedditPost>, CoreError> {
+        if !keyword.search_reddit {
+            return Ok(Vec::new());
+        }
+
+        let mut query = search::SearchQuery::new(keyword.text.clone()).with_sort("new");
+        let mut posts = Vec::new();
+
+        while posts.len() < max_posts {
+            query.limit = Some((max_posts - posts.len()).min(100) as u32);
+            let (page, after) = self.search_posts(&query).await?;
+            if page.is_empty() {
+                break;
+            }
+            posts.extend(page);
+
+            match after {
+                Some(after) if query.after.as_deref() != Some(after.as_str()) => {
+                    query.after = Some(after);
+                }
+                _ => break,
+            }
+        }
+
+        posts.truncate(max_posts);
+        tracing::debug!(
+            "Reddit search for keyword '{}' found {} posts",
+            keyword.text,
+            posts.len()
+        );
+        Ok(posts)
+    }
+
+    /// Fetch a post and its comment tree. See [`comments::CommentFetchOptions`]
+    /// for the depth, count and request limits.
+    pub async fn fetch_post_comments(
+        &mut self,
+        post_id: &str,
+        options: &comments::CommentFetchOptions,
+    ) -> Result<comments::CommentThread, CoreError> {
+        self.ensure_authenticated().await?;
+
+        if let AuthState::Authenticated { token } = &self.auth_state {
+            self.api_client
+                .get_post_comments(&token.access_token, post_id, options)
+                .await
+        } else {
+            Err(CoreError::RedditApi(RedditApiError::AuthenticationFailed {
+                reason: "Not authenticated".to_string(),
+            }))
+        }
+    }
+
     pub async fn get_user_info(&mut self) -> Result<api::RedditUserData, CoreError> {
         self.ensure_authenticated().await?;
 
         if let AuthState::Authenticated { token } = &self.auth_state {
-            let api_client = api::RedditApiClient::new(self.config.user_agent.clone());
-            api_client.get_user_info(&token.access_token).await
+            self.api_client.get_user_info(&token.access_token).await
         } else {
             Err(CoreError::RedditApi(RedditApiError::AuthenticationFailed {
                 reason: "Not authenticated".to_string(),
@@ -529,8 +1261,7 @@ impl RedditClient {
         self.ensure_authenticated().await?;
 
         if let AuthState::Authenticated { token } = &self.auth_state {
-            let api_client = api::RedditApiClient::new(self.config.user_agent.clone());
-            api_client
+            self.api_client
                 .get_subreddit_info(&token.access_token, subreddit)
                 .await
         } else {
@@ -540,24 +1271,37 @@ impl RedditClient {
         }
     }
 
+    /// Fetch every subreddit the user is subscribed to, following `after`
+    /// cursors until the listing is exhausted
     pub async fn get_user_subreddits(
         &mut self,
     ) -> Result<Vec<api::RedditSubredditData>, CoreError> {
         self.ensure_authenticated().await?;
 
         if let AuthState::Authenticated { token } = &self.auth_state {
-            let api_client = api::RedditApiClient::new(self.config.user_agent.clone());
-            let listing = api_client
-                .get_user_subreddits(&token.access_token, Some(100))
-                .await?;
-
-            let subreddits: Vec<api::RedditSubredditData> = listing
-                .data
-                .children
-                .into_iter()
-                .map(|child| child.data)
-                .collect();
+            let mut subreddits: Vec<api::RedditSubredditData> = Vec::new();
+            let mut after: Option<String> = None;
+
+            loop {
+                let listing = self
+                    .api_client
+                    .get_user_subreddits(&token.access_token, Some(100), after.as_deref())
+                    .await?;
+
+                let page_size = listing.data.children.len();
+                subreddits.extend(listing.data.children.into_iter().map(|child| child.data));
+
+                // Guard against a cursor that doesn't move, so a misbehaving
+                // listing can't loop forever
+                match listing.data.after {
+                    Some(next) if page_size > 0 && after.as_deref() != Some(next.as_str()) => {
+                        after = Some(next);
+                    }
+                    _ => break,
+                }
+            }
 
+            tracing::debug!("Fetched {} subscribed subreddits", subreddits.len());
             Ok(subreddits)
         } else {
             Err(CoreError::RedditApi(RedditApiError::AuthenticationFailed {
@@ -567,34 +1311,49 @@ impl RedditClient {
     }
 
     pub async fn get_api_metrics(&self) -> metrics::ApiMetrics {
-        let api_client = api::RedditApiClient::new(self.config.user_agent.clone());
-        api_client.get_metrics().await
+        self.api_client.get_metrics().await
     }
 
     pub async fn get_rate_limit_status(&self) -> rate_limiter::RateLimitStatus {
-        let api_client = api::RedditApiClient::new(self.config.user_agent.clone());
-        api_client.get_rate_limit_status().await
+        self.api_client.get_rate_limit_status().await
     }
 
     pub fn get_retry_metrics(&self) -> retry::RetryMetrics {
-        let api_client = api::RedditApiClient::new(self.config.user_agent.clone());
-        api_client.get_retry_metrics()
+        self.api_client.get_retry_metrics()
+    }
+
+    /// Circuit breaker states by endpoint pattern and subreddit
+    pub fn get_circuit_breaker_state(&self) -> BTreeMap<String, retry::CircuitBreakerState> {
+        self.api_client.get_circuit_breaker_state()
     }
 
-    pub fn get_circuit_breaker_state(&self) -> retry::CircuitBreakerState {
-        let api_client = api::RedditApiClient::new(self.config.user_agent.clone());
-        api_client.get_circuit_breaker_state()
+    /// Whether requests for `endpoint` would get past their circuit breaker
+    pub fn is_source_available(&self, endpoint: &str, subreddit: Option<&str>) -> bool {
+        self.api_client.is_source_available(endpoint, subreddit)
     }
 }
 
+pub mod accounts;
 pub mod api;
 #[cfg(feature = "database")]
 pub mod api_tracker;
+pub mod batching;
+pub mod cache;
+pub mod comments;
+pub mod endpoint_router;
+mod local_http;
 pub mod metrics;
+#[cfg(feature = "mock-server")]
+pub mod mock_server;
+pub mod oauth_callback;
+pub mod openmetrics;
+pub mod pagination;
 pub mod rate_limiter;
 #[cfg(feature = "database")]
 pub mod request_queue;
 pub mod retry;
+pub mod search;
+pub mod token_store;
 #[cfg(feature = "database")]
 pub mod usage_dashboard;
 
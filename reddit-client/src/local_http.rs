@@ -0,0 +1,255 @@
+//! Minimal HTTP/1.1 plumbing for the small loopback servers in this crate.
+//!
+//! This is intentionally tiny: one request per connection, `Connection: close`
+//! on every response, and no chunked transfer encoding. It is only meant for
+//! localhost helpers such as the mock Reddit server, never for public traffic.
+
+use std::collections::HashMap;
+use std::io;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+/// Upper bound on the size of a request head we are willing to buffer
+const MAX_HEAD_SIZE: usize = 16 * 1024;
+
+/// Upper bound on the size of a request body we are willing to buffer
+const MAX_BODY_SIZE: usize = 1024 * 1024;
+
+#[derive(Debug, Clone)]
+pub(crate) struct HttpRequest {
+    pub method: String,
+    pub path: String,
+    pub query: Vec<(String, String)>,
+    #[cfg_attr(not(feature = "mock-server"), allow(dead_code))]
+    pub headers: HashMap<String, String>,
+    #[cfg_attr(not(feature = "mock-server"), allow(dead_code))]
+    pub body: Vec<u8>,
+}
+
+#[cfg(feature = "mock-server")]
+impl HttpRequest {
+    /// Case-insensitive header lookup
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .get(&name.to_ascii_lowercase())
+            .map(|value| value.as_str())
+    }
+
+    pub fn query_param(&self, name: &str) -> Option<&str> {
+        self.query
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Decode an `application/x-www-form-urlencoded` body
+    pub fn form_params(&self) -> Vec<(String, String)> {
+        url::form_urlencoded::parse(&self.body)
+            .into_owned()
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct HttpResponse {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl HttpResponse {
+    pub fn new(status: u16, content_type: &str, body: impl Into<Vec<u8>>) -> Self {
+        Self {
+            status,
+            headers: vec![("Content-Type".to_string(), content_type.to_string())],
+            body: body.into(),
+        }
+    }
+
+    #[cfg(feature = "mock-server")]
+    pub fn json(status: u16, body: &serde_json::Value) -> Self {
+        Self::new(status, "application/json; charset=UTF-8", body.to_string())
+    }
+
+    pub fn html(status: u16, body: impl Into<Vec<u8>>) -> Self {
+        Self::new(status, "text/html; charset=utf-8", body)
+    }
+
+    #[cfg(feature = "mock-server")]
+    pub fn with_header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+}
+
+/// Read a single request from the stream.
+///
+/// Returns `Ok(None)` if the peer closed the connection before sending a
+/// complete request head.
+pub(crate) async fn read_request(stream: &mut TcpStream) -> io::Result<Option<HttpRequest>> {
+    let mut buffer = Vec::with_capacity(1024);
+    let mut chunk = [0u8; 1024];
+
+    let head_end = loop {
+        if let Some(position) = find_head_end(&buffer) {
+            break position;
+        }
+        if buffer.len() > MAX_HEAD_SIZE {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "request head too large",
+            ));
+        }
+
+        let read = stream.read(&mut chunk).await?;
+        if read == 0 {
+            return Ok(None);
+        }
+        buffer.extend_from_slice(&chunk[..read]);
+    };
+
+    let head = String::from_utf8_lossy(&buffer[..head_end]).to_string();
+    let mut lines = head.split("\r\n");
+
+    let request_line = lines.next().unwrap_or_default();
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or_default().to_string();
+    let target = parts.next().unwrap_or("/").to_string();
+
+    let mut headers = HashMap::new();
+    for line in lines {
+        if let Some((name, value)) = line.split_once(':') {
+            headers.insert(name.trim().to_ascii_lowercase(), value.trim().to_string());
+        }
+    }
+
+    let (path, query) = match target.split_once('?') {
+        Some((path, query)) => (
+            path.to_string(),
+            url::form_urlencoded::parse(query.as_bytes())
+                .into_owned()
+                .collect(),
+        ),
+        None => (target, Vec::new()),
+    };
+
+    let content_length = headers
+        .get("content-length")
+        .and_then(|value| value.parse::<usize>().ok())
+        .unwrap_or(0);
+    if content_length > MAX_BODY_SIZE {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "request body too large",
+        ));
+    }
+
+    let mut body = buffer[head_end + 4..].to_vec();
+    while body.len() < content_length {
+        let read = stream.read(&mut chunk).await?;
+        if read == 0 {
+            break;
+        }
+        body.extend_from_slice(&chunk[..read]);
+    }
+    body.truncate(content_length);
+
+    Ok(Some(HttpRequest {
+        method,
+        path,
+        query,
+        headers,
+        body,
+    }))
+}
+
+/// Write a response and flush it. The connection is always closed afterwards.
+pub(crate) async fn write_response(
+    stream: &mut TcpStream,
+    response: &HttpResponse,
+) -> io::Result<()> {
+    let mut head = format!(
+        "HTTP/1.1 {} {}\r\n",
+        response.status,
+        reason_phrase(response.status)
+    );
+    for (name, value) in &response.headers {
+        head.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    head.push_str(&format!("Content-Length: {}\r\n", response.body.len()));
+    head.push_str("Connection: close\r\n\r\n");
+
+    stream.write_all(head.as_bytes()).await?;
+    stream.write_all(&response.body).await?;
+    stream.flush().await?;
+    stream.shutdown().await
+}
+
+fn find_head_end(buffer: &[u8]) -> Option<usize> {
+    buffer.windows(4).position(|window| window == b"\r\n\r\n")
+}
+
+fn reason_phrase(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        204 => "No Content",
+        304 => "Not Modified",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        403 => "Forbidden",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        408 => "Request Timeout",
+        429 => "Too Many Requests",
+        500 => "Internal Server Error",
+        502 => "Bad Gateway",
+        503 => "Service Unavailable",
+        504 => "Gateway Timeout",
+        _ => "Unknown",
+    }
+}
+
+#[cfg(all(test, feature = "mock-server"))]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    #[tokio::test]
+    async fn test_round_trip_request_and_response() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let server = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let request = read_request(&mut stream).await.unwrap().unwrap();
+            let response = HttpResponse::json(200, &serde_json::json!({ "ok": true }));
+            write_response(&mut stream, &response).await.unwrap();
+            request
+        });
+
+        let client = reqwest::Client::new();
+        let response = client
+            .post(format!("http://{}/api/v1/thing?limit=5&q=a%20b", addr))
+            .header("X-Custom", "value")
+            .body("grant_type=refresh_token&refresh_token=abc")
+            .send()
+            .await
+            .unwrap();
+
+        assert_eq!(response.status().as_u16(), 200);
+        assert_eq!(
+            response.headers().get("content-type").unwrap(),
+            "application/json; charset=UTF-8"
+        );
+
+        let request = server.await.unwrap();
+        assert_eq!(request.method, "POST");
+        assert_eq!(request.path, "/api/v1/thing");
+        assert_eq!(request.query_param("limit"), Some("5"));
+        assert_eq!(request.query_param("q"), Some("a b"));
+        assert_eq!(request.header("x-custom"), Some("value"));
+        assert!(request
+            .form_params()
+            .contains(&("refresh_token".to_string(), "abc".to_string())));
+    }
+}
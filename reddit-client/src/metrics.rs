@@ -1,3 +1,4 @@
+use crate::endpoint_router::endpoint_pattern;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::sync::Arc;
@@ -10,12 +11,23 @@ pub struct ApiMetrics {
     pub successful_requests: u64,
     pub failed_requests: u64,
     pub rate_limited_requests: u64,
+    /// Requests answered from the response cache without contacting Reddit
+    pub cache_hits: u64,
     pub average_response_time: Duration,
+    /// Response times across every endpoint
+    #[serde(default)]
+    pub latency: LatencyHistogram,
     pub last_request_time: Option<SystemTime>,
+    /// Keyed by [`endpoint_pattern`], so there is one entry per kind of call
+    /// rather than one per subreddit, user or post
     pub requests_by_endpoint: HashMap<String, EndpointMetrics>,
+    /// Requests per hour, kept for [`HOURLY_RETENTION`]
     pub hourly_request_counts: Vec<HourlyCount>,
 }
 
+/// How long [`ApiMetrics::hourly_request_counts`] keeps each hour
+pub const HOURLY_RETENTION: Duration = Duration::from_secs(24 * 3600);
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct EndpointMetrics {
     pub request_count: u64,
@@ -24,12 +36,174 @@ pub struct EndpointMetrics {
     pub total_response_time: Duration,
     pub min_response_time: Duration,
     pub max_response_time: Duration,
+    #[serde(default)]
+    pub latency: LatencyHistogram,
+}
+
+/// Upper bounds of the latency histogram buckets. Slower requests land in a
+/// final overflow bucket.
+pub const LATENCY_BUCKETS: [Duration; 12] = [
+    Duration::from_millis(5),
+    Duration::from_millis(10),
+    Duration::from_millis(25),
+    Duration::from_millis(50),
+    Duration::from_millis(100),
+    Duration::from_millis(250),
+    Duration::from_millis(500),
+    Duration::from_secs(1),
+    Duration::from_millis(2500),
+    Duration::from_secs(5),
+    Duration::from_secs(10),
+    Duration::from_secs(30),
+];
+
+/// Sub-buckets per power of two in the quantile buckets, as a power of two.
+/// 16 sub-buckets keep every estimate within about 3% of the true value.
+const SUB_BUCKET_BITS: u32 = 4;
+const SUB_BUCKETS: u64 = 1 << SUB_BUCKET_BITS;
+/// Response times are clamped to 2^36µs (about 19 hours)
+const MAX_MICROS: u64 = (1 << 36) - 1;
+
+/// Response times counted into the fixed [`LATENCY_BUCKETS`] for export, and
+/// into log-linear buckets over microseconds for percentiles. Both have a
+/// bounded number of buckets, so memory does not grow with traffic.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LatencyHistogram {
+    /// Requests per bucket, not cumulative; the last entry is the overflow
+    counts: Vec<u64>,
+    sum: Duration,
+    /// Requests per log-linear bucket, grown up to the slowest bucket seen
+    #[serde(default)]
+    quantile_counts: Vec<u64>,
+}
+
+impl Default for LatencyHistogram {
+    fn default() -> Self {
+        Self {
+            counts: vec![0; LATENCY_BUCKETS.len() + 1],
+            sum: Duration::ZERO,
+            quantile_counts: Vec::new(),
+        }
+    }
+}
+
+/// Index of the log-linear bucket holding `micros`. Values under
+/// `SUB_BUCKETS` get a bucket each; every power of two above that is split
+/// into `SUB_BUCKETS` equal parts.
+fn quantile_bucket(micros: u64) -> usize {
+    let micros = micros.min(MAX_MICROS);
+    if micros < SUB_BUCKETS {
+        return micros as usize;
+    }
+    let shift = 63 - micros.leading_zeros() - SUB_BUCKET_BITS;
+    let sub_bucket = (micros >> shift) - SUB_BUCKETS;
+    (SUB_BUCKETS * (shift as u64 + 1) + sub_bucket) as usize
+}
+
+/// The range of microseconds counted in a log-linear bucket
+fn quantile_bucket_range(index: usize) -> (u64, u64) {
+    let index = index as u64;
+    if index < SUB_BUCKETS {
+        return (index, index + 1);
+    }
+    let shift = index / SUB_BUCKETS - 1;
+    let lower = (SUB_BUCKETS + index % SUB_BUCKETS) << shift;
+    (lower, lower + (1 << shift))
+}
+
+impl LatencyHistogram {
+    pub fn record(&mut self, latency: Duration) {
+        let bucket = LATENCY_BUCKETS
+            .iter()
+            .position(|bound| latency <= *bound)
+            .unwrap_or(LATENCY_BUCKETS.len());
+        self.counts[bucket] += 1;
+        self.sum += latency;
+
+        let index = quantile_bucket(latency.as_micros().min(u64::MAX as u128) as u64);
+        if self.quantile_counts.len() <= index {
+            self.quantile_counts.resize(index + 1, 0);
+        }
+        self.quantile_counts[index] += 1;
+    }
+
+    /// Add the counts of another histogram
+    pub fn merge(&mut self, other: &LatencyHistogram) {
+        for (count, other) in self.counts.iter_mut().zip(&other.counts) {
+            *count += other;
+        }
+        self.sum += other.sum;
+        if self.quantile_counts.len() < other.quantile_counts.len() {
+            self.quantile_counts.resize(other.quantile_counts.len(), 0);
+        }
+        for (count, other) in self.quantile_counts.iter_mut().zip(&other.quantile_counts) {
+            *count += other;
+        }
+    }
+
+    /// Estimated response time at quantile `q` (0.5 for the median), taken
+    /// as the middle of the bucket holding that rank. Zero when empty.
+    pub fn percentile(&self, q: f64) -> Duration {
+        let total: u64 = self.quantile_counts.iter().sum();
+        if total == 0 {
+            return Duration::ZERO;
+        }
+
+        let rank = ((q.clamp(0.0, 1.0) * total as f64).ceil() as u64).max(1);
+        let mut seen = 0;
+        for (index, count) in self.quantile_counts.iter().enumerate() {
+            seen += count;
+            if seen >= rank {
+                let (lower, upper) = quantile_bucket_range(index);
+                return Duration::from_micros(lower + (upper - lower - 1) / 2);
+            }
+        }
+        Duration::ZERO
+    }
+
+    pub fn p50(&self) -> Duration {
+        self.percentile(0.5)
+    }
+
+    pub fn p95(&self) -> Duration {
+        self.percentile(0.95)
+    }
+
+    pub fn p99(&self) -> Duration {
+        self.percentile(0.99)
+    }
+
+    pub fn count(&self) -> u64 {
+        self.counts.iter().sum()
+    }
+
+    /// Total of every recorded latency
+    pub fn sum(&self) -> Duration {
+        self.sum
+    }
+
+    /// Requests at or under each bound, ending with `None` for all requests
+    pub fn cumulative_buckets(&self) -> Vec<(Option<Duration>, u64)> {
+        let mut total = 0;
+        self.counts
+            .iter()
+            .enumerate()
+            .map(|(index, count)| {
+                total += count;
+                (LATENCY_BUCKETS.get(index).copied(), total)
+            })
+            .collect()
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct HourlyCount {
     pub timestamp: SystemTime,
     pub request_count: u64,
+    /// Requests in this hour per [`endpoint_pattern`], so names and ids in
+    /// the path don't multiply the entries kept for every hour
+    #[serde(default)]
+    pub requests_by_pattern: HashMap<String, EndpointMetrics>,
 }
 
 #[derive(Debug, Clone)]
@@ -50,7 +224,9 @@ impl Default for ApiMetrics {
             successful_requests: 0,
             failed_requests: 0,
             rate_limited_requests: 0,
+            cache_hits: 0,
             average_response_time: Duration::from_millis(0),
+            latency: LatencyHistogram::default(),
             last_request_time: None,
             requests_by_endpoint: HashMap::new(),
             hourly_request_counts: Vec::new(),
@@ -58,6 +234,41 @@ impl Default for ApiMetrics {
     }
 }
 
+impl ApiMetrics {
+    fn hours_since(&self, since: SystemTime) -> impl Iterator<Item = &HourlyCount> {
+        self.hourly_request_counts
+            .iter()
+            .filter(move |hour| hour.timestamp >= since)
+    }
+
+    /// Requests per endpoint pattern in the hours starting at or after
+    /// `since`. Hours older than [`HOURLY_RETENTION`] are not kept.
+    pub fn requests_by_pattern_since(&self, since: SystemTime) -> HashMap<String, EndpointMetrics> {
+        let mut requests = HashMap::new();
+        for hour in self.hours_since(since) {
+            for (pattern, metrics) in &hour.requests_by_pattern {
+                requests
+                    .entry(pattern.clone())
+                    .or_insert_with(EndpointMetrics::new)
+                    .merge(metrics);
+            }
+        }
+        requests
+    }
+
+    /// Response times across every endpoint in the hours starting at or
+    /// after `since`
+    pub fn latency_since(&self, since: SystemTime) -> LatencyHistogram {
+        let mut latency = LatencyHistogram::default();
+        for hour in self.hours_since(since) {
+            for metrics in hour.requests_by_pattern.values() {
+                latency.merge(&metrics.latency);
+            }
+        }
+        latency
+    }
+}
+
 impl EndpointMetrics {
     fn new() -> Self {
         Self {
@@ -67,12 +278,14 @@ impl EndpointMetrics {
             total_response_time: Duration::from_millis(0),
             min_response_time: Duration::from_secs(u64::MAX),
             max_response_time: Duration::from_millis(0),
+            latency: LatencyHistogram::default(),
         }
     }
 
     fn update(&mut self, metrics: &RequestMetrics) {
         self.request_count += 1;
         self.total_response_time += metrics.response_time;
+        self.latency.record(metrics.response_time);
 
         if metrics.response_time < self.min_response_time {
             self.min_response_time = metrics.response_time;
@@ -88,6 +301,17 @@ impl EndpointMetrics {
         }
     }
 
+    /// Add the requests counted by another endpoint's metrics
+    pub fn merge(&mut self, other: &EndpointMetrics) {
+        self.request_count += other.request_count;
+        self.success_count += other.success_count;
+        self.error_count += other.error_count;
+        self.total_response_time += other.total_response_time;
+        self.min_response_time = self.min_response_time.min(other.min_response_time);
+        self.max_response_time = self.max_response_time.max(other.max_response_time);
+        self.latency.merge(&other.latency);
+    }
+
     pub fn average_response_time(&self) -> Duration {
         if self.request_count == 0 {
             Duration::from_millis(0)
@@ -139,55 +363,73 @@ impl MetricsCollector {
             - metrics.average_response_time
             + request_metrics.response_time;
         metrics.average_response_time = total_time / metrics.total_requests as u32;
+        metrics.latency.record(request_metrics.response_time);
 
         // Update endpoint-specific metrics
         let endpoint_metrics = metrics
             .requests_by_endpoint
-            .entry(request_metrics.endpoint.clone())
+            .entry(endpoint_pattern(&request_metrics.endpoint))
             .or_insert_with(EndpointMetrics::new);
         endpoint_metrics.update(&request_metrics);
 
         // Update hourly counts
-        self.update_hourly_counts(&mut metrics).await;
+        self.update_hourly_counts(&mut metrics, &request_metrics)
+            .await;
     }
 
-    async fn update_hourly_counts(&self, metrics: &mut ApiMetrics) {
+    async fn update_hourly_counts(
+        &self,
+        metrics: &mut ApiMetrics,
+        request_metrics: &RequestMetrics,
+    ) {
         let now = SystemTime::now();
         let current_hour =
             now.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs() / 3600 * 3600; // Round down to hour
         let current_hour_time = UNIX_EPOCH + Duration::from_secs(current_hour);
 
         // Find or create current hour entry
-        if let Some(last_entry) = metrics.hourly_request_counts.last_mut() {
-            if last_entry.timestamp == current_hour_time {
-                last_entry.request_count += 1;
-            } else {
-                metrics.hourly_request_counts.push(HourlyCount {
-                    timestamp: current_hour_time,
-                    request_count: 1,
-                });
-            }
-        } else {
+        if metrics
+            .hourly_request_counts
+            .last()
+            .map(|last| last.timestamp)
+            != Some(current_hour_time)
+        {
             metrics.hourly_request_counts.push(HourlyCount {
                 timestamp: current_hour_time,
-                request_count: 1,
+                request_count: 0,
+                requests_by_pattern: HashMap::new(),
             });
         }
+        if let Some(hour) = metrics.hourly_request_counts.last_mut() {
+            hour.request_count += 1;
+            hour.requests_by_pattern
+                .entry(endpoint_pattern(&request_metrics.endpoint))
+                .or_insert_with(EndpointMetrics::new)
+                .update(request_metrics);
+        }
 
         // Keep only last 24 hours
-        let cutoff_time = now - Duration::from_secs(24 * 3600);
+        let cutoff_time = now - HOURLY_RETENTION;
         metrics
             .hourly_request_counts
             .retain(|count| count.timestamp >= cutoff_time);
     }
 
+    pub async fn record_cache_hit(&self) {
+        self.metrics.write().await.cache_hits += 1;
+    }
+
     pub async fn get_metrics(&self) -> ApiMetrics {
         self.metrics.read().await.clone()
     }
 
+    /// Metrics for every endpoint sharing `endpoint`'s pattern
     pub async fn get_endpoint_metrics(&self, endpoint: &str) -> Option<EndpointMetrics> {
         let metrics = self.metrics.read().await;
-        metrics.requests_by_endpoint.get(endpoint).cloned()
+        metrics
+            .requests_by_endpoint
+            .get(&endpoint_pattern(endpoint))
+            .cloned()
     }
 
     pub async fn get_requests_per_minute(&self) -> f64 {
@@ -289,6 +531,113 @@ mod tests {
         assert_eq!(metrics.average_response_time(), Duration::from_millis(100));
     }
 
+    #[test]
+    fn test_latency_histogram_buckets() {
+        let mut histogram = LatencyHistogram::default();
+        for millis in [3, 80, 100, 700, 45_000] {
+            histogram.record(Duration::from_millis(millis));
+        }
+
+        let buckets = histogram.cumulative_buckets();
+        assert_eq!(buckets.len(), LATENCY_BUCKETS.len() + 1);
+        assert_eq!(buckets[0], (Some(Duration::from_millis(5)), 1));
+        assert_eq!(buckets[4], (Some(Duration::from_millis(100)), 3));
+        assert_eq!(buckets[7], (Some(Duration::from_secs(1)), 4));
+        assert_eq!(buckets[12], (None, 5));
+        assert_eq!(histogram.count(), 5);
+        assert_eq!(histogram.sum(), Duration::from_millis(45_883));
+    }
+
+    #[test]
+    fn test_latency_percentiles() {
+        let mut histogram = LatencyHistogram::default();
+        assert_eq!(histogram.p50(), Duration::ZERO);
+
+        for millis in 1..=1000 {
+            histogram.record(Duration::from_millis(millis));
+        }
+        for (estimate, exact) in [
+            (histogram.p50(), 500.0),
+            (histogram.p95(), 950.0),
+            (histogram.p99(), 990.0),
+        ] {
+            let error = (estimate.as_secs_f64() * 1000.0 - exact).abs() / exact;
+            assert!(error < 0.04, "{:?} is not close to {}ms", estimate, exact);
+        }
+
+        // Merging keeps the shape of both histograms
+        let mut slow = LatencyHistogram::default();
+        for _ in 0..1000 {
+            slow.record(Duration::from_secs(20));
+        }
+        histogram.merge(&slow);
+        assert_eq!(histogram.count(), 2000);
+        assert!(histogram.p99() > Duration::from_secs(19));
+        assert!(histogram.percentile(0.25) < Duration::from_millis(520));
+    }
+
+    #[tokio::test]
+    async fn test_recent_requests_are_grouped_by_pattern() {
+        let collector = MetricsCollector::new();
+        for (endpoint, millis) in [("/r/rust/new", 100), ("/r/golang/new", 300)] {
+            collector
+                .record_request(RequestMetrics {
+                    endpoint: endpoint.to_string(),
+                    method: "GET".to_string(),
+                    status_code: Some(200),
+                    response_time: Duration::from_millis(millis),
+                    success: true,
+                    rate_limited: false,
+                    error_type: None,
+                })
+                .await;
+        }
+
+        // One entry per pattern, however many subreddits are polled
+        let mut metrics = collector.get_metrics().await;
+        assert_eq!(metrics.requests_by_endpoint.len(), 1);
+        assert_eq!(metrics.requests_by_endpoint["/r/*/new"].request_count, 2);
+        assert_eq!(
+            collector
+                .get_endpoint_metrics("/r/python/new")
+                .await
+                .map(|metrics| metrics.request_count),
+            Some(2)
+        );
+
+        // A slow hour from two days ago falls outside the window
+        let mut stale = EndpointMetrics::new();
+        stale.update(&RequestMetrics {
+            endpoint: "/r/rust/new".to_string(),
+            method: "GET".to_string(),
+            status_code: Some(500),
+            response_time: Duration::from_secs(20),
+            success: false,
+            rate_limited: false,
+            error_type: None,
+        });
+        let now = SystemTime::now();
+        metrics.hourly_request_counts.insert(
+            0,
+            HourlyCount {
+                timestamp: now - 2 * HOURLY_RETENTION,
+                request_count: 1,
+                requests_by_pattern: HashMap::from([("/r/*/new".to_string(), stale)]),
+            },
+        );
+
+        let since = now - HOURLY_RETENTION;
+        let recent = metrics.requests_by_pattern_since(since);
+        assert_eq!(recent.len(), 1);
+        assert_eq!(recent["/r/*/new"].request_count, 2);
+        assert_eq!(recent["/r/*/new"].error_count, 0);
+
+        let latency = metrics.latency_since(since);
+        assert_eq!(latency.count(), 2);
+        assert!(latency.p99() < Duration::from_secs(1));
+        assert_eq!(metrics.latency_since(now - 3 * HOURLY_RETENTION).count(), 3);
+    }
+
     #[tokio::test]
     async fn test_export_metrics() {
         let collector = MetricsCollector::new();
@@ -0,0 +1,966 @@
+//! In-process fake Reddit server for integration tests.
+//!
+//! [`MockRedditServer`] binds to an ephemeral localhost port and serves just
+//! enough of the Reddit API for the client to be exercised end to end:
+//! subreddit listings, `/r/{sub}/about`, `/api/v1/me`,
+//! `/subreddits/mine/subscriber`, search, comment trees with
+//! `/api/morechildren` and the OAuth token and revocation endpoints.
+//! Successful GETs carry an `ETag` and honour `If-None-Match`. Tests can
+//! also script one-off responses (429s, 5xx, ...) that are returned before
+//! the normal routing kicks in.
+//!
+//! Only built with the `mock-server` feature, which the crate's own tests
+//! turn on.
+//!
+//! ```no_run
+//! # async fn example() -> std::io::Result<()> {
+//! use reddit_client::mock_server::{MockRedditServer, ScriptedResponse};
+//!
+//! let server = MockRedditServer::start().await?;
+//! server.add_post(MockRedditServer::sample_post("rust", "abc123", 1_700_000_000));
+//! server.enqueue_response(ScriptedResponse::rate_limited(0).for_path("/r/rust"));
+//!
+//! let endpoints = server.endpoints();
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::api::{RedditListing, RedditListingChild, RedditListingData, RedditPostData};
+use crate::api::{RedditSubredditData, RedditUserData};
+use crate::local_http::{read_request, write_response, HttpRequest, HttpResponse};
+use crate::RedditEndpoints;
+use likeminded_core::RedditComment;
+use serde_json::{json, Value};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::hash::{Hash, Hasher};
+use std::io;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use tokio::net::TcpListener;
+use tokio::task::JoinHandle;
+use tracing::debug;
+
+/// Access token handed out by the mock token endpoint
+pub const MOCK_ACCESS_TOKEN: &str = "mock-access-token";
+
+/// Refresh token handed out by the mock token endpoint
+pub const MOCK_REFRESH_TOKEN: &str = "mock-refresh-token";
+
+/// Credentials accepted by the mock password grant
+pub const MOCK_USERNAME: &str = "mock_user";
+pub const MOCK_PASSWORD: &str = "mock-password";
+
+/// Default per-window budget advertised in the `X-Ratelimit-*` headers
+const DEFAULT_RATE_LIMIT_BUDGET: u32 = 600;
+
+/// Default window length advertised in the `X-Ratelimit-Reset` header
+const DEFAULT_RATE_LIMIT_RESET_SECONDS: u64 = 600;
+
+const LISTING_SORTS: &[&str] = &["hot", "new", "top", "rising", "controversial"];
+
+/// A canned response returned by the mock server ahead of normal routing
+#[derive(Debug, Clone)]
+pub struct ScriptedResponse {
+    path_prefix: Option<String>,
+    status: u16,
+    headers: Vec<(String, String)>,
+    body: String,
+}
+
+impl ScriptedResponse {
+    pub fn new(status: u16, body: &str) -> Self {
+        Self {
+            path_prefix: None,
+            status,
+            headers: vec![(
+                "Content-Type".to_string(),
+                "application/json; charset=UTF-8".to_string(),
+            )],
+            body: body.to_string(),
+        }
+    }
+
+    /// A 429 with the given `Retry-After` value in seconds
+    pub fn rate_limited(retry_after_seconds: u64) -> Self {
+        Self::new(429, r#"{"message": "Too Many Requests", "error": 429}"#)
+            .with_header("Retry-After", &retry_after_seconds.to_string())
+    }
+
+    /// A bare server error such as 500 or 503
+    pub fn server_error(status: u16) -> Self {
+        Self::new(status, r#"{"message": "Server Error"}"#)
+    }
+
+    /// Only use this response for requests whose path starts with `prefix`
+    pub fn for_path(mut self, prefix: &str) -> Self {
+        self.path_prefix = Some(prefix.to_string());
+        self
+    }
+
+    pub fn with_header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    fn matches(&self, path: &str) -> bool {
+        self.path_prefix
+            .as_deref()
+            .is_none_or(|prefix| path.starts_with(prefix))
+    }
+
+    fn to_response(&self) -> HttpResponse {
+        HttpResponse {
+            status: self.status,
+            headers: self.headers.clone(),
+            body: self.body.clone().into_bytes(),
+        }
+    }
+}
+
+/// A request as seen by the mock server
+#[derive(Debug, Clone)]
+pub struct RecordedRequest {
+    pub method: String,
+    pub path: String,
+    pub query: Vec<(String, String)>,
+    pub headers: HashMap<String, String>,
+    pub body: String,
+}
+
+impl RecordedRequest {
+    pub fn query_param(&self, name: &str) -> Option<&str> {
+        self.query
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+#[derive(Debug)]
+struct MockState {
+    /// Posts keyed by lowercase subreddit name, in insertion order
+    posts: HashMap<String, Vec<RedditPostData>>,
+    /// Subreddit metadata keyed by lowercase subreddit name
+    subreddits: HashMap<String, RedditSubredditData>,
+    /// Flat comment lists keyed by post id, in insertion order
+    comments: HashMap<String, Vec<RedditComment>>,
+    user: RedditUserData,
+    scripted: VecDeque<ScriptedResponse>,
+    requests: Vec<RecordedRequest>,
+    /// Tokens revoked through `/api/v1/revoke_token` or [`MockRedditServer::revoke_token`]
+    revoked_tokens: HashSet<String>,
+    /// Requests left in the advertised rate limit window
+    rate_limit_remaining: u32,
+    rate_limit_used: u32,
+    rate_limit_reset_seconds: u64,
+}
+
+/// Fake Reddit API server bound to `127.0.0.1`
+#[derive(Debug)]
+pub struct MockRedditServer {
+    addr: SocketAddr,
+    state: Arc<Mutex<MockState>>,
+    handle: JoinHandle<()>,
+}
+
+impl MockRedditServer {
+    /// Bind to an ephemeral port and start serving in the background
+    pub async fn start() -> io::Result<Self> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+
+        let state = Arc::new(Mutex::new(MockState {
+            posts: HashMap::new(),
+            subreddits: HashMap::new(),
+            comments: HashMap::new(),
+            user: Self::sample_user("mock_user"),
+            scripted: VecDeque::new(),
+            requests: Vec::new(),
+            revoked_tokens: HashSet::new(),
+            rate_limit_remaining: DEFAULT_RATE_LIMIT_BUDGET,
+            rate_limit_used: 0,
+            rate_limit_reset_seconds: DEFAULT_RATE_LIMIT_RESET_SECONDS,
+        }));
+
+        let server_state = state.clone();
+        let handle = tokio::spawn(async move {
+            loop {
+                let (mut stream, _) = match listener.accept().await {
+                    Ok(connection) => connection,
+                    Err(e) => {
+                        debug!("Mock Reddit server accept failed: {}", e);
+                        continue;
+                    }
+                };
+
+                let state = server_state.clone();
+                tokio::spawn(async move {
+                    if let Ok(Some(request)) = read_request(&mut stream).await {
+                        let response = handle_request(&state, request);
+                        let _ = write_response(&mut stream, &response).await;
+                    }
+                });
+            }
+        });
+
+        debug!("Mock Reddit server listening on {}", addr);
+        Ok(Self {
+            addr,
+            state,
+            handle,
+        })
+    }
+
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    pub fn base_url(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+
+    /// Endpoints pointing every Reddit URL at this server
+    pub fn endpoints(&self) -> RedditEndpoints {
+        RedditEndpoints::with_base_url(&self.base_url())
+    }
+
+    /// Add a post to its subreddit's listing
+    pub fn add_post(&self, post: RedditPostData) {
+        let mut state = self.state.lock().unwrap();
+        let key = post.subreddit.to_lowercase();
+        if !state.subreddits.contains_key(&key) {
+            let subreddit = Self::sample_subreddit(&post.subreddit);
+            state.subreddits.insert(key.clone(), subreddit);
+        }
+        state.posts.entry(key).or_default().push(post);
+    }
+
+    /// Register subreddit metadata served by `/r/{sub}/about`
+    pub fn add_subreddit(&self, subreddit: RedditSubredditData) {
+        let mut state = self.state.lock().unwrap();
+        state
+            .subreddits
+            .insert(subreddit.display_name.to_lowercase(), subreddit);
+    }
+
+    /// Add a comment to a post. Replies and depth are derived from
+    /// `parent_id`, so `replies` and `depth` on the comment are ignored.
+    pub fn add_comment(&self, comment: RedditComment) {
+        let mut state = self.state.lock().unwrap();
+        state
+            .comments
+            .entry(comment.post_id.clone())
+            .or_default()
+            .push(comment);
+    }
+
+    /// Replace the user returned by `/api/v1/me`
+    pub fn set_user(&self, user: RedditUserData) {
+        self.state.lock().unwrap().user = user;
+    }
+
+    /// Set the budget advertised in the `X-Ratelimit-*` headers of API responses
+    pub fn set_rate_limit_budget(&self, remaining: u32, reset_seconds: u64) {
+        let mut state = self.state.lock().unwrap();
+        state.rate_limit_remaining = remaining;
+        state.rate_limit_used = 0;
+        state.rate_limit_reset_seconds = reset_seconds;
+    }
+
+    /// Queue a response to be returned for the next matching request
+    pub fn enqueue_response(&self, response: ScriptedResponse) {
+        self.state.lock().unwrap().scripted.push_back(response);
+    }
+
+    /// Every request received so far, in arrival order
+    pub fn requests(&self) -> Vec<RecordedRequest> {
+        self.state.lock().unwrap().requests.clone()
+    }
+
+    /// Revoke a token as if the user removed the app in their Reddit
+    /// preferences. Refreshing a revoked refresh token fails with
+    /// `invalid_grant` until the user authorizes the app again.
+    pub fn revoke_token(&self, token: &str) {
+        self.state
+            .lock()
+            .unwrap()
+            .revoked_tokens
+            .insert(token.to_string());
+    }
+
+    pub fn is_token_revoked(&self, token: &str) -> bool {
+        self.state.lock().unwrap().revoked_tokens.contains(token)
+    }
+
+    /// Number of requests received whose path starts with `path_prefix`
+    pub fn request_count(&self, path_prefix: &str) -> usize {
+        self.state
+            .lock()
+            .unwrap()
+            .requests
+            .iter()
+            .filter(|request| request.path.starts_with(path_prefix))
+            .count()
+    }
+
+    pub fn clear_requests(&self) {
+        self.state.lock().unwrap().requests.clear();
+    }
+
+    /// Build a self post with sensible defaults
+    pub fn sample_post(subreddit: &str, id: &str, created_utc: i64) -> RedditPostData {
+        RedditPostData {
+            id: id.to_string(),
+            title: format!("Post {}", id),
+            selftext: format!("Body of post {}", id),
+            author: "mock_author".to_string(),
+            subreddit: subreddit.to_string(),
+            subreddit_name_prefixed: format!("r/{}", subreddit),
+            url: format!("https://www.reddit.com/r/{}/comments/{}/", subreddit, id),
+            permalink: format!("/r/{}/comments/{}/", subreddit, id),
+            created_utc: created_utc as f64,
+            score: 1,
+            num_comments: 0,
+            over_18: false,
+            stickied: false,
+            locked: false,
+            ups: 1,
+            downs: 0,
+            upvote_ratio: Some(1.0),
+            thumbnail: None,
+            is_self: true,
+            domain: format!("self.{}", subreddit),
+        }
+    }
+
+    /// Build a comment; `parent` is the id of the comment replied to, or
+    /// `None` for a top-level comment
+    pub fn sample_comment(post_id: &str, id: &str, parent: Option<&str>) -> RedditComment {
+        RedditComment {
+            id: id.to_string(),
+            post_id: post_id.to_string(),
+            parent_id: match parent {
+                Some(parent) => format!("t1_{}", parent),
+                None => format!("t3_{}", post_id),
+            },
+            author: "commenter".to_string(),
+            body: format!("Comment {}", id),
+            score: 1,
+            created_utc: 1_700_000_000,
+            depth: 0,
+            stickied: false,
+            replies: Vec::new(),
+        }
+    }
+
+    pub fn sample_subreddit(name: &str) -> RedditSubredditData {
+        RedditSubredditData {
+            id: name.to_lowercase(),
+            name: format!("t5_{}", name.to_lowercase()),
+            display_name: name.to_string(),
+            title: format!("r/{}", name),
+            description: String::new(),
+            subscribers: 1,
+            active_user_count: None,
+            created_utc: 1_500_000_000.0,
+            over18: false,
+            lang: "en".to_string(),
+            url: format!("/r/{}/", name),
+            icon_img: None,
+            header_img: None,
+        }
+    }
+
+    pub fn sample_user(name: &str) -> RedditUserData {
+        RedditUserData {
+            id: "mockuser".to_string(),
+            name: name.to_string(),
+            created_utc: 1_500_000_000.0,
+            link_karma: 1,
+            comment_karma: 1,
+            is_gold: false,
+            is_mod: false,
+            verified: true,
+            has_verified_email: true,
+        }
+    }
+}
+
+impl Drop for MockRedditServer {
+    fn drop(&mut self) {
+        self.handle.abort();
+    }
+}
+
+fn handle_request(state: &Mutex<MockState>, request: HttpRequest) -> HttpResponse {
+    let mut state = state.lock().unwrap();
+
+    state.requests.push(RecordedRequest {
+        method: request.method.clone(),
+        path: request.path.clone(),
+        query: request.query.clone(),
+        headers: request.headers.clone(),
+        body: String::from_utf8_lossy(&request.body).to_string(),
+    });
+
+    if let Some(position) = state
+        .scripted
+        .iter()
+        .position(|scripted| scripted.matches(&request.path))
+    {
+        let scripted = state.scripted.remove(position).unwrap();
+        return scripted.to_response();
+    }
+
+    if request.path.starts_with("/api/v1/access_token") {
+        return token_response(&mut state, &request);
+    }
+    if request.path.starts_with("/api/v1/revoke_token") {
+        return revoke_response(&mut state, &request);
+    }
+
+    // API responses carry Reddit's rate limit headers
+    state.rate_limit_used += 1;
+    state.rate_limit_remaining = state.rate_limit_remaining.saturating_sub(1);
+    with_etag(route(&state, &request), &request)
+        .with_header("X-Ratelimit-Used", &state.rate_limit_used.to_string())
+        .with_header(
+            "X-Ratelimit-Remaining",
+            &format!("{:.1}", state.rate_limit_remaining as f64),
+        )
+        .with_header(
+            "X-Ratelimit-Reset",
+            &state.rate_limit_reset_seconds.to_string(),
+        )
+}
+
+fn route(state: &MockState, request: &HttpRequest) -> HttpResponse {
+    let segments: Vec<&str> = request
+        .path
+        .trim_matches('/')
+        .split('/')
+        .filter(|segment| !segment.is_empty())
+        .collect();
+
+    match (request.method.as_str(), segments.as_slice()) {
+        (_, ["api", "v1", "authorize"]) => HttpResponse::html(200, "<html>authorize</html>"),
+        _ if !has_bearer_token(request) => {
+            HttpResponse::json(401, &json!({ "message": "Unauthorized", "error": 401 }))
+        }
+        ("GET", ["api", "v1", "me"]) => HttpResponse::json(200, &json!(state.user)),
+        ("GET", ["subreddits", "mine", "subscriber"]) => subscriptions_response(state, request),
+        ("GET", ["search"]) => search_response(state, None, request),
+        ("GET", ["r", subreddit, "search"]) => search_response(state, Some(subreddit), request),
+        ("GET", ["comments", post_id, ..]) => comments_response(state, post_id, request),
+        ("GET", ["api", "morechildren"]) => more_children_response(state, request),
+        ("GET", ["r", subreddit, "about"]) => {
+            match state.subreddits.get(&subreddit.to_lowercase()) {
+                Some(data) => HttpResponse::json(
+                    200,
+                    &json!(RedditListingChild {
+                        kind: "t5".to_string(),
+                        data: data.clone(),
+                    }),
+                ),
+                None => not_found(),
+            }
+        }
+        ("GET", ["r", subreddit, sort]) if LISTING_SORTS.contains(sort) => {
+            listing_response(state, subreddit, sort, request)
+        }
+        _ => not_found(),
+    }
+}
+
+/// Tag successful GETs with an ETag of the body and answer a matching
+/// `If-None-Match` with `304 Not Modified`
+fn with_etag(response: HttpResponse, request: &HttpRequest) -> HttpResponse {
+    if request.method != "GET" || response.status != 200 {
+        return response;
+    }
+
+    let mut hasher = DefaultHasher::new();
+    response.body.hash(&mut hasher);
+    let etag = format!("\"{:016x}\"", hasher.finish());
+
+    if request.header("if-none-match") == Some(etag.as_str()) {
+        return HttpResponse {
+            status: 304,
+            headers: Vec::new(),
+            body: Vec::new(),
+        }
+        .with_header("ETag", &etag);
+    }
+    response.with_header("ETag", &etag)
+}
+
+fn has_bearer_token(request: &HttpRequest) -> bool {
+    request
+        .header("authorization")
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .is_some_and(|token| !token.trim().is_empty())
+}
+
+fn token_response(state: &mut MockState, request: &HttpRequest) -> HttpResponse {
+    if request.header("authorization").is_none() {
+        return HttpResponse::json(401, &json!({ "error": "invalid_client" }));
+    }
+
+    let form = request.form_params();
+    let param = |name: &str| {
+        form.iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    };
+    let grant_type = param("grant_type").unwrap_or_default();
+
+    match grant_type {
+        "authorization_code" | "refresh_token" => {
+            if grant_type == "refresh_token"
+                && param("refresh_token").is_some_and(|token| state.revoked_tokens.contains(token))
+            {
+                return HttpResponse::json(400, &json!({ "error": "invalid_grant" }));
+            }
+            // A fresh authorization issues a usable refresh token again
+            state.revoked_tokens.remove(MOCK_REFRESH_TOKEN);
+            HttpResponse::json(
+                200,
+                &json!({
+                    "access_token": MOCK_ACCESS_TOKEN,
+                    "token_type": "bearer",
+                    "expires_in": 3600,
+                    "scope": "identity read mysubreddits",
+                    "refresh_token": MOCK_REFRESH_TOKEN,
+                }),
+            )
+        }
+        // Like Reddit, a rejected password is a 200 carrying an error
+        "password" => match (param("username"), param("password")) {
+            (Some(MOCK_USERNAME), Some(MOCK_PASSWORD)) => token_without_refresh(),
+            _ => HttpResponse::json(200, &json!({ "error": "invalid_grant" })),
+        },
+        "client_credentials" => token_without_refresh(),
+        "https://oauth.reddit.com/grants/installed_client" => match param("device_id") {
+            Some(_) => token_without_refresh(),
+            None => HttpResponse::json(400, &json!({ "error": "invalid_request" })),
+        },
+        _ => HttpResponse::json(400, &json!({ "error": "unsupported_grant_type" })),
+    }
+}
+
+fn revoke_response(state: &mut MockState, request: &HttpRequest) -> HttpResponse {
+    if request.method != "POST" || request.header("authorization").is_none() {
+        return HttpResponse::json(401, &json!({ "error": "invalid_client" }));
+    }
+
+    let token = request
+        .form_params()
+        .into_iter()
+        .find(|(key, _)| key == "token")
+        .map(|(_, value)| value);
+    match token {
+        Some(token) => {
+            state.revoked_tokens.insert(token);
+            HttpResponse::new(204, "text/plain", Vec::new())
+        }
+        None => HttpResponse::json(400, &json!({ "error": "invalid_request" })),
+    }
+}
+
+/// Response for the grants that never issue a refresh token
+fn token_without_refresh() -> HttpResponse {
+    HttpResponse::json(
+        200,
+        &json!({
+            "access_token": MOCK_ACCESS_TOKEN,
+            "token_type": "bearer",
+            "expires_in": 3600,
+            "scope": "*",
+        }),
+    )
+}
+
+fn listing_response(
+    state: &MockState,
+    subreddit: &str,
+    sort: &str,
+    request: &HttpRequest,
+) -> HttpResponse {
+    // Multireddit paths (`a+b+c`) merge the listings of every known subreddit
+    let keys: Vec<String> = subreddit
+        .split('+')
+        .map(|name| name.to_lowercase())
+        .filter(|key| state.subreddits.contains_key(key))
+        .collect();
+    if keys.is_empty() {
+        return not_found();
+    }
+
+    let mut posts: Vec<RedditPostData> = keys
+        .iter()
+        .flat_map(|key| state.posts.get(key).cloned().unwrap_or_default())
+        .collect();
+    if sort == "new" {
+        posts.sort_by(|a, b| b.created_utc.total_cmp(&a.created_utc));
+    }
+
+    let listing = paginate(posts, request, |post| format!("t3_{}", post.id), "t3");
+    HttpResponse::json(200, &json!(listing))
+}
+
+/// Case-insensitive substring match on title and selftext. Sorting by `new`
+/// is honoured; every other sort keeps insertion order.
+fn search_response(
+    state: &MockState,
+    subreddit: Option<&str>,
+    request: &HttpRequest,
+) -> HttpResponse {
+    let query = request.query_param("q").unwrap_or_default().to_lowercase();
+    if query.trim().is_empty() {
+        return HttpResponse::json(400, &json!({ "message": "Bad Request", "error": 400 }));
+    }
+
+    let restrict_to = subreddit
+        .filter(|_| {
+            request
+                .query_param("restrict_sr")
+                .is_some_and(|v| v == "on" || v == "true")
+        })
+        .map(|subreddit| subreddit.to_lowercase());
+
+    let mut posts: Vec<RedditPostData> = state
+        .posts
+        .iter()
+        .filter(|(key, _)| {
+            restrict_to
+                .as_ref()
+                .is_none_or(|restrict_to| *key == restrict_to)
+        })
+        .flat_map(|(_, posts)| posts.iter())
+        .filter(|post| {
+            post.title.to_lowercase().contains(&query)
+                || post.selftext.to_lowercase().contains(&query)
+        })
+        .cloned()
+        .collect();
+
+    // Posts live in a HashMap keyed by subreddit, so pin down an order first
+    posts.sort_by(|a, b| a.subreddit.cmp(&b.subreddit));
+    if request.query_param("sort") == Some("new") {
+        posts.sort_by(|a, b| b.created_utc.total_cmp(&a.created_utc));
+    }
+
+    let listing = paginate(posts, request, |post| format!("t3_{}", post.id), "t3");
+    HttpResponse::json(200, &json!(listing))
+}
+
+/// The mock treats every known subreddit as one the user is subscribed to,
+/// served in name order
+fn subscriptions_response(state: &MockState, request: &HttpRequest) -> HttpResponse {
+    let mut subreddits: Vec<RedditSubredditData> = state.subreddits.values().cloned().collect();
+    subreddits.sort_by_key(|subreddit| subreddit.display_name.to_lowercase());
+
+    let listing = paginate(
+        subreddits,
+        request,
+        |subreddit| subreddit.name.clone(),
+        "t5",
+    );
+    HttpResponse::json(200, &json!(listing))
+}
+
+/// Cut one page out of `items` honouring the `limit` and `after` query
+/// parameters the way Reddit listings do
+fn paginate<T>(
+    items: Vec<T>,
+    request: &HttpRequest,
+    fullname: impl Fn(&T) -> String,
+    kind: &str,
+) -> RedditListing<T> {
+    let limit = request
+        .query_param("limit")
+        .and_then(|value| value.parse::<usize>().ok())
+        .unwrap_or(25)
+        .min(100);
+
+    let start = match request.query_param("after") {
+        Some(after) => items
+            .iter()
+            .position(|item| fullname(item) == after)
+            .map_or(items.len(), |position| position + 1),
+        None => 0,
+    };
+
+    let total = items.len();
+    let page: Vec<T> = items.into_iter().skip(start).take(limit).collect();
+    let after = if start + page.len() < total {
+        page.last().map(&fullname)
+    } else {
+        None
+    };
+
+    RedditListing {
+        kind: "Listing".to_string(),
+        data: RedditListingData {
+            dist: Some(page.len() as u32),
+            children: page
+                .into_iter()
+                .map(|data| RedditListingChild {
+                    kind: kind.to_string(),
+                    data,
+                })
+                .collect(),
+            after,
+            before: None,
+            modhash: None,
+        },
+    }
+}
+
+/// Render a post's comment tree. Only the first `limit` top-level comments
+/// and `depth` levels are inlined; the rest is collapsed into `more` stubs.
+fn comments_response(state: &MockState, post_id: &str, request: &HttpRequest) -> HttpResponse {
+    let Some(post) = state
+        .posts
+        .values()
+        .flatten()
+        .find(|post| post.id == post_id)
+    else {
+        return not_found();
+    };
+
+    let comments = state.comments.get(post_id).cloned().unwrap_or_default();
+    let limit = request
+        .query_param("limit")
+        .and_then(|value| value.parse::<usize>().ok());
+    let depth = request
+        .query_param("depth")
+        .and_then(|value| value.parse::<u32>().ok());
+
+    let post_listing = RedditListing {
+        kind: "Listing".to_string(),
+        data: RedditListingData {
+            dist: Some(1),
+            children: vec![RedditListingChild {
+                kind: "t3".to_string(),
+                data: post.clone(),
+            }],
+            after: None,
+            before: None,
+            modhash: None,
+        },
+    };
+    let comment_listing =
+        render_comment_level(&comments, &format!("t3_{}", post_id), 0, depth, limit);
+
+    HttpResponse::json(200, &json!([post_listing, comment_listing]))
+}
+
+fn render_comment_level(
+    comments: &[RedditComment],
+    parent_id: &str,
+    depth: u32,
+    max_depth: Option<u32>,
+    limit: Option<usize>,
+) -> Value {
+    let children: Vec<&RedditComment> = comments
+        .iter()
+        .filter(|comment| comment.parent_id == parent_id)
+        .collect();
+
+    // Reddit's `depth` counts levels from 1
+    let inline_count = match max_depth {
+        Some(max_depth) if depth >= max_depth => 0,
+        _ => limit.unwrap_or(children.len()).min(children.len()),
+    };
+
+    let mut rendered: Vec<Value> = children[..inline_count]
+        .iter()
+        .map(|comment| {
+            let has_replies = comments
+                .iter()
+                .any(|reply| reply.parent_id == format!("t1_{}", comment.id));
+            let replies = if has_replies {
+                render_comment_level(
+                    comments,
+                    &format!("t1_{}", comment.id),
+                    depth + 1,
+                    max_depth,
+                    None,
+                )
+            } else {
+                json!("")
+            };
+            comment_json(comment, depth, replies)
+        })
+        .collect();
+
+    let hidden = &children[inline_count..];
+    if !hidden.is_empty() {
+        let count: usize = hidden
+            .iter()
+            .map(|comment| subtree(comments, comment).len())
+            .sum();
+        rendered.push(json!({
+            "kind": "more",
+            "data": {
+                "count": count,
+                "name": format!("t1_{}", hidden[0].id),
+                "id": hidden[0].id,
+                "parent_id": parent_id,
+                "depth": depth,
+                "children": hidden.iter().map(|comment| comment.id.as_str()).collect::<Vec<_>>(),
+            }
+        }));
+    }
+
+    json!({ "kind": "Listing", "data": { "children": rendered, "after": null, "before": null } })
+}
+
+/// Return the requested comments with all of their replies, flattened with
+/// parents first the way `/api/morechildren` does
+fn more_children_response(state: &MockState, request: &HttpRequest) -> HttpResponse {
+    let post_id = request
+        .query_param("link_id")
+        .unwrap_or_default()
+        .trim_start_matches("t3_");
+    let comments = state.comments.get(post_id).cloned().unwrap_or_default();
+
+    let things: Vec<Value> = request
+        .query_param("children")
+        .unwrap_or_default()
+        .split(',')
+        .filter_map(|id| comments.iter().find(|comment| comment.id == id))
+        .flat_map(|comment| subtree(&comments, comment))
+        .map(|comment| comment_json(comment, comment_depth(&comments, comment), json!("")))
+        .collect();
+
+    HttpResponse::json(
+        200,
+        &json!({ "json": { "errors": [], "data": { "things": things } } }),
+    )
+}
+
+/// `root` followed by all of its descendants, depth first
+fn subtree<'a>(comments: &'a [RedditComment], root: &'a RedditComment) -> Vec<&'a RedditComment> {
+    let mut result = vec![root];
+    let fullname = format!("t1_{}", root.id);
+    for reply in comments
+        .iter()
+        .filter(|comment| comment.parent_id == fullname)
+    {
+        result.extend(subtree(comments, reply));
+    }
+    result
+}
+
+fn comment_depth(comments: &[RedditComment], comment: &RedditComment) -> u32 {
+    let mut depth = 0;
+    let mut parent_id = comment.parent_id.as_str();
+    while let Some(parent) = parent_id
+        .strip_prefix("t1_")
+        .and_then(|id| comments.iter().find(|comment| comment.id == id))
+    {
+        depth += 1;
+        parent_id = parent.parent_id.as_str();
+    }
+    depth
+}
+
+fn comment_json(comment: &RedditComment, depth: u32, replies: Value) -> Value {
+    json!({
+        "kind": "t1",
+        "data": {
+            "id": comment.id,
+            "name": format!("t1_{}", comment.id),
+            "parent_id": comment.parent_id,
+            "link_id": format!("t3_{}", comment.post_id),
+            "author": comment.author,
+            "body": comment.body,
+            "score": comment.score,
+            "created_utc": comment.created_utc as f64,
+            "depth": depth,
+            "stickied": comment.stickied,
+            "replies": replies,
+        }
+    })
+}
+
+fn not_found() -> HttpResponse {
+    HttpResponse::json(404, &json!({ "message": "Not Found", "error": 404 }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_listing_pagination() {
+        let server = MockRedditServer::start().await.unwrap();
+        for i in 0..5 {
+            server.add_post(MockRedditServer::sample_post(
+                "rust",
+                &format!("p{}", i),
+                1000 + i,
+            ));
+        }
+
+        let client = reqwest::Client::new();
+        let first: serde_json::Value = client
+            .get(format!("{}/r/rust/new?limit=3", server.base_url()))
+            .bearer_auth("token")
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+
+        assert_eq!(first["data"]["children"].as_array().unwrap().len(), 3);
+        assert_eq!(first["data"]["children"][0]["data"]["id"], "p4");
+        assert_eq!(first["data"]["after"], "t3_p2");
+
+        let second: serde_json::Value = client
+            .get(format!(
+                "{}/r/rust/new?limit=3&after=t3_p2",
+                server.base_url()
+            ))
+            .bearer_auth("token")
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+
+        assert_eq!(second["data"]["children"].as_array().unwrap().len(), 2);
+        assert!(second["data"]["after"].is_null());
+    }
+
+    #[tokio::test]
+    async fn test_scripted_response_is_consumed_once() {
+        let server = MockRedditServer::start().await.unwrap();
+        server.enqueue_response(ScriptedResponse::server_error(503).for_path("/api/v1/me"));
+
+        let client = reqwest::Client::new();
+        let url = format!("{}/api/v1/me", server.base_url());
+
+        let first = client.get(&url).bearer_auth("token").send().await.unwrap();
+        assert_eq!(first.status().as_u16(), 503);
+
+        let second = client.get(&url).bearer_auth("token").send().await.unwrap();
+        assert_eq!(second.status().as_u16(), 200);
+        assert_eq!(server.request_count("/api/v1/me"), 2);
+    }
+
+    #[tokio::test]
+    async fn test_requires_bearer_token() {
+        let server = MockRedditServer::start().await.unwrap();
+
+        let response = reqwest::get(format!("{}/api/v1/me", server.base_url()))
+            .await
+            .unwrap();
+        assert_eq!(response.status().as_u16(), 401);
+    }
+}
@@ -0,0 +1,231 @@
+//! Loopback listener for the OAuth redirect.
+//!
+//! Reddit sends the browser back to the app's `redirect_uri` with `code` and
+//! `state` in the query string. When that URI points at this machine (e.g.
+//! `http://localhost:8080/callback`), [`CallbackListener`] accepts the
+//! redirect itself so nobody has to copy the URL out of the address bar.
+
+use crate::local_http::{read_request, write_response, HttpResponse};
+use likeminded_core::{ConfigError, CoreError};
+use std::net::SocketAddr;
+use std::time::Duration;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::time::Instant;
+use url::Url;
+
+/// How long to wait for the browser to come back from Reddit by default
+pub const DEFAULT_CALLBACK_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// Per-connection limit, so a stalled client cannot hold up the listener
+const READ_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug)]
+pub struct CallbackListener {
+    listener: TcpListener,
+    redirect_uri: Url,
+}
+
+impl CallbackListener {
+    /// Bind to the port named in `redirect_uri`. Only `http` redirects to
+    /// `localhost`, `127.0.0.1` or `[::1]` are accepted.
+    pub async fn bind(redirect_uri: &str) -> Result<Self, CoreError> {
+        let redirect_uri = Url::parse(redirect_uri).map_err(|e| invalid_redirect(e.to_string()))?;
+        if redirect_uri.scheme() != "http" {
+            return Err(invalid_redirect(format!(
+                "loopback redirect must use http, got {}",
+                redirect_uri.scheme()
+            )));
+        }
+
+        let host = match redirect_uri.host_str() {
+            Some("localhost") | Some("127.0.0.1") => "127.0.0.1",
+            Some("[::1]") => "[::1]",
+            other => {
+                return Err(invalid_redirect(format!(
+                    "loopback redirect must point at this machine, got {}",
+                    other.unwrap_or_default()
+                )))
+            }
+        };
+        let port = redirect_uri.port_or_known_default().unwrap_or(80);
+
+        let listener = TcpListener::bind(format!("{}:{}", host, port)).await?;
+        tracing::debug!("Listening for OAuth callback on {}", listener.local_addr()?);
+
+        Ok(Self {
+            listener,
+            redirect_uri,
+        })
+    }
+
+    pub fn local_addr(&self) -> Result<SocketAddr, CoreError> {
+        Ok(self.listener.local_addr()?)
+    }
+
+    /// Wait for the browser to hit the redirect path. Other requests (such as
+    /// `/favicon.ico`) get a 404 and the listener keeps waiting.
+    pub async fn accept(&self, timeout: Duration) -> Result<PendingCallback, CoreError> {
+        let deadline = Instant::now() + timeout;
+
+        loop {
+            let (mut stream, _) = tokio::time::timeout_at(deadline, self.listener.accept())
+                .await
+                .map_err(|_| CoreError::Timeout {
+                    seconds: timeout.as_secs(),
+                })??;
+
+            let request = match tokio::time::timeout(READ_TIMEOUT, read_request(&mut stream)).await
+            {
+                Ok(Ok(Some(request))) => request,
+                Ok(Ok(None)) => continue,
+                Ok(Err(e)) => {
+                    tracing::debug!("Ignoring malformed callback request: {}", e);
+                    continue;
+                }
+                Err(_) => continue,
+            };
+
+            if request.method != "GET" || request.path != self.redirect_uri.path() {
+                let _ = write_response(
+                    &mut stream,
+                    &HttpResponse::new(404, "text/plain; charset=utf-8", "Not Found"),
+                )
+                .await;
+                continue;
+            }
+
+            let mut url = self.redirect_uri.clone();
+            url.set_fragment(None);
+            url.query_pairs_mut().clear().extend_pairs(&request.query);
+            return Ok(PendingCallback {
+                url: url.to_string(),
+                stream,
+            });
+        }
+    }
+}
+
+/// A redirect that has been received but not yet answered
+#[derive(Debug)]
+pub struct PendingCallback {
+    url: String,
+    stream: TcpStream,
+}
+
+impl PendingCallback {
+    /// The full callback URL, ready for [`crate::RedditClient::handle_callback`]
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Show the user a page saying whether the connection worked
+    pub async fn respond<T>(mut self, result: &Result<T, CoreError>) {
+        let response = match result {
+            Ok(_) => HttpResponse::html(200, success_page()),
+            Err(e) => HttpResponse::html(400, failure_page(&e.to_string())),
+        };
+        if let Err(e) = write_response(&mut self.stream, &response).await {
+            tracing::warn!("Failed to answer OAuth callback: {}", e);
+        }
+    }
+}
+
+fn invalid_redirect(value: String) -> CoreError {
+    CoreError::Config(ConfigError::InvalidValue {
+        field: "redirect_uri".to_string(),
+        value,
+    })
+}
+
+fn success_page() -> String {
+    page(
+        "Reddit connected",
+        "Likeminded is now connected to your Reddit account. You can close this window.",
+    )
+}
+
+fn failure_page(reason: &str) -> String {
+    page(
+        "Reddit connection failed",
+        &format!(
+            "Likeminded could not connect to your Reddit account: {}. Close this window and try again.",
+            escape_html(reason)
+        ),
+    )
+}
+
+fn page(title: &str, message: &str) -> String {
+    format!(
+        "<!DOCTYPE html><html><head><meta charset=\"utf-8\"><title>{title}</title></head>\
+         <body style=\"font-family: sans-serif; max-width: 32em; margin: 4em auto\">\
+         <h1>{title}</h1><p>{message}</p></body></html>"
+    )
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_rejects_non_loopback_redirect() {
+        assert!(CallbackListener::bind("https://example.com/callback")
+            .await
+            .is_err());
+        assert!(CallbackListener::bind("http://example.com/callback")
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_accepts_callback_and_ignores_other_paths() {
+        let listener = CallbackListener::bind("http://127.0.0.1:0/callback")
+            .await
+            .unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let browser = tokio::spawn(async move {
+            let favicon = reqwest::get(format!("http://{}/favicon.ico", addr))
+                .await
+                .unwrap();
+            assert_eq!(favicon.status().as_u16(), 404);
+
+            let response = reqwest::get(format!("http://{}/callback?state=xyz&code=abc", addr))
+                .await
+                .unwrap();
+            (response.status().as_u16(), response.text().await.unwrap())
+        });
+
+        let callback = listener.accept(Duration::from_secs(5)).await.unwrap();
+        let url = Url::parse(callback.url()).unwrap();
+        assert_eq!(url.path(), "/callback");
+        assert!(url
+            .query_pairs()
+            .any(|(key, value)| key == "code" && value == "abc"));
+
+        callback
+            .respond::<()>(&Err(CoreError::Internal {
+                message: "<bad>".to_string(),
+            }))
+            .await;
+
+        let (status, body) = browser.await.unwrap();
+        assert_eq!(status, 400);
+        assert!(body.contains("&lt;bad&gt;"));
+    }
+
+    #[tokio::test]
+    async fn test_times_out() {
+        let listener = CallbackListener::bind("http://localhost:0/callback")
+            .await
+            .unwrap();
+        let result = listener.accept(Duration::from_millis(50)).await;
+        assert!(matches!(result, Err(CoreError::Timeout { .. })));
+    }
+}
@@ -0,0 +1,532 @@
+//! OpenMetrics text exposition of the client's metrics.
+//!
+//! [`MetricsExporter`] gathers request counts and latency histograms per
+//! endpoint pattern, rate limiter tokens, circuit breaker states, retry
+//! counters and, with the `database` feature, request queue depth. Requests
+//! are labelled by pattern (`/r/*/new`, `/comments/*`) rather than path, so a
+//! long-running poller doesn't grow a series per subreddit or post. [`MetricsExporter::serve`]
+//! puts them on `GET /metrics` on a loopback port so a local Prometheus can
+//! scrape a long-running poller. Nothing listens unless `serve` is called.
+
+use crate::api::RedditApiClient;
+use crate::local_http::{read_request, write_response, HttpResponse};
+use crate::metrics::{ApiMetrics, EndpointMetrics};
+use crate::rate_limiter::RateLimitStatus;
+#[cfg(feature = "database")]
+use crate::request_queue::{QueueStats, RequestQueue};
+use crate::retry::{CircuitBreakerState, RetryMetrics};
+use likeminded_core::CoreError;
+use std::collections::btree_map::Entry;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tokio::net::TcpListener;
+use tokio::task::JoinHandle;
+use tracing::debug;
+
+/// Content type Prometheus asks for when scraping OpenMetrics
+pub const OPENMETRICS_CONTENT_TYPE: &str =
+    "application/openmetrics-text; version=1.0.0; charset=utf-8";
+
+/// Request queue depth at the time of a snapshot
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct QueueDepth {
+    /// Requests held in memory, waiting or scheduled for retry
+    pub in_memory: usize,
+    pub queued: usize,
+    pub executing: usize,
+}
+
+#[cfg(feature = "database")]
+impl From<&QueueStats> for QueueDepth {
+    fn from(stats: &QueueStats) -> Self {
+        Self {
+            in_memory: stats.total_queued,
+            queued: stats.queued,
+            executing: stats.executing,
+        }
+    }
+}
+
+/// Everything the exporter reports, read at one point in time
+#[derive(Debug, Clone)]
+pub struct MetricsSnapshot {
+    pub api: ApiMetrics,
+    /// `api.requests_by_endpoint` added up per endpoint pattern
+    pub requests_by_pattern: BTreeMap<String, EndpointMetrics>,
+    pub rate_limit: RateLimitStatus,
+    /// Tokens left per endpoint pattern in the client's router
+    pub endpoint_tokens: Vec<(String, u32)>,
+    pub circuit_breakers: BTreeMap<String, CircuitBreakerState>,
+    pub retry: RetryMetrics,
+    pub queue: Option<QueueDepth>,
+}
+
+#[derive(Debug, Clone)]
+pub struct MetricsExporter {
+    api_client: Arc<RedditApiClient>,
+    #[cfg(feature = "database")]
+    request_queue: Option<Arc<RequestQueue>>,
+}
+
+impl MetricsExporter {
+    pub fn new(api_client: Arc<RedditApiClient>) -> Self {
+        Self {
+            api_client,
+            #[cfg(feature = "database")]
+            request_queue: None,
+        }
+    }
+
+    /// Also report the depth of `request_queue`
+    #[cfg(feature = "database")]
+    pub fn with_request_queue(mut self, request_queue: Arc<RequestQueue>) -> Self {
+        self.request_queue = Some(request_queue);
+        self
+    }
+
+    pub async fn snapshot(&self) -> MetricsSnapshot {
+        let mut endpoint_tokens = Vec::new();
+        if let Some(router) = self.api_client.endpoint_router() {
+            for route in router.routes() {
+                endpoint_tokens.push((
+                    route.limits().pattern.clone(),
+                    route.available_tokens().await,
+                ));
+            }
+        }
+
+        let api = self.api_client.get_metrics().await;
+        let requests_by_pattern =
+            requests_by_pattern(&api, |endpoint| self.api_client.endpoint_pattern(endpoint));
+
+        MetricsSnapshot {
+            api,
+            requests_by_pattern,
+            rate_limit: self.api_client.get_rate_limit_status().await,
+            endpoint_tokens,
+            circuit_breakers: self.api_client.get_circuit_breaker_state(),
+            retry: self.api_client.get_retry_metrics(),
+            queue: self.queue_depth().await,
+        }
+    }
+
+    #[cfg(feature = "database")]
+    async fn queue_depth(&self) -> Option<QueueDepth> {
+        let queue = self.request_queue.as_ref()?;
+        match queue.get_queue_stats().await {
+            Ok(stats) => Some(QueueDepth::from(&stats)),
+            Err(e) => {
+                debug!("Leaving queue depth out of metrics: {}", e);
+                None
+            }
+        }
+    }
+
+    #[cfg(not(feature = "database"))]
+    async fn queue_depth(&self) -> Option<QueueDepth> {
+        None
+    }
+
+    /// The current metrics in OpenMetrics text format
+    pub async fn render(&self) -> String {
+        render(&self.snapshot().await)
+    }
+
+    /// Serve `GET /metrics` on `addr` until the returned server is dropped.
+    /// Only loopback addresses are accepted; use port 0 for any free port.
+    pub async fn serve(self, addr: SocketAddr) -> Result<MetricsServer, CoreError> {
+        if !addr.ip().is_loopback() {
+            return Err(CoreError::InvalidInput {
+                message: format!(
+                    "Metrics server must bind to a loopback address, got {}",
+                    addr
+                ),
+            });
+        }
+
+        let listener = TcpListener::bind(addr).await?;
+        let addr = listener.local_addr()?;
+        let exporter = Arc::new(self);
+
+        let handle = tokio::spawn(async move {
+            loop {
+                let (mut stream, _) = match listener.accept().await {
+                    Ok(connection) => connection,
+                    Err(e) => {
+                        debug!("Metrics server accept failed: {}", e);
+                        continue;
+                    }
+                };
+
+                let exporter = exporter.clone();
+                tokio::spawn(async move {
+                    if let Ok(Some(request)) = read_request(&mut stream).await {
+                        let response = match (request.method.as_str(), request.path.as_str()) {
+                            ("GET", "/metrics") => HttpResponse::new(
+                                200,
+                                OPENMETRICS_CONTENT_TYPE,
+                                exporter.render().await,
+                            ),
+                            (_, "/metrics") => {
+                                HttpResponse::new(405, "text/plain", "Method Not Allowed")
+                            }
+                            _ => HttpResponse::new(404, "text/plain", "Not Found"),
+                        };
+                        let _ = write_response(&mut stream, &response).await;
+                    }
+                });
+            }
+        });
+
+        debug!("Serving metrics on http://{}/metrics", addr);
+        Ok(MetricsServer { addr, handle })
+    }
+}
+
+/// Background task answering scrapes; stops when dropped
+#[derive(Debug)]
+pub struct MetricsServer {
+    addr: SocketAddr,
+    handle: JoinHandle<()>,
+}
+
+impl MetricsServer {
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// The scrape URL to put in the Prometheus config
+    pub fn url(&self) -> String {
+        format!("http://{}/metrics", self.addr)
+    }
+}
+
+impl Drop for MetricsServer {
+    fn drop(&mut self) {
+        self.handle.abort();
+    }
+}
+
+/// Add up the per-endpoint metrics in `api` under the pattern `pattern_for`
+/// gives each endpoint
+pub fn requests_by_pattern(
+    api: &ApiMetrics,
+    pattern_for: impl Fn(&str) -> String,
+) -> BTreeMap<String, EndpointMetrics> {
+    let mut by_pattern: BTreeMap<String, EndpointMetrics> = BTreeMap::new();
+    for (endpoint, metrics) in &api.requests_by_endpoint {
+        match by_pattern.entry(pattern_for(endpoint)) {
+            Entry::Occupied(mut entry) => entry.get_mut().merge(metrics),
+            Entry::Vacant(entry) => {
+                entry.insert(metrics.clone());
+            }
+        }
+    }
+    by_pattern
+}
+
+/// Write `snapshot` in the OpenMetrics text format
+pub fn render(snapshot: &MetricsSnapshot) -> String {
+    let mut out = String::new();
+    let patterns = &snapshot.requests_by_pattern;
+
+    family(
+        &mut out,
+        "reddit_requests",
+        "counter",
+        "Requests sent to Reddit",
+    );
+    for (pattern, metrics) in patterns {
+        for (outcome, count) in [
+            ("success", metrics.success_count),
+            ("error", metrics.error_count),
+        ] {
+            let _ = writeln!(
+                out,
+                "reddit_requests_total{{pattern=\"{}\",outcome=\"{}\"}} {}",
+                escape(pattern),
+                outcome,
+                count
+            );
+        }
+    }
+
+    family(
+        &mut out,
+        "reddit_rate_limited_requests",
+        "counter",
+        "Requests Reddit refused with a rate limit",
+    );
+    let _ = writeln!(
+        out,
+        "reddit_rate_limited_requests_total {}",
+        snapshot.api.rate_limited_requests
+    );
+
+    family(
+        &mut out,
+        "reddit_cache_hits",
+        "counter",
+        "Requests answered from the response cache",
+    );
+    let _ = writeln!(out, "reddit_cache_hits_total {}", snapshot.api.cache_hits);
+
+    family(
+        &mut out,
+        "reddit_request_duration_seconds",
+        "histogram",
+        "Response time per endpoint pattern",
+    );
+    for (pattern, metrics) in patterns {
+        let latency = &metrics.latency;
+        for (bound, count) in latency.cumulative_buckets() {
+            let le = match bound {
+                Some(bound) => format_seconds(bound.as_secs_f64()),
+                None => "+Inf".to_string(),
+            };
+            let _ = writeln!(
+                out,
+                "reddit_request_duration_seconds_bucket{{pattern=\"{}\",le=\"{}\"}} {}",
+                escape(pattern),
+                le,
+                count
+            );
+        }
+        let _ = writeln!(
+            out,
+            "reddit_request_duration_seconds_count{{pattern=\"{}\"}} {}",
+            escape(pattern),
+            latency.count()
+        );
+        let _ = writeln!(
+            out,
+            "reddit_request_duration_seconds_sum{{pattern=\"{}\"}} {}",
+            escape(pattern),
+            format_seconds(latency.sum().as_secs_f64())
+        );
+    }
+
+    let rate_limit = &snapshot.rate_limit;
+    family(
+        &mut out,
+        "reddit_rate_limit_tokens",
+        "gauge",
+        "Tokens left in the client's rate limiter",
+    );
+    let _ = writeln!(
+        out,
+        "reddit_rate_limit_tokens {}",
+        rate_limit.available_tokens
+    );
+    family(
+        &mut out,
+        "reddit_rate_limit_tokens_max",
+        "gauge",
+        "Capacity of the client's rate limiter",
+    );
+    let _ = writeln!(
+        out,
+        "reddit_rate_limit_tokens_max {}",
+        rate_limit.max_tokens
+    );
+    family(
+        &mut out,
+        "reddit_rate_limit_permits",
+        "gauge",
+        "Concurrent request permits available",
+    );
+    let _ = writeln!(
+        out,
+        "reddit_rate_limit_permits {}",
+        rate_limit.available_permits
+    );
+
+    if !snapshot.endpoint_tokens.is_empty() {
+        family(
+            &mut out,
+            "reddit_endpoint_rate_limit_tokens",
+            "gauge",
+            "Tokens left per endpoint pattern",
+        );
+        for (pattern, tokens) in &snapshot.endpoint_tokens {
+            let _ = writeln!(
+                out,
+                "reddit_endpoint_rate_limit_tokens{{pattern=\"{}\"}} {}",
+                escape(pattern),
+                tokens
+            );
+        }
+    }
+
+    family(
+        &mut out,
+        "reddit_circuit_breaker_state",
+        "stateset",
+        "Circuit breaker state per endpoint pattern and subreddit",
+    );
+    for (source, state) in &snapshot.circuit_breakers {
+        for (name, value) in [
+            ("closed", CircuitBreakerState::Closed),
+            ("open", CircuitBreakerState::Open),
+            ("half_open", CircuitBreakerState::HalfOpen),
+        ] {
+            let _ = writeln!(
+                out,
+                "reddit_circuit_breaker_state{{source=\"{}\",reddit_circuit_breaker_state=\"{}\"}} {}",
+                escape(source),
+                name,
+                u8::from(*state == value)
+            );
+        }
+    }
+
+    let retry = &snapshot.retry;
+    for (name, help, value) in [
+        ("reddit_retries", "Retries made", retry.total_retries),
+        (
+            "reddit_retry_successes",
+            "Operations that succeeded after retrying",
+            retry.successful_retries,
+        ),
+        (
+            "reddit_retry_failures",
+            "Operations that failed after every attempt",
+            retry.failed_retries,
+        ),
+        (
+            "reddit_circuit_breaker_rejections",
+            "Requests blocked by an open circuit breaker",
+            retry.circuit_breaker_trips,
+        ),
+    ] {
+        family(&mut out, name, "counter", help);
+        let _ = writeln!(out, "{}_total {}", name, value);
+    }
+
+    if let Some(ref queue) = snapshot.queue {
+        family(
+            &mut out,
+            "reddit_request_queue_depth",
+            "gauge",
+            "Unfinished requests in the request queue by status",
+        );
+        for (status, depth) in [("queued", queue.queued), ("executing", queue.executing)] {
+            let _ = writeln!(
+                out,
+                "reddit_request_queue_depth{{status=\"{}\"}} {}",
+                status, depth
+            );
+        }
+        family(
+            &mut out,
+            "reddit_request_queue_in_memory",
+            "gauge",
+            "Requests held in memory by the request queue",
+        );
+        let _ = writeln!(out, "reddit_request_queue_in_memory {}", queue.in_memory);
+    }
+
+    out.push_str("# EOF\n");
+    out
+}
+
+fn family(out: &mut String, name: &str, metric_type: &str, help: &str) {
+    let _ = writeln!(out, "# TYPE {} {}", name, metric_type);
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+}
+
+fn format_seconds(value: f64) -> String {
+    let formatted = format!("{}", value);
+    if formatted.contains('.') || formatted.contains('e') {
+        formatted
+    } else {
+        format!("{}.0", formatted)
+    }
+}
+
+/// Escape a label value
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::endpoint_router::endpoint_pattern;
+    use crate::metrics::{MetricsCollector, RequestMetrics};
+    use crate::rate_limiter::{RateLimitConfig, RateLimiter};
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_render_openmetrics() {
+        let collector = MetricsCollector::new();
+        for (endpoint, millis, success) in [
+            ("/r/rust/new", 80, true),
+            ("/r/golang/new", 700, false),
+            ("/api/v1/me", 20, true),
+        ] {
+            collector
+                .record_request(RequestMetrics {
+                    endpoint: endpoint.to_string(),
+                    method: "GET".to_string(),
+                    status_code: Some(if success { 200 } else { 503 }),
+                    response_time: Duration::from_millis(millis),
+                    success,
+                    rate_limited: false,
+                    error_type: None,
+                })
+                .await;
+        }
+
+        let mut circuit_breakers = BTreeMap::new();
+        circuit_breakers.insert(
+            "/r/*/new r/\"quoted\"".to_string(),
+            CircuitBreakerState::Open,
+        );
+
+        let api = collector.get_metrics().await;
+        let snapshot = MetricsSnapshot {
+            requests_by_pattern: requests_by_pattern(&api, endpoint_pattern),
+            api,
+            rate_limit: RateLimiter::new(RateLimitConfig::reddit_oauth())
+                .get_rate_limit_status()
+                .await,
+            endpoint_tokens: vec![("/r/*/new".to_string(), 28)],
+            circuit_breakers,
+            retry: RetryMetrics::default(),
+            queue: Some(QueueDepth {
+                in_memory: 4,
+                queued: 3,
+                executing: 1,
+            }),
+        };
+        let text = render(&snapshot);
+
+        assert!(text.contains("# TYPE reddit_requests counter\n"));
+        // Both subreddits' listings are one series
+        assert!(text.contains("reddit_requests_total{pattern=\"/r/*/new\",outcome=\"error\"} 1\n"));
+        assert!(
+            text.contains("reddit_requests_total{pattern=\"/r/*/new\",outcome=\"success\"} 1\n")
+        );
+        assert!(!text.contains("rust/new"));
+        assert!(text.contains(
+            "reddit_request_duration_seconds_bucket{pattern=\"/r/*/new\",le=\"0.1\"} 1\n"
+        ));
+        assert!(text.contains(
+            "reddit_request_duration_seconds_bucket{pattern=\"/r/*/new\",le=\"+Inf\"} 2\n"
+        ));
+        assert!(text.contains("reddit_request_duration_seconds_sum{pattern=\"/r/*/new\"} 0.78\n"));
+        assert!(text.contains("reddit_request_duration_seconds_count{pattern=\"/api/v1/me\"} 1\n"));
+        assert!(text.contains("reddit_endpoint_rate_limit_tokens{pattern=\"/r/*/new\"} 28\n"));
+        assert!(text.contains(
+            "reddit_circuit_breaker_state{source=\"/r/*/new r/\\\"quoted\\\"\",reddit_circuit_breaker_state=\"open\"} 1\n"
+        ));
+        assert!(text.contains("reddit_request_queue_depth{status=\"queued\"} 3\n"));
+        assert!(text.ends_with("# EOF\n"));
+    }
+}
@@ -0,0 +1,304 @@
+//! Automatic pagination of subreddit listings.
+//!
+//! Reddit listings are cursor based: every page carries an `after` fullname
+//! that has to be passed back to get the next one. [`paginate_subreddit_posts`]
+//! turns that into a `Stream` of posts that follows the cursors on demand and
+//! stops as soon as one of the [`PaginationOptions`] limits is hit, so callers
+//! never fetch more pages than they actually consume.
+
+use crate::api::RedditApiClient;
+use futures::stream::{self, BoxStream, StreamExt};
+use likeminded_core::{CoreError, RedditPost};
+use std::collections::{HashSet, VecDeque};
+use std::sync::Arc;
+use tracing::debug;
+
+/// Largest page Reddit will return for a listing
+pub const MAX_PAGE_SIZE: u32 = 100;
+
+/// Limits that decide when a paginated listing stops
+#[derive(Debug, Clone)]
+pub struct PaginationOptions {
+    /// Stop after yielding this many posts
+    pub max_posts: Option<usize>,
+    /// Stop at the first post created at or before this unix timestamp.
+    /// Only meaningful for the `new` sort, where posts arrive newest first.
+    pub created_after: Option<i64>,
+    /// Stop at the first post whose id is in this set
+    pub seen_post_ids: HashSet<String>,
+    /// Number of posts requested per page (capped at [`MAX_PAGE_SIZE`])
+    pub page_size: u32,
+    /// Hard cap on the number of pages fetched
+    pub max_pages: Option<usize>,
+    /// Fullname to start the listing after, to continue an earlier walk
+    pub start_after: Option<String>,
+}
+
+impl Default for PaginationOptions {
+    fn default() -> Self {
+        Self {
+            max_posts: None,
+            created_after: None,
+            seen_post_ids: HashSet::new(),
+            page_size: MAX_PAGE_SIZE,
+            max_pages: None,
+            start_after: None,
+        }
+    }
+}
+
+impl PaginationOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_max_posts(mut self, max_posts: usize) -> Self {
+        self.max_posts = Some(max_posts);
+        self
+    }
+
+    pub fn with_created_after(mut self, created_utc: i64) -> Self {
+        self.created_after = Some(created_utc);
+        self
+    }
+
+    pub fn with_seen_post_id(mut self, post_id: impl Into<String>) -> Self {
+        self.seen_post_ids.insert(post_id.into());
+        self
+    }
+
+    pub fn with_seen_post_ids<I, S>(mut self, post_ids: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.seen_post_ids
+            .extend(post_ids.into_iter().map(|id| id.into()));
+        self
+    }
+
+    pub fn with_page_size(mut self, page_size: u32) -> Self {
+        self.page_size = page_size.clamp(1, MAX_PAGE_SIZE);
+        self
+    }
+
+    pub fn with_max_pages(mut self, max_pages: usize) -> Self {
+        self.max_pages = Some(max_pages);
+        self
+    }
+
+    pub fn with_start_after(mut self, fullname: impl Into<String>) -> Self {
+        self.start_after = Some(fullname.into());
+        self
+    }
+
+    /// Decide what to do with the next post in the listing
+    fn check(&self, post: &RedditPost) -> PostDecision {
+        if self.seen_post_ids.contains(&post.id) {
+            return PostDecision::Stop;
+        }
+
+        if let Some(cutoff) = self.created_after {
+            if post.created_utc <= cutoff {
+                // Stickied posts sit at the top regardless of age, so an old
+                // sticky must not end the walk
+                return if post.stickied {
+                    PostDecision::Skip
+                } else {
+                    PostDecision::Stop
+                };
+            }
+        }
+
+        PostDecision::Yield
+    }
+
+    /// Page size for the next request, trimmed so we don't over-fetch
+    fn next_page_size(&self, yielded: usize) -> u32 {
+        match self.max_posts {
+            Some(max_posts) => {
+                let remaining = max_posts.saturating_sub(yielded) as u32;
+                remaining.clamp(1, self.page_size)
+            }
+            None => self.page_size,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum PostDecision {
+    Yield,
+    Skip,
+    Stop,
+}
+
+struct PaginationState {
+    api_client: Arc<RedditApiClient>,
+    access_token: String,
+    subreddit: String,
+    sort: Option<String>,
+    time_filter: Option<String>,
+    options: PaginationOptions,
+    buffer: VecDeque<RedditPost>,
+    after: Option<String>,
+    pages_fetched: usize,
+    yielded: usize,
+    exhausted: bool,
+    finished: bool,
+}
+
+impl PaginationState {
+    async fn next_post(mut self) -> Option<(Result<RedditPost, CoreError>, Self)> {
+        loop {
+            if self.finished {
+                return None;
+            }
+            if matches!(self.options.max_posts, Some(max) if self.yielded >= max) {
+                return None;
+            }
+
+            if let Some(post) = self.buffer.pop_front() {
+                match self.options.check(&post) {
+                    PostDecision::Yield => {
+                        self.yielded += 1;
+                        return Some((Ok(post), self));
+                    }
+                    PostDecision::Skip => continue,
+                    PostDecision::Stop => {
+                        debug!(
+                            "Stopping pagination of r/{} at post {}",
+                            self.subreddit, post.id
+                        );
+                        self.finished = true;
+                        return None;
+                    }
+                }
+            }
+
+            if self.exhausted
+                || matches!(self.options.max_pages, Some(max) if self.pages_fetched >= max)
+            {
+                return None;
+            }
+
+            let limit = self.options.next_page_size(self.yielded);
+            let result = self
+                .api_client
+                .get_subreddit_posts_with_time_filter(
+                    &self.access_token,
+                    &self.subreddit,
+                    self.sort.as_deref(),
+                    self.time_filter.as_deref(),
+                    Some(limit),
+                    self.after.as_deref(),
+                )
+                .await;
+
+            match result {
+                Ok(listing) => {
+                    self.pages_fetched += 1;
+                    self.after = listing.data.after;
+                    self.exhausted = self.after.is_none() || listing.data.children.is_empty();
+                    self.buffer.extend(
+                        listing
+                            .data
+                            .children
+                            .into_iter()
+                            .map(|child| RedditPost::from(child.data)),
+                    );
+                }
+                Err(e) => {
+                    self.finished = true;
+                    return Some((Err(e), self));
+                }
+            }
+        }
+    }
+}
+
+/// Stream every post of a subreddit listing, following `after` cursors.
+///
+/// Pages are only requested when the consumer polls past the end of the
+/// previous one. An API error is yielded once and ends the stream.
+pub fn paginate_subreddit_posts(
+    api_client: Arc<RedditApiClient>,
+    access_token: String,
+    subreddit: String,
+    sort: Option<String>,
+    time_filter: Option<String>,
+    options: PaginationOptions,
+) -> BoxStream<'static, Result<RedditPost, CoreError>> {
+    let after = options.start_after.clone();
+    let state = PaginationState {
+        api_client,
+        access_token,
+        subreddit,
+        sort,
+        time_filter,
+        options,
+        buffer: VecDeque::new(),
+        after,
+        pages_fetched: 0,
+        yielded: 0,
+        exhausted: false,
+        finished: false,
+    };
+
+    stream::unfold(state, PaginationState::next_post).boxed()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn post(id: &str, created_utc: i64, stickied: bool) -> RedditPost {
+        RedditPost {
+            id: id.to_string(),
+            title: "title".to_string(),
+            content: None,
+            subreddit: "rust".to_string(),
+            url: String::new(),
+            permalink: String::new(),
+            author: "author".to_string(),
+            created_utc,
+            score: 0,
+            num_comments: 0,
+            upvote_ratio: None,
+            over_18: false,
+            stickied,
+            locked: false,
+            is_self: true,
+            domain: "self.rust".to_string(),
+            thumbnail: None,
+        }
+    }
+
+    #[test]
+    fn test_stop_conditions() {
+        let options = PaginationOptions::new()
+            .with_created_after(1_000)
+            .with_seen_post_id("seen");
+
+        assert_eq!(options.check(&post("a", 2_000, false)), PostDecision::Yield);
+        assert_eq!(
+            options.check(&post("seen", 2_000, false)),
+            PostDecision::Stop
+        );
+        assert_eq!(options.check(&post("b", 1_000, false)), PostDecision::Stop);
+        assert_eq!(options.check(&post("c", 500, true)), PostDecision::Skip);
+    }
+
+    #[test]
+    fn test_page_size_is_trimmed_to_remaining_posts() {
+        let options = PaginationOptions::new()
+            .with_page_size(40)
+            .with_max_posts(50);
+
+        assert_eq!(options.next_page_size(0), 40);
+        assert_eq!(options.next_page_size(40), 10);
+
+        let unbounded = PaginationOptions::new().with_page_size(500);
+        assert_eq!(unbounded.page_size, MAX_PAGE_SIZE);
+        assert_eq!(unbounded.next_page_size(1_000), MAX_PAGE_SIZE);
+    }
+}
@@ -1,3 +1,4 @@
+use reqwest::header::HeaderMap;
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use std::time::{Duration, Instant, SystemTime};
@@ -27,6 +28,15 @@ pub struct TokenBucket {
     capacity: f64,
     refill_rate: f64, // tokens per second
     last_refill: Arc<Mutex<Instant>>,
+    server_pace: Arc<Mutex<Option<ServerPace>>>,
+}
+
+/// Refill rate that spreads the server's remaining budget over the rest of
+/// its window, used instead of the configured rate until `until`
+#[derive(Debug, Clone, Copy)]
+struct ServerPace {
+    rate: f64,
+    until: Instant,
 }
 
 impl TokenBucket {
@@ -39,59 +49,173 @@ impl TokenBucket {
             capacity,
             refill_rate,
             last_refill: Arc::new(Mutex::new(Instant::now())),
+            server_pace: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    /// Add the tokens earned since the last refill, at the server pace while
+    /// one applies and at the configured rate after that
+    fn refill(
+        &self,
+        tokens: &mut f64,
+        last_refill: &mut Instant,
+        server_pace: &mut Option<ServerPace>,
+        now: Instant,
+    ) {
+        let mut from = *last_refill;
+        let mut tokens_to_add = 0.0;
+
+        if let Some(pace) = *server_pace {
+            if from < pace.until {
+                let paced_until = now.min(pace.until);
+                tokens_to_add += paced_until.duration_since(from).as_secs_f64() * pace.rate;
+                from = paced_until;
+            }
+            if now >= pace.until {
+                *server_pace = None;
+            }
         }
+        tokens_to_add += now.saturating_duration_since(from).as_secs_f64() * self.refill_rate;
+
+        *tokens = (*tokens + tokens_to_add).min(self.capacity);
+        *last_refill = now;
+    }
+
+    /// Time until `missing` more tokens have been refilled
+    fn refill_time(&self, missing: f64, server_pace: Option<ServerPace>, now: Instant) -> Duration {
+        if let Some(pace) = server_pace {
+            let paced_for = pace.until.saturating_duration_since(now);
+            let paced_tokens = paced_for.as_secs_f64() * pace.rate;
+            if paced_tokens >= missing {
+                return Duration::from_secs_f64(missing / pace.rate);
+            }
+            return paced_for
+                + Duration::from_secs_f64((missing - paced_tokens) / self.refill_rate);
+        }
+        Duration::from_secs_f64(missing / self.refill_rate)
     }
 
     pub async fn acquire(&self, tokens_needed: f64) -> Result<(), Duration> {
         let now = Instant::now();
+        let mut tokens = self.tokens.lock().await;
+        let mut last_refill = self.last_refill.lock().await;
+        let mut server_pace = self.server_pace.lock().await;
 
         // Refill tokens based on elapsed time
-        {
-            let mut tokens = self.tokens.lock().await;
-            let mut last_refill = self.last_refill.lock().await;
-
-            let elapsed = now.duration_since(*last_refill);
-            let tokens_to_add = elapsed.as_secs_f64() * self.refill_rate;
-
-            *tokens = (*tokens + tokens_to_add).min(self.capacity);
-            *last_refill = now;
-        }
+        self.refill(&mut tokens, &mut last_refill, &mut server_pace, now);
 
         // Check if we have enough tokens
-        let mut tokens = self.tokens.lock().await;
         if *tokens >= tokens_needed {
             *tokens -= tokens_needed;
             Ok(())
         } else {
             // Calculate wait time for next token
-            let tokens_needed_after_current = tokens_needed - *tokens;
-            let wait_time = Duration::from_secs_f64(tokens_needed_after_current / self.refill_rate);
-            Err(wait_time)
+            Err(self.refill_time(tokens_needed - *tokens, *server_pace, now))
         }
     }
 
+    /// Drop tokens so that no more than `max_tokens` remain available
+    pub async fn clamp_tokens(&self, max_tokens: f64) {
+        let now = Instant::now();
+        let mut tokens = self.tokens.lock().await;
+        let mut last_refill = self.last_refill.lock().await;
+        let mut server_pace = self.server_pace.lock().await;
+
+        self.refill(&mut tokens, &mut last_refill, &mut server_pace, now);
+        *tokens = tokens.min(max_tokens.max(0.0));
+    }
+
+    /// Hold no more than `remaining` tokens, and refill only what is left of
+    /// `remaining` over the next `reset_in`, so the bucket never hands out
+    /// more than the server allows before its window resets
+    pub async fn apply_server_budget(&self, remaining: f64, reset_in: Duration) {
+        let now = Instant::now();
+        let mut tokens = self.tokens.lock().await;
+        let mut last_refill = self.last_refill.lock().await;
+        let mut server_pace = self.server_pace.lock().await;
+
+        self.refill(&mut tokens, &mut last_refill, &mut server_pace, now);
+        let remaining = remaining.max(0.0);
+        *tokens = tokens.min(remaining);
+        *server_pace = if reset_in.is_zero() {
+            None
+        } else {
+            Some(ServerPace {
+                rate: (remaining - *tokens) / reset_in.as_secs_f64(),
+                until: now + reset_in,
+            })
+        };
+    }
+
     pub async fn get_available_tokens(&self) -> f64 {
         // Update tokens first
         let now = Instant::now();
         let mut tokens = self.tokens.lock().await;
         let mut last_refill = self.last_refill.lock().await;
+        let mut server_pace = self.server_pace.lock().await;
 
-        let elapsed = now.duration_since(*last_refill);
-        let tokens_to_add = elapsed.as_secs_f64() * self.refill_rate;
-
-        *tokens = (*tokens + tokens_to_add).min(self.capacity);
-        *last_refill = now;
+        self.refill(&mut tokens, &mut last_refill, &mut server_pace, now);
 
         *tokens
     }
 }
 
+/// Rate limit budget reported by Reddit in the `X-Ratelimit-*` response headers
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ServerRateLimit {
+    /// Requests used in the current server window (`X-Ratelimit-Used`)
+    pub used: u32,
+    /// Requests left in the current server window (`X-Ratelimit-Remaining`)
+    pub remaining: f64,
+    /// Time until the server window resets (`X-Ratelimit-Reset`), as of `observed_at`
+    pub reset_in: Duration,
+    pub observed_at: SystemTime,
+}
+
+impl ServerRateLimit {
+    /// Parse the rate limit headers, returning `None` if Reddit did not send them
+    pub fn from_headers(headers: &HeaderMap) -> Option<Self> {
+        let header = |name: &str| headers.get(name).and_then(|value| value.to_str().ok());
+
+        let remaining = header("x-ratelimit-remaining")?.trim().parse::<f64>().ok()?;
+        let reset_seconds = header("x-ratelimit-reset")?.trim().parse::<f64>().ok()?;
+        let used = header("x-ratelimit-used")
+            .and_then(|value| value.trim().parse::<f64>().ok())
+            .unwrap_or(0.0);
+
+        Some(Self {
+            used: used.max(0.0) as u32,
+            remaining: remaining.max(0.0),
+            reset_in: Duration::from_secs_f64(reset_seconds.max(0.0)),
+            observed_at: SystemTime::now(),
+        })
+    }
+
+    pub fn reset_at(&self) -> SystemTime {
+        self.observed_at + self.reset_in
+    }
+
+    /// Time left until the server window resets, or `None` if it already has
+    pub fn time_until_reset(&self) -> Option<Duration> {
+        self.reset_at()
+            .duration_since(SystemTime::now())
+            .ok()
+            .filter(|remaining| !remaining.is_zero())
+    }
+
+    /// True if the server budget is used up and its window has not reset yet
+    pub fn is_exhausted(&self) -> bool {
+        self.remaining < 1.0 && self.time_until_reset().is_some()
+    }
+}
+
 #[derive(Debug)]
 pub struct RateLimiter {
     token_bucket: TokenBucket,
     semaphore: Arc<Semaphore>,
     config: RateLimitConfig,
     window_tracker: Arc<Mutex<WindowTracker>>,
+    server_limit: Arc<Mutex<Option<ServerRateLimit>>>,
 }
 
 impl RateLimiter {
@@ -105,9 +229,43 @@ impl RateLimiter {
             semaphore,
             config,
             window_tracker,
+            server_limit: Arc::new(Mutex::new(None)),
         }
     }
 
+    /// Feed the budget reported by Reddit back into the limiter.
+    ///
+    /// The local bucket is never allowed to hold more tokens than the server
+    /// says remain, and refills no faster than the rest of that budget allows
+    /// until the server window resets. Once the server budget is exhausted new
+    /// permits wait for the reset.
+    pub async fn update_from_server(&self, server_limit: ServerRateLimit) {
+        tracing::debug!(
+            "Server rate limit: {} used, {} remaining, reset in {:?}",
+            server_limit.used,
+            server_limit.remaining,
+            server_limit.reset_in
+        );
+
+        self.token_bucket
+            .apply_server_budget(server_limit.remaining, server_limit.reset_in)
+            .await;
+        *self.server_limit.lock().await = Some(server_limit);
+    }
+
+    pub async fn get_server_rate_limit(&self) -> Option<ServerRateLimit> {
+        self.server_limit.lock().await.clone()
+    }
+
+    /// How long to wait before the server budget allows another request
+    async fn server_wait_time(&self) -> Option<Duration> {
+        let server_limit = self.server_limit.lock().await;
+        server_limit
+            .as_ref()
+            .filter(|limit| limit.is_exhausted())
+            .and_then(|limit| limit.time_until_reset())
+    }
+
     pub async fn acquire_permit(&self) -> RateLimitPermit {
         let start_time = Instant::now();
         let _permit = self
@@ -117,6 +275,15 @@ impl RateLimiter {
             .await
             .expect("Semaphore should not be closed");
 
+        // Respect the server-reported budget before touching the local bucket
+        while let Some(wait_time) = self.server_wait_time().await {
+            tracing::warn!(
+                "Reddit rate limit budget exhausted, waiting {:?} for reset",
+                wait_time
+            );
+            sleep(wait_time).await;
+        }
+
         // Try to acquire token, wait if necessary
         loop {
             match self.token_bucket.acquire(1.0).await {
@@ -134,6 +301,15 @@ impl RateLimiter {
             window_tracker.record_request();
         }
 
+        // Count the request against the server budget until the next response
+        // tells us the real figure
+        if let Some(server_limit) = self.server_limit.lock().await.as_mut() {
+            if server_limit.time_until_reset().is_some() {
+                server_limit.remaining = (server_limit.remaining - 1.0).max(0.0);
+                server_limit.used += 1;
+            }
+        }
+
         let queue_wait_time = start_time.elapsed();
         RateLimitPermit {
             _permit,
@@ -147,14 +323,21 @@ impl RateLimiter {
         let window_tracker = self.window_tracker.lock().await;
         let window_stats = window_tracker.get_current_window_stats();
 
+        let server_limit = self.server_limit.lock().await.clone();
+
         let is_near_limit = available_tokens < (self.config.burst_allowance as f64 * 0.2);
-        let estimated_wait_time = if available_tokens < 1.0 {
+        let local_wait_time = if available_tokens < 1.0 {
             Some(Duration::from_secs_f64(
                 1.0 / (self.config.max_requests as f64 / 60.0),
             ))
         } else {
             None
         };
+        let server_wait_time = server_limit
+            .as_ref()
+            .filter(|limit| limit.is_exhausted())
+            .and_then(|limit| limit.time_until_reset());
+        let estimated_wait_time = local_wait_time.max(server_wait_time);
 
         RateLimitStatus {
             available_tokens: available_tokens as u32,
@@ -167,6 +350,7 @@ impl RateLimiter {
             next_token_available_at: estimated_wait_time.map(|d| SystemTime::now() + d),
             is_near_limit,
             estimated_wait_time,
+            server_limit,
         }
     }
 }
@@ -189,6 +373,8 @@ pub struct RateLimitStatus {
     pub next_token_available_at: Option<SystemTime>,
     pub is_near_limit: bool,
     pub estimated_wait_time: Option<Duration>,
+    /// Budget last reported by Reddit, if any response carried the headers
+    pub server_limit: Option<ServerRateLimit>,
 }
 
 #[derive(Debug)]
@@ -274,6 +460,16 @@ impl RateLimitStatus {
         (self.current_window_requests as f64 / self.requests_per_minute as f64) * 100.0
     }
 
+    /// Requests left according to Reddit, falling back to the local estimate
+    pub fn effective_remaining(&self) -> u32 {
+        match &self.server_limit {
+            Some(server_limit) if server_limit.time_until_reset().is_some() => {
+                (server_limit.remaining as u32).min(self.requests_remaining_in_window())
+            }
+            _ => self.requests_remaining_in_window(),
+        }
+    }
+
     pub fn time_until_window_reset(&self) -> Duration {
         let elapsed_since_window_start = SystemTime::now()
             .duration_since(self.window_start_time)
@@ -405,6 +601,96 @@ mod tests {
         assert_eq!(stats.rate_limited_requests, 1);
     }
 
+    fn rate_limit_headers(used: &str, remaining: &str, reset: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert("x-ratelimit-used", used.parse().unwrap());
+        headers.insert("x-ratelimit-remaining", remaining.parse().unwrap());
+        headers.insert("x-ratelimit-reset", reset.parse().unwrap());
+        headers
+    }
+
+    #[test]
+    fn test_server_rate_limit_from_headers() {
+        let headers = rate_limit_headers("42", "558.0", "317");
+        let server_limit = ServerRateLimit::from_headers(&headers).unwrap();
+
+        assert_eq!(server_limit.used, 42);
+        assert_eq!(server_limit.remaining, 558.0);
+        assert_eq!(server_limit.reset_in, Duration::from_secs(317));
+        assert!(!server_limit.is_exhausted());
+
+        // Missing headers mean Reddit gave us nothing to go on
+        assert!(ServerRateLimit::from_headers(&HeaderMap::new()).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_server_limit_clamps_local_tokens() {
+        let limiter = RateLimiter::new(RateLimitConfig::reddit_oauth());
+
+        let headers = rate_limit_headers("597", "3", "100");
+        limiter
+            .update_from_server(ServerRateLimit::from_headers(&headers).unwrap())
+            .await;
+
+        let status = limiter.get_rate_limit_status().await;
+        assert_eq!(status.available_tokens, 3);
+        assert_eq!(status.server_limit.as_ref().unwrap().remaining, 3.0);
+        assert_eq!(status.effective_remaining(), 3);
+
+        // Local permits count against the server budget until the next update
+        let _permit = limiter.acquire_permit().await;
+        let server_limit = limiter.get_server_rate_limit().await.unwrap();
+        assert_eq!(server_limit.remaining, 2.0);
+        assert_eq!(server_limit.used, 598);
+    }
+
+    #[tokio::test]
+    async fn test_server_budget_paces_refill() {
+        let bucket = TokenBucket::new(&RateLimitConfig::reddit_oauth());
+        // Tokens earned before the update count towards the clamp, not after it
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        bucket
+            .apply_server_budget(2.0, Duration::from_secs(100))
+            .await;
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        // The bucket was already at the server figure, so nothing refills
+        assert!(bucket.get_available_tokens().await <= 2.0);
+        assert!(bucket.acquire(1.0).await.is_ok());
+        assert!(bucket.acquire(1.0).await.is_ok());
+        let wait_time = bucket.acquire(1.0).await.unwrap_err();
+        assert!(wait_time >= Duration::from_secs(99));
+
+        // With budget to spare the bucket refills it over the server window
+        let bucket = TokenBucket::new(&RateLimitConfig::reddit_oauth());
+        bucket.clamp_tokens(0.0).await;
+        bucket
+            .apply_server_budget(5.0, Duration::from_millis(200))
+            .await;
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        let tokens = bucket.get_available_tokens().await;
+        assert!((2.0..3.5).contains(&tokens), "{} tokens", tokens);
+    }
+
+    #[tokio::test]
+    async fn test_exhausted_server_budget_waits_for_reset() {
+        let limiter = RateLimiter::new(RateLimitConfig::reddit_oauth());
+        limiter
+            .update_from_server(ServerRateLimit {
+                used: 600,
+                remaining: 0.0,
+                reset_in: Duration::from_millis(300),
+                observed_at: SystemTime::now(),
+            })
+            .await;
+
+        let status = limiter.get_rate_limit_status().await;
+        assert!(status.estimated_wait_time.is_some());
+
+        let permit = limiter.acquire_permit().await;
+        assert!(permit.queue_wait_time >= Duration::from_millis(250));
+    }
+
     #[tokio::test]
     async fn test_permit_wait_time_tracking() {
         let config = RateLimitConfig::reddit_oauth();
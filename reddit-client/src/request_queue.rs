@@ -1,8 +1,15 @@
-use crate::api_tracker::ApiTracker;
-use likeminded_core::CoreError;
+use crate::api::{
+    RedditApiClient, RedditListing, RedditListingChild, RedditPostData, RedditSubredditData,
+    RedditUserData,
+};
+use crate::retry::{get_retry_strategy, RetryStrategy};
+use likeminded_core::{CoreError, RedditApiError};
+use reqwest::Method;
 use serde::{Deserialize, Serialize};
-use sqlx::SqlitePool;
+use sqlx::sqlite::SqliteRow;
+use sqlx::{Row, SqlitePool};
 use std::collections::{BinaryHeap, HashMap};
+use std::marker::PhantomData;
 use std::sync::Arc;
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use tokio::sync::{mpsc, RwLock};
@@ -10,6 +17,9 @@ use tokio::time::{sleep, timeout};
 use tracing::{debug, error, info, warn};
 use uuid::Uuid;
 
+/// A request waiting in the queue. Everything but `access_token` is written to
+/// the `request_queue` table so the request can be restored after a restart;
+/// `account_id` says whose token to restore it with.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct QueuedRequest {
     pub request_id: String,
@@ -17,6 +27,7 @@ pub struct QueuedRequest {
     pub method: String,
     pub priority: i32,
     pub operation_type: Option<String>,
+    pub account_id: i64,
     pub access_token: String,
     pub query_params: Option<Vec<(String, String)>>,
     pub payload: Option<String>,
@@ -39,6 +50,149 @@ pub struct RequestResult {
     pub response_data: Option<String>,
 }
 
+/// A Reddit call the queue knows how to decode. Enqueue one with
+/// [`RequestQueue::enqueue_operation`] to get its response typed instead of
+/// as a raw body.
+#[derive(Debug, Clone, PartialEq)]
+pub enum QueuedOperation {
+    /// A page of posts from `/r/{subreddit}/{sort}`
+    SubredditListing {
+        subreddit: String,
+        sort: String,
+        limit: Option<u32>,
+        after: Option<String>,
+    },
+    /// `/r/{subreddit}/about`
+    SubredditAbout { subreddit: String },
+    /// The authenticated user, from `/api/v1/me`
+    Me,
+}
+
+impl QueuedOperation {
+    pub fn endpoint(&self) -> String {
+        match self {
+            Self::SubredditListing {
+                subreddit, sort, ..
+            } => format!("/r/{}/{}", subreddit, sort),
+            Self::SubredditAbout { subreddit } => format!("/r/{}/about", subreddit),
+            Self::Me => "/api/v1/me".to_string(),
+        }
+    }
+
+    pub fn query_params(&self) -> Option<Vec<(String, String)>> {
+        match self {
+            Self::SubredditListing { limit, after, .. } => {
+                let mut params = vec![(
+                    "limit".to_string(),
+                    limit.unwrap_or(25).min(100).to_string(),
+                )];
+                if let Some(after) = after {
+                    params.push(("after".to_string(), after.clone()));
+                }
+                Some(params)
+            }
+            Self::SubredditAbout { .. } | Self::Me => None,
+        }
+    }
+
+    /// Name recorded as the request's `operation_type`, matching what the
+    /// equivalent [`RedditApiClient`] method records
+    pub fn operation_type(&self) -> &'static str {
+        match self {
+            Self::SubredditListing { .. } => "get_subreddit_posts",
+            Self::SubredditAbout { .. } => "get_subreddit_info",
+            Self::Me => "get_user_info",
+        }
+    }
+
+    pub fn subreddit(&self) -> Option<&str> {
+        match self {
+            Self::SubredditListing { subreddit, .. } | Self::SubredditAbout { subreddit } => {
+                Some(subreddit)
+            }
+            Self::Me => None,
+        }
+    }
+}
+
+/// Response types a [`QueuedOperation`] can resolve to
+pub trait QueuedResponseData: Sized + Send + 'static {
+    fn decode(body: &str) -> Result<Self, CoreError>;
+}
+
+impl QueuedResponseData for RedditListing<RedditPostData> {
+    fn decode(body: &str) -> Result<Self, CoreError> {
+        decode_json(body, "listing")
+    }
+}
+
+impl QueuedResponseData for RedditSubredditData {
+    fn decode(body: &str) -> Result<Self, CoreError> {
+        decode_json::<RedditListingChild<Self>>(body, "subreddit info").map(|child| child.data)
+    }
+}
+
+impl QueuedResponseData for RedditUserData {
+    fn decode(body: &str) -> Result<Self, CoreError> {
+        decode_json(body, "user data")
+    }
+}
+
+fn decode_json<T: serde::de::DeserializeOwned>(body: &str, what: &str) -> Result<T, CoreError> {
+    serde_json::from_str(body).map_err(|e| {
+        error!("Failed to parse queued {}: {}", what, e);
+        CoreError::RedditApi(RedditApiError::InvalidResponse {
+            details: format!("Failed to parse {}", what),
+        })
+    })
+}
+
+/// The pending result of a typed request. Identical requests are coalesced
+/// as with [`RequestQueue::enqueue_request`], so `request_id` may belong to a
+/// request another caller queued first.
+#[derive(Debug)]
+pub struct QueuedResponse<T> {
+    request_id: String,
+    receiver: mpsc::UnboundedReceiver<RequestResult>,
+    _response: PhantomData<fn() -> T>,
+}
+
+impl<T: QueuedResponseData> QueuedResponse<T> {
+    pub fn request_id(&self) -> &str {
+        &self.request_id
+    }
+
+    /// Wait for the request to finish and decode its body
+    pub async fn wait(mut self) -> Result<T, CoreError> {
+        let result = self
+            .receiver
+            .recv()
+            .await
+            .ok_or_else(|| CoreError::Internal {
+                message: format!("Request {} was dropped by the queue", self.request_id),
+            })?;
+        result_into::<T>(result)
+    }
+}
+
+fn result_into<T: QueuedResponseData>(result: RequestResult) -> Result<T, CoreError> {
+    if !result.success {
+        return Err(CoreError::RequestFailed {
+            message: result
+                .error_message
+                .unwrap_or_else(|| format!("Request {} failed", result.request_id)),
+            status_code: result.status_code,
+        });
+    }
+
+    match result.response_data {
+        Some(body) => T::decode(&body),
+        None => Err(CoreError::RedditApi(RedditApiError::InvalidResponse {
+            details: format!("Request {} finished without a body", result.request_id),
+        })),
+    }
+}
+
 #[derive(Debug, Clone, Eq, PartialEq)]
 struct PriorityRequest {
     request_id: String,
@@ -49,10 +203,9 @@ struct PriorityRequest {
 impl Ord for PriorityRequest {
     fn cmp(&self, other: &Self) -> std::cmp::Ordering {
         // Higher priority first, then earlier scheduled time
-        other
-            .priority
-            .cmp(&self.priority)
-            .then_with(|| self.scheduled_for.cmp(&other.scheduled_for))
+        self.priority
+            .cmp(&other.priority)
+            .then_with(|| other.scheduled_for.cmp(&self.scheduled_for))
     }
 }
 
@@ -62,35 +215,310 @@ impl PartialOrd for PriorityRequest {
     }
 }
 
+/// Pop the highest priority request that is due, leaving requests scheduled
+/// for later in the heap
+fn take_next_due(
+    queue: &mut BinaryHeap<PriorityRequest>,
+    now: SystemTime,
+) -> Option<PriorityRequest> {
+    let mut not_due = Vec::new();
+    let mut next = None;
+
+    while let Some(request) = queue.pop() {
+        if request.scheduled_for <= now {
+            next = Some(request);
+            break;
+        }
+        not_due.push(request);
+    }
+
+    queue.extend(not_due);
+    next
+}
+
+/// Identity of a logical request for deduplication: method, endpoint, query
+/// parameters in any order and account, so the same call made for two
+/// accounts is not shared.
+fn request_key(request: &QueuedRequest) -> String {
+    let mut params: Vec<String> = request
+        .query_params
+        .iter()
+        .flatten()
+        .map(|(key, value)| format!("{}={}", key, value))
+        .collect();
+    params.sort();
+
+    format!(
+        "{} {}?{} @{}",
+        request.method.to_uppercase(),
+        request.endpoint,
+        params.join("&"),
+        request.account_id
+    )
+}
+
+/// Give the entry for `request_id` a new priority, keeping the heap ordered
+fn reprioritize(queue: &mut BinaryHeap<PriorityRequest>, request_id: &str, priority: i32) {
+    let entries: Vec<PriorityRequest> = queue
+        .drain()
+        .map(|mut entry| {
+            if entry.request_id == request_id {
+                entry.priority = priority;
+            }
+            entry
+        })
+        .collect();
+    queue.extend(entries);
+}
+
+/// How long finished requests stay in the database by default, see
+/// [`RequestQueue::with_result_retention`]
+pub const DEFAULT_RESULT_RETENTION: Duration = Duration::from_secs(3600);
+
+/// How often [`RequestQueue::start_processing`] purges expired results
+const PURGE_INTERVAL: Duration = Duration::from_secs(300);
+
+/// What [`RequestQueue::restore_pending`] found in the database
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct RestoreReport {
+    /// Requests put back in the in-memory queue
+    pub restored: usize,
+    /// Rows left `executing` by a previous run and queued again
+    pub reset_executing: usize,
+    /// Restored rows that duplicated another one and were merged into it
+    pub merged: usize,
+    /// Rows left in the database because their account had no access token
+    pub skipped: usize,
+}
+
 #[derive(Debug)]
 pub struct RequestQueue {
     pool: Arc<SqlitePool>,
-    api_tracker: Option<Arc<ApiTracker>>,
+    api_client: Arc<RedditApiClient>,
     queue: Arc<RwLock<BinaryHeap<PriorityRequest>>>,
     requests: Arc<RwLock<HashMap<String, QueuedRequest>>>,
-    result_senders: Arc<RwLock<HashMap<String, mpsc::UnboundedSender<RequestResult>>>>,
+    result_senders: Arc<RwLock<HashMap<String, Vec<mpsc::UnboundedSender<RequestResult>>>>>,
+    /// Request ID of every queued or executing request, by [`request_key`]
+    in_flight: Arc<RwLock<HashMap<String, String>>>,
+    /// Restored duplicates and the request they were merged into
+    merged_requests: Arc<RwLock<HashMap<String, String>>>,
     max_queue_size: usize,
     processing_enabled: bool,
+    result_retention: Duration,
 }
 
 impl RequestQueue {
-    pub fn new(pool: Arc<SqlitePool>, max_queue_size: usize) -> Self {
+    /// Queued requests are sent through `api_client`, so they share its rate
+    /// limits, retries, metrics and API tracking with direct calls
+    pub fn new(
+        pool: Arc<SqlitePool>,
+        api_client: Arc<RedditApiClient>,
+        max_queue_size: usize,
+    ) -> Self {
         Self {
             pool,
-            api_tracker: None,
+            api_client,
             queue: Arc::new(RwLock::new(BinaryHeap::new())),
             requests: Arc::new(RwLock::new(HashMap::new())),
             result_senders: Arc::new(RwLock::new(HashMap::new())),
+            in_flight: Arc::new(RwLock::new(HashMap::new())),
+            merged_requests: Arc::new(RwLock::new(HashMap::new())),
             max_queue_size,
             processing_enabled: true,
+            result_retention: DEFAULT_RESULT_RETENTION,
         }
     }
 
-    pub fn with_api_tracker(mut self, api_tracker: Arc<ApiTracker>) -> Self {
-        self.api_tracker = Some(api_tracker);
+    /// Keep finished requests, response bodies included, for `retention`
+    /// before [`Self::purge_finished_requests`] deletes them. This is how long
+    /// [`Self::subscribe`] can still hand out a finished request's result.
+    pub fn with_result_retention(mut self, retention: Duration) -> Self {
+        self.result_retention = retention;
         self
     }
 
+    /// Reload unfinished requests saved by a previous run. Rows still marked
+    /// `executing` were interrupted mid-flight and are queued again. Access
+    /// tokens are never written to the database, so each request is restored
+    /// with `access_token_for(account_id)`; rows of accounts without a token
+    /// stay queued in the database for a later restore. Call this once at
+    /// startup, before [`Self::start_processing`].
+    pub async fn restore_pending<F>(&self, access_token_for: F) -> Result<RestoreReport, CoreError>
+    where
+        F: Fn(i64) -> Option<String>,
+    {
+        let reset = sqlx::query(
+            "UPDATE request_queue SET status = 'queued', started_at = NULL WHERE status = 'executing'",
+        )
+        .execute(&*self.pool)
+        .await
+        .map_err(sql_error)?;
+
+        let rows = sqlx::query(
+            r#"
+            SELECT request_id, endpoint, method, priority, operation_type, account_id, payload,
+                   headers, query_params, queued_at, scheduled_for, retry_count, max_retries,
+                   subreddit, timeout_seconds
+            FROM request_queue
+            WHERE status = 'queued'
+            ORDER BY queued_at
+            "#,
+        )
+        .fetch_all(&*self.pool)
+        .await
+        .map_err(sql_error)?;
+
+        let mut report = RestoreReport {
+            reset_executing: reset.rows_affected() as usize,
+            ..Default::default()
+        };
+        let mut merged = Vec::new();
+
+        {
+            let mut in_flight = self.in_flight.write().await;
+            let mut queue = self.queue.write().await;
+            let mut requests = self.requests.write().await;
+            let mut merged_requests = self.merged_requests.write().await;
+            let mut restored = Vec::new();
+
+            for row in rows {
+                let Some(access_token) = access_token_for(row.get("account_id")) else {
+                    report.skipped += 1;
+                    continue;
+                };
+                let request = queued_request_from_row(&row, access_token);
+                if requests.contains_key(&request.request_id) {
+                    continue;
+                }
+
+                // A duplicate of a request that is already queued, e.g. one
+                // enqueued again before the restore, joins the queued one
+                let key = request_key(&request);
+                if let Some(kept_id) = in_flight.get(&key) {
+                    if let Some(kept) = requests.get_mut(kept_id) {
+                        kept.priority = kept.priority.max(request.priority);
+                    }
+                    merged_requests.insert(request.request_id.clone(), kept_id.clone());
+                    merged.push((request.request_id, kept_id.clone()));
+                    continue;
+                }
+
+                in_flight.insert(key, request.request_id.clone());
+                restored.push(request.request_id.clone());
+                requests.insert(request.request_id.clone(), request);
+            }
+
+            // Heap entries go in last so they carry any merged priority
+            for request_id in &restored {
+                let request = &requests[request_id];
+                queue.push(PriorityRequest {
+                    request_id: request_id.clone(),
+                    priority: request.priority,
+                    scheduled_for: request.scheduled_for.unwrap_or(request.queued_at),
+                });
+            }
+            report.restored = restored.len();
+            report.merged = merged.len();
+        }
+
+        for (request_id, kept_id) in merged {
+            sqlx::query(
+                "UPDATE request_queue SET status = 'cancelled', error_message = ? WHERE request_id = ?",
+            )
+            .bind(format!("Merged into request {}", kept_id))
+            .bind(&request_id)
+            .execute(&*self.pool)
+            .await
+            .map_err(sql_error)?;
+
+            let priority = self
+                .requests
+                .read()
+                .await
+                .get(&kept_id)
+                .map(|kept| kept.priority);
+            if let Some(priority) = priority {
+                self.update_request_priority(&kept_id, priority).await?;
+            }
+        }
+
+        info!(
+            "Restored {} queued requests ({} interrupted while executing, {} merged, {} skipped without a token)",
+            report.restored, report.reset_executing, report.merged, report.skipped
+        );
+        Ok(report)
+    }
+
+    /// Get a new result channel for a request, e.g. after a restart dropped
+    /// the receiver returned by [`Self::enqueue_request`]. Every subscriber
+    /// gets the same result. A request that has already finished gets its
+    /// stored result straight away for as long as the queue's result
+    /// retention (an hour by default) keeps it; after that, and for unknown
+    /// IDs, this gives `None`.
+    pub async fn subscribe(
+        &self,
+        request_id: &str,
+    ) -> Result<Option<mpsc::UnboundedReceiver<RequestResult>>, CoreError> {
+        let request_id = match self.merged_requests.read().await.get(request_id) {
+            Some(kept_id) => kept_id.clone(),
+            None => request_id.to_string(),
+        };
+        let request_id = request_id.as_str();
+        let (tx, rx) = mpsc::unbounded_channel();
+
+        {
+            // Holding the requests lock keeps the request from finishing
+            // before the sender is in place
+            let requests = self.requests.read().await;
+            if requests.contains_key(request_id) {
+                let mut senders = self.result_senders.write().await;
+                senders.entry(request_id.to_string()).or_default().push(tx);
+                return Ok(Some(rx));
+            }
+        }
+
+        let row = sqlx::query(
+            r#"
+            SELECT status, response_data, error_message, started_at, completed_at, failed_at
+            FROM request_queue
+            WHERE request_id = ?
+            "#,
+        )
+        .bind(request_id)
+        .fetch_optional(&*self.pool)
+        .await
+        .map_err(sql_error)?;
+
+        let Some(row) = row else {
+            return Ok(None);
+        };
+        let status: String = row.get("status");
+        if status != "completed" && status != "failed" {
+            return Ok(None);
+        }
+
+        let started_at: Option<i64> = row.get("started_at");
+        let finished_at: Option<i64> = row
+            .get::<Option<i64>, _>("completed_at")
+            .or_else(|| row.get("failed_at"));
+        let response_time = match (started_at, finished_at) {
+            (Some(started), Some(finished)) => {
+                Duration::from_secs(finished.saturating_sub(started).max(0) as u64)
+            }
+            _ => Duration::from_secs(0),
+        };
+
+        let _ = tx.send(RequestResult {
+            request_id: request_id.to_string(),
+            success: status == "completed",
+            status_code: None,
+            response_time,
+            error_message: row.get("error_message"),
+            response_data: row.get("response_data"),
+        });
+        Ok(Some(rx))
+    }
+
     pub async fn start_processing(&self) {
         if !self.processing_enabled {
             warn!("Request queue processing is disabled");
@@ -99,21 +527,36 @@ impl RequestQueue {
 
         info!("Starting request queue processor");
 
+        let mut last_purge = SystemTime::now();
         loop {
             if let Err(e) = self.process_next_request().await {
                 error!("Error processing request: {}", e);
                 sleep(Duration::from_millis(100)).await;
             }
 
+            if last_purge.elapsed().unwrap_or_default() >= PURGE_INTERVAL {
+                last_purge = SystemTime::now();
+                if let Err(e) = self.purge_finished_requests().await {
+                    warn!("Failed to purge finished requests: {}", e);
+                }
+            }
+
             // Small delay to prevent busy waiting
             sleep(Duration::from_millis(10)).await;
         }
     }
 
+    /// Queue a request and get a channel for its result. If an identical
+    /// request (same method, endpoint, query and account) is already queued
+    /// or executing, the caller joins it instead: the existing request ID is
+    /// returned, both callers get the same result and the higher priority
+    /// wins.
+    #[allow(clippy::too_many_arguments)]
     pub async fn enqueue_request(
         &self,
         endpoint: String,
         method: String,
+        account_id: i64,
         access_token: String,
         priority: i32,
         operation_type: Option<String>,
@@ -124,25 +567,13 @@ impl RequestQueue {
         let request_id = Uuid::new_v4().to_string();
         let now = SystemTime::now();
 
-        // Check queue size limit
-        {
-            let queue = self.queue.read().await;
-            if queue.len() >= self.max_queue_size {
-                return Err(CoreError::RateLimited {
-                    message: "Request queue is full".to_string(),
-                    retry_after: Some(Duration::from_secs(60)),
-                });
-            }
-        }
-
-        let (tx, rx) = mpsc::unbounded_channel();
-
         let queued_request = QueuedRequest {
             request_id: request_id.clone(),
             endpoint,
             method,
             priority,
             operation_type,
+            account_id,
             access_token,
             query_params,
             payload: None,
@@ -155,6 +586,32 @@ impl RequestQueue {
             subreddit,
         };
 
+        // Held until the request is registered, so an identical request
+        // enqueued meanwhile joins this one instead of racing it
+        let key = request_key(&queued_request);
+        let mut in_flight = self.in_flight.write().await;
+        if let Some(existing_id) = in_flight.get(&key).cloned() {
+            let rx = self.join_request(&existing_id, priority).await?;
+            debug!(
+                "Coalesced request for {} into queued request {}",
+                queued_request.endpoint, existing_id
+            );
+            return Ok((existing_id, rx));
+        }
+
+        // Check queue size limit
+        {
+            let queue = self.queue.read().await;
+            if queue.len() >= self.max_queue_size {
+                return Err(CoreError::RateLimited {
+                    message: "Request queue is full".to_string(),
+                    retry_after: Some(Duration::from_secs(60)),
+                });
+            }
+        }
+
+        let (tx, rx) = mpsc::unbounded_channel();
+
         // Save request to database
         self.save_queued_request(&queued_request).await?;
 
@@ -171,8 +628,9 @@ impl RequestQueue {
             });
 
             requests.insert(request_id.clone(), queued_request.clone());
-            senders.insert(request_id.clone(), tx);
+            senders.insert(request_id.clone(), vec![tx]);
         }
+        in_flight.insert(key, request_id.clone());
 
         debug!(
             "Enqueued request {} with priority {} for endpoint {}",
@@ -182,24 +640,123 @@ impl RequestQueue {
         Ok((request_id, rx))
     }
 
+    /// Queue a typed Reddit call. The response resolves to the type the
+    /// matching [`RedditApiClient`] method returns, e.g.
+    /// `RedditListing<RedditPostData>` for a listing.
+    pub async fn enqueue_operation<T: QueuedResponseData>(
+        &self,
+        operation: QueuedOperation,
+        account_id: i64,
+        access_token: String,
+        priority: i32,
+    ) -> Result<QueuedResponse<T>, CoreError> {
+        let (request_id, receiver) = self
+            .enqueue_request(
+                operation.endpoint(),
+                "GET".to_string(),
+                account_id,
+                access_token,
+                priority,
+                Some(operation.operation_type().to_string()),
+                operation.query_params(),
+                operation.subreddit().map(|s| s.to_string()),
+                None,
+            )
+            .await?;
+
+        Ok(QueuedResponse {
+            request_id,
+            receiver,
+            _response: PhantomData,
+        })
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub async fn enqueue_listing(
+        &self,
+        account_id: i64,
+        access_token: String,
+        subreddit: &str,
+        sort: &str,
+        limit: Option<u32>,
+        after: Option<String>,
+        priority: i32,
+    ) -> Result<QueuedResponse<RedditListing<RedditPostData>>, CoreError> {
+        let operation = QueuedOperation::SubredditListing {
+            subreddit: subreddit.to_string(),
+            sort: sort.to_string(),
+            limit,
+            after,
+        };
+        self.enqueue_operation(operation, account_id, access_token, priority)
+            .await
+    }
+
+    pub async fn enqueue_subreddit_about(
+        &self,
+        account_id: i64,
+        access_token: String,
+        subreddit: &str,
+        priority: i32,
+    ) -> Result<QueuedResponse<RedditSubredditData>, CoreError> {
+        let operation = QueuedOperation::SubredditAbout {
+            subreddit: subreddit.to_string(),
+        };
+        self.enqueue_operation(operation, account_id, access_token, priority)
+            .await
+    }
+
+    pub async fn enqueue_me(
+        &self,
+        account_id: i64,
+        access_token: String,
+        priority: i32,
+    ) -> Result<QueuedResponse<RedditUserData>, CoreError> {
+        self.enqueue_operation(QueuedOperation::Me, account_id, access_token, priority)
+            .await
+    }
+
+    /// Subscribe to a request that is already queued or executing, raising
+    /// its priority if the new caller's is higher
+    async fn join_request(
+        &self,
+        request_id: &str,
+        priority: i32,
+    ) -> Result<mpsc::UnboundedReceiver<RequestResult>, CoreError> {
+        let (tx, rx) = mpsc::unbounded_channel();
+
+        let raised = {
+            let mut queue = self.queue.write().await;
+            let mut requests = self.requests.write().await;
+            let mut senders = self.result_senders.write().await;
+
+            senders.entry(request_id.to_string()).or_default().push(tx);
+
+            match requests.get_mut(request_id) {
+                Some(request) if priority > request.priority => {
+                    request.priority = priority;
+                    reprioritize(&mut queue, request_id, priority);
+                    true
+                }
+                _ => false,
+            }
+        };
+
+        if raised {
+            debug!("Raised priority of request {} to {}", request_id, priority);
+            self.update_request_priority(request_id, priority).await?;
+        }
+
+        Ok(rx)
+    }
+
     async fn process_next_request(&self) -> Result<(), CoreError> {
         let next_request = {
             let mut queue = self.queue.write().await;
-            queue.pop()
+            take_next_due(&mut queue, SystemTime::now())
         };
 
         if let Some(priority_req) = next_request {
-            // Check if request is scheduled for the future
-            if priority_req.scheduled_for > SystemTime::now() {
-                // Put it back and wait
-                {
-                    let mut queue = self.queue.write().await;
-                    queue.push(priority_req);
-                }
-                sleep(Duration::from_millis(100)).await;
-                return Ok(());
-            }
-
             let request = {
                 let requests = self.requests.read().await;
                 requests.get(&priority_req.request_id).cloned()
@@ -209,7 +766,7 @@ impl RequestQueue {
                 self.execute_request(&mut request).await?;
             }
         } else {
-            // No requests in queue, wait a bit
+            // Nothing due yet, wait a bit
             sleep(Duration::from_millis(100)).await;
         }
 
@@ -236,35 +793,42 @@ impl RequestQueue {
             response_data: None,
         };
 
-        // Simulate API request execution
-        // In a real implementation, this would call the actual API client
-        match self.simulate_api_request(request).await {
-            Ok((status_code, response_data)) => {
-                result.success = status_code < 400;
+        match self.send_request(request).await {
+            Ok((status_code, response_data)) if (200..300).contains(&status_code) => {
+                result.success = true;
                 result.status_code = Some(status_code);
                 result.response_data = Some(response_data);
                 result.response_time = start_time.elapsed().unwrap_or_default();
 
-                if result.success {
-                    self.complete_request(request, &result).await?;
-                } else {
-                    self.handle_request_failure(request, &result).await?;
-                }
+                self.complete_request(request, &result).await?;
             }
-            Err(e) => {
-                result.error_message = Some(e.to_string());
+            Ok((status_code, _)) => {
+                // Statuses the API client doesn't map to an error, such as
+                // 400 or 409, won't succeed on a retry either
+                result.status_code = Some(status_code);
+                result.error_message =
+                    Some(format!("Reddit answered with HTTP status {}", status_code));
                 result.response_time = start_time.elapsed().unwrap_or_default();
 
-                self.handle_request_failure(request, &result).await?;
+                self.fail_request(request, &result).await?;
             }
-        }
+            Err(e) => {
+                result.error_message = Some(e.to_string());
+                result.response_time = start_time.elapsed().unwrap_or_default();
 
-        // Send result to waiting caller
-        {
-            let senders = self.result_senders.read().await;
-            if let Some(sender) = senders.get(&request.request_id) {
-                if let Err(_) = sender.send(result) {
-                    warn!("Failed to send result for request {}", request.request_id);
+                // The API client has already retried whatever it could and
+                // hands back its last error. Rate limits, server errors and
+                // timeouts are queued again; anything else fails now.
+                match get_retry_strategy(&e) {
+                    RetryStrategy::NoRetry => self.fail_request(request, &result).await?,
+                    RetryStrategy::RetryWithDelay(retry_after) => {
+                        self.handle_request_failure(request, &result, retry_after)
+                            .await?
+                    }
+                    RetryStrategy::Retry => {
+                        self.handle_request_failure(request, &result, Duration::ZERO)
+                            .await?
+                    }
                 }
             }
         }
@@ -272,48 +836,42 @@ impl RequestQueue {
         Ok(())
     }
 
-    async fn simulate_api_request(
-        &self,
-        request: &QueuedRequest,
-    ) -> Result<(u16, String), CoreError> {
-        // This is a placeholder - in real implementation, this would use the actual API client
-        // For now, we'll simulate different responses based on endpoint patterns
-
-        sleep(Duration::from_millis(50 + (request.priority * 10) as u64)).await;
-
-        let status_code = if request.endpoint.contains("nonexistent") {
-            404
-        } else if request.retry_count > 0 {
-            200 // Succeed on retry
-        } else if request.priority < 0 {
-            // Low priority requests might get rate limited more often
-            if rand::random::<f32>() < 0.3 {
-                429
-            } else {
-                200
-            }
-        } else {
-            200
-        };
+    /// Send a request through the API client and read its body
+    async fn send_request(&self, request: &QueuedRequest) -> Result<(u16, String), CoreError> {
+        let method =
+            Method::from_bytes(request.method.to_uppercase().as_bytes()).map_err(|_| {
+                CoreError::InvalidInput {
+                    message: format!("Invalid HTTP method: {}", request.method),
+                }
+            })?;
+        let query_params: Option<Vec<(&str, &str)>> = request.query_params.as_ref().map(|params| {
+            params
+                .iter()
+                .map(|(key, value)| (key.as_str(), value.as_str()))
+                .collect()
+        });
 
-        let response_data = format!(
-            "{{\"endpoint\": \"{}\", \"method\": \"{}\", \"status\": {}}}",
-            request.endpoint, request.method, status_code
-        );
+        let send = async {
+            let response = self
+                .api_client
+                .make_request_with_context(
+                    method,
+                    &request.endpoint,
+                    &request.access_token,
+                    query_params.as_deref(),
+                    request.operation_type.as_deref(),
+                    request.subreddit.as_deref(),
+                    request.priority,
+                )
+                .await?;
+            let status_code = response.status().as_u16();
+            let body = response.text().await?;
+            Ok((status_code, body))
+        };
 
-        if status_code == 429 {
-            Err(CoreError::RateLimited {
-                message: "Rate limited".to_string(),
-                retry_after: Some(Duration::from_secs(60)),
-            })
-        } else if status_code >= 500 {
-            Err(CoreError::RequestFailed {
-                message: "Server error".to_string(),
-                status_code: Some(status_code),
-            })
-        } else {
-            Ok((status_code, response_data))
-        }
+        timeout(request.timeout_duration, send)
+            .await
+            .unwrap_or_else(|_| Err(CoreError::RedditApi(RedditApiError::RequestTimeout)))
     }
 
     async fn complete_request(
@@ -324,49 +882,28 @@ impl RequestQueue {
         // Update database
         self.update_request_status(&request.request_id, "completed")
             .await?;
+        self.save_request_result(result).await?;
 
-        // Remove from in-memory structures
-        {
-            let mut requests = self.requests.write().await;
-            let mut senders = self.result_senders.write().await;
-
-            requests.remove(&request.request_id);
-            senders.remove(&request.request_id);
-        }
-
-        // Record metrics if tracker is available
-        if let Some(ref tracker) = self.api_tracker {
-            let _ = tracker
-                .record_api_call(
-                    &request.endpoint,
-                    &request.method,
-                    result.status_code,
-                    result.response_time,
-                    false, // Not rate limited if we completed successfully
-                    request.priority,
-                    Duration::from_secs(0), // No additional queue wait time
-                    request.operation_type.as_deref(),
-                    request.subreddit.as_deref(),
-                    None,
-                    None,
-                )
-                .await;
-        }
+        self.finish_request(result).await;
 
         debug!("Completed request {} successfully", request.request_id);
         Ok(())
     }
 
+    /// Queue a failed request again with exponential backoff, waiting at
+    /// least `retry_after`, or fail it once it is out of retries
     async fn handle_request_failure(
         &self,
         request: &mut QueuedRequest,
-        _result: &RequestResult,
+        result: &RequestResult,
+        retry_after: Duration,
     ) -> Result<(), CoreError> {
         request.retry_count += 1;
 
         if request.retry_count <= request.max_retries {
             // Schedule for retry with exponential backoff
-            let backoff_seconds = 2_u64.pow(request.retry_count) * 60; // 2, 4, 8 minutes
+            let backoff_seconds = (2_u64.pow(request.retry_count) * 60) // 2, 4, 8 minutes
+                .max(retry_after.as_secs());
             let retry_time = SystemTime::now() + Duration::from_secs(backoff_seconds);
 
             request.scheduled_for = Some(retry_time);
@@ -394,27 +931,55 @@ impl RequestQueue {
             );
         } else {
             // Max retries exceeded, mark as failed
-            self.update_request_status(&request.request_id, "failed")
-                .await?;
+            self.fail_request(request, result).await?;
+        }
 
-            // Remove from in-memory structures
-            {
-                let mut requests = self.requests.write().await;
-                let mut senders = self.result_senders.write().await;
+        Ok(())
+    }
 
-                requests.remove(&request.request_id);
-                senders.remove(&request.request_id);
-            }
+    async fn fail_request(
+        &self,
+        request: &QueuedRequest,
+        result: &RequestResult,
+    ) -> Result<(), CoreError> {
+        self.update_request_status(&request.request_id, "failed")
+            .await?;
+        self.save_request_result(result).await?;
 
-            error!(
-                "Request {} failed permanently after {} retries",
-                request.request_id, request.retry_count
-            );
-        }
+        self.finish_request(result).await;
 
+        error!(
+            "Request {} failed permanently after {} retries",
+            request.request_id, request.retry_count
+        );
         Ok(())
     }
 
+    /// Drop a finished request from memory and hand its result to every
+    /// subscriber still listening
+    async fn finish_request(&self, result: &RequestResult) {
+        let subscribers = {
+            let mut in_flight = self.in_flight.write().await;
+            let mut requests = self.requests.write().await;
+            let mut senders = self.result_senders.write().await;
+
+            in_flight.retain(|_, request_id| *request_id != result.request_id);
+            requests.remove(&result.request_id);
+            senders.remove(&result.request_id).unwrap_or_default()
+        };
+
+        let delivered = subscribers
+            .iter()
+            .filter(|sender| sender.send(result.clone()).is_ok())
+            .count();
+        debug!(
+            "Delivered result for request {} to {}/{} subscribers",
+            result.request_id,
+            delivered,
+            subscribers.len()
+        );
+    }
+
     async fn save_queued_request(&self, request: &QueuedRequest) -> Result<(), CoreError> {
         let scheduled_timestamp = request
             .scheduled_for
@@ -429,26 +994,45 @@ impl RequestQueue {
             .unwrap_or_default()
             .as_secs() as i64;
 
-        sqlx::query!(
+        let query_params = request
+            .query_params
+            .as_ref()
+            .map(serde_json::to_string)
+            .transpose()?;
+        let headers = request
+            .headers
+            .as_ref()
+            .map(serde_json::to_string)
+            .transpose()?;
+        let timeout_seconds = request.timeout_duration.as_secs() as i64;
+
+        sqlx::query(
             r#"
             INSERT INTO request_queue (
-                request_id, endpoint, method, priority, operation_type,
-                queued_at, scheduled_for, status, retry_count, max_retries
-            ) VALUES (?, ?, ?, ?, ?, ?, ?, 'queued', ?, ?)
+                request_id, endpoint, method, priority, operation_type, account_id,
+                payload, headers, query_params, queued_at, scheduled_for,
+                status, retry_count, max_retries, subreddit, timeout_seconds
+            ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, 'queued', ?, ?, ?, ?)
             "#,
-            request.request_id,
-            request.endpoint,
-            request.method,
-            request.priority,
-            request.operation_type,
-            queued_timestamp,
-            scheduled_timestamp,
-            request.retry_count,
-            request.max_retries
         )
+        .bind(&request.request_id)
+        .bind(&request.endpoint)
+        .bind(&request.method)
+        .bind(request.priority)
+        .bind(&request.operation_type)
+        .bind(request.account_id)
+        .bind(&request.payload)
+        .bind(headers)
+        .bind(query_params)
+        .bind(queued_timestamp)
+        .bind(scheduled_timestamp)
+        .bind(request.retry_count)
+        .bind(request.max_retries)
+        .bind(&request.subreddit)
+        .bind(timeout_seconds)
         .execute(&*self.pool)
         .await
-        .map_err(|e| CoreError::Database(likeminded_core::DatabaseError::Sql(e)))?;
+        .map_err(sql_error)?;
 
         Ok(())
     }
@@ -463,6 +1047,16 @@ impl RequestQueue {
             "executing" => "started_at",
             "completed" => "completed_at",
             "failed" => "failed_at",
+            // Cancelled rows only need their status, so they are not restored
+            "cancelled" => {
+                sqlx::query("UPDATE request_queue SET status = ? WHERE request_id = ?")
+                    .bind(status)
+                    .bind(request_id)
+                    .execute(&*self.pool)
+                    .await
+                    .map_err(sql_error)?;
+                return Ok(());
+            }
             _ => return Ok(()),
         };
 
@@ -475,11 +1069,75 @@ impl RequestQueue {
         .bind(request_id)
         .execute(&*self.pool)
         .await
-        .map_err(|e| CoreError::Database(likeminded_core::DatabaseError::Sql(e)))?;
+        .map_err(sql_error)?;
+
+        Ok(())
+    }
+
+    async fn update_request_priority(
+        &self,
+        request_id: &str,
+        priority: i32,
+    ) -> Result<(), CoreError> {
+        sqlx::query("UPDATE request_queue SET priority = ? WHERE request_id = ?")
+            .bind(priority)
+            .bind(request_id)
+            .execute(&*self.pool)
+            .await
+            .map_err(sql_error)?;
+
+        Ok(())
+    }
+
+    /// Keep the outcome so late subscribers can still read it
+    async fn save_request_result(&self, result: &RequestResult) -> Result<(), CoreError> {
+        sqlx::query(
+            "UPDATE request_queue SET response_data = ?, error_message = ? WHERE request_id = ?",
+        )
+        .bind(&result.response_data)
+        .bind(&result.error_message)
+        .bind(&result.request_id)
+        .execute(&*self.pool)
+        .await
+        .map_err(sql_error)?;
 
         Ok(())
     }
 
+    /// Delete completed, failed and cancelled requests older than the result
+    /// retention, returning how many rows went. Called periodically by
+    /// [`Self::start_processing`].
+    pub async fn purge_finished_requests(&self) -> Result<u64, CoreError> {
+        let cutoff = SystemTime::now()
+            .checked_sub(self.result_retention)
+            .unwrap_or(UNIX_EPOCH)
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs() as i64;
+
+        // Cancelled rows have no finish time, so their queue time is used
+        let purged = sqlx::query(
+            r#"
+            DELETE FROM request_queue
+            WHERE (status = 'completed' AND completed_at <= ?)
+               OR (status = 'failed' AND failed_at <= ?)
+               OR (status = 'cancelled' AND queued_at <= ?)
+            "#,
+        )
+        .bind(cutoff)
+        .bind(cutoff)
+        .bind(cutoff)
+        .execute(&*self.pool)
+        .await
+        .map_err(sql_error)?
+        .rows_affected();
+
+        if purged > 0 {
+            debug!("Purged {} finished requests", purged);
+        }
+        Ok(purged)
+    }
+
     async fn update_request_retry_info(
         &self,
         request_id: &str,
@@ -491,15 +1149,16 @@ impl RequestQueue {
             .unwrap_or_default()
             .as_secs() as i64;
 
+        // Back to `queued` so a restart before the retry picks it up again
         sqlx::query!(
-            "UPDATE request_queue SET retry_count = ?, scheduled_for = ? WHERE request_id = ?",
+            "UPDATE request_queue SET status = 'queued', started_at = NULL, retry_count = ?, scheduled_for = ? WHERE request_id = ?",
             retry_count,
             scheduled_timestamp,
             request_id
         )
         .execute(&*self.pool)
         .await
-        .map_err(|e| CoreError::Database(likeminded_core::DatabaseError::Sql(e)))?;
+        .map_err(sql_error)?;
 
         Ok(())
     }
@@ -520,10 +1179,12 @@ impl RequestQueue {
         )
         .fetch_all(&*self.pool)
         .await
-        .map_err(|e| CoreError::Database(likeminded_core::DatabaseError::Sql(e)))?;
+        .map_err(sql_error)?;
 
-        let mut stats = QueueStats::default();
-        stats.total_queued = queue_size;
+        let mut stats = QueueStats {
+            total_queued: queue_size,
+            ..Default::default()
+        };
 
         for row in requests_by_status {
             match row.status.as_str() {
@@ -539,10 +1200,13 @@ impl RequestQueue {
     pub async fn cancel_request(&self, request_id: &str) -> Result<bool, CoreError> {
         // Remove from queue and requests map
         let found = {
+            let mut in_flight = self.in_flight.write().await;
             let mut queue = self.queue.write().await;
             let mut requests = self.requests.write().await;
             let mut senders = self.result_senders.write().await;
 
+            in_flight.retain(|_, queued_id| queued_id != request_id);
+
             // Remove from priority queue (this is O(n) but queue should be small)
             let mut temp_vec: Vec<PriorityRequest> = queue.drain().collect();
             temp_vec.retain(|req| req.request_id != request_id);
@@ -565,6 +1229,41 @@ impl RequestQueue {
     }
 }
 
+fn queued_request_from_row(row: &SqliteRow, access_token: String) -> QueuedRequest {
+    let from_unix = |secs: i64| UNIX_EPOCH + Duration::from_secs(secs.max(0) as u64);
+
+    QueuedRequest {
+        request_id: row.get("request_id"),
+        endpoint: row.get("endpoint"),
+        method: row.get("method"),
+        priority: row.get::<i64, _>("priority") as i32,
+        operation_type: row.get("operation_type"),
+        account_id: row.get("account_id"),
+        access_token,
+        query_params: json_column(row, "query_params"),
+        payload: row.get("payload"),
+        headers: json_column(row, "headers"),
+        queued_at: from_unix(row.get("queued_at")),
+        scheduled_for: row.get::<Option<i64>, _>("scheduled_for").map(from_unix),
+        retry_count: row.get::<i64, _>("retry_count").max(0) as u32,
+        max_retries: row.get::<i64, _>("max_retries").max(0) as u32,
+        timeout_duration: row
+            .get::<Option<i64>, _>("timeout_seconds")
+            .map(|secs| Duration::from_secs(secs.max(1) as u64))
+            .unwrap_or(Duration::from_secs(30)),
+        subreddit: row.get("subreddit"),
+    }
+}
+
+fn json_column<T: serde::de::DeserializeOwned>(row: &SqliteRow, column: &str) -> Option<T> {
+    row.get::<Option<String>, _>(column)
+        .and_then(|value| serde_json::from_str(&value).ok())
+}
+
+fn sql_error(e: sqlx::Error) -> CoreError {
+    CoreError::Database(likeminded_core::DatabaseError::Sql(e))
+}
+
 #[derive(Debug, Default, Clone)]
 pub struct QueueStats {
     pub total_queued: usize,
@@ -575,28 +1274,11 @@ pub struct QueueStats {
     pub average_wait_time: Duration,
 }
 
-// Add rand dependency for simulation
-#[cfg(not(target_arch = "wasm32"))]
-mod rand {
-    pub fn random<T>() -> T
-    where
-        T: From<f32>,
-    {
-        // Simple pseudo-random for simulation
-        use std::collections::hash_map::DefaultHasher;
-        use std::hash::{Hash, Hasher};
-        use std::time::SystemTime;
-
-        let mut hasher = DefaultHasher::new();
-        SystemTime::now().hash(&mut hasher);
-        let hash = hasher.finish();
-        T::from((hash % 1000) as f32 / 1000.0)
-    }
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::mock_server::{MockRedditServer, ScriptedResponse, MOCK_ACCESS_TOKEN};
+    use crate::retry::RetryConfig;
 
     #[test]
     fn test_priority_request_ordering() {
@@ -624,4 +1306,481 @@ mod tests {
         assert_eq!(heap.pop().unwrap().request_id, "normal");
         assert_eq!(heap.pop().unwrap().request_id, "low");
     }
+
+    fn queued(method: &str, params: &[(&str, &str)], account_id: i64) -> QueuedRequest {
+        QueuedRequest {
+            request_id: Uuid::new_v4().to_string(),
+            endpoint: "/r/rust/new".to_string(),
+            method: method.to_string(),
+            priority: 0,
+            operation_type: None,
+            account_id,
+            access_token: format!("token-{}", Uuid::new_v4()),
+            query_params: Some(
+                params
+                    .iter()
+                    .map(|(key, value)| (key.to_string(), value.to_string()))
+                    .collect(),
+            ),
+            payload: None,
+            headers: None,
+            queued_at: SystemTime::now(),
+            scheduled_for: None,
+            retry_count: 0,
+            max_retries: 3,
+            timeout_duration: Duration::from_secs(30),
+            subreddit: None,
+        }
+    }
+
+    #[test]
+    fn test_request_key_identifies_logical_requests() {
+        // Each request gets a fresh access token; only the account counts
+        let key = request_key(&queued("GET", &[("limit", "25"), ("t", "day")], 1));
+
+        assert_eq!(
+            key,
+            request_key(&queued("get", &[("t", "day"), ("limit", "25")], 1))
+        );
+        assert_ne!(
+            key,
+            request_key(&queued("GET", &[("limit", "50"), ("t", "day")], 1))
+        );
+        assert_ne!(
+            key,
+            request_key(&queued("GET", &[("limit", "25"), ("t", "day")], 2))
+        );
+    }
+
+    #[test]
+    fn test_reprioritize_moves_request_forward() {
+        let now = SystemTime::now();
+        let mut heap = BinaryHeap::new();
+        for (request_id, priority) in [("first", 0), ("second", -1)] {
+            heap.push(PriorityRequest {
+                request_id: request_id.to_string(),
+                priority,
+                scheduled_for: now,
+            });
+        }
+
+        reprioritize(&mut heap, "second", 1);
+        assert_eq!(heap.pop().unwrap().request_id, "second");
+        assert_eq!(heap.pop().unwrap().request_id, "first");
+    }
+
+    #[test]
+    fn test_take_next_due_skips_future_requests() {
+        let now = SystemTime::now();
+        let mut heap = BinaryHeap::new();
+
+        heap.push(PriorityRequest {
+            request_id: "later".to_string(),
+            priority: 1,
+            scheduled_for: now + Duration::from_secs(60),
+        });
+        heap.push(PriorityRequest {
+            request_id: "due".to_string(),
+            priority: 0,
+            scheduled_for: now,
+        });
+        heap.push(PriorityRequest {
+            request_id: "overdue".to_string(),
+            priority: 0,
+            scheduled_for: now - Duration::from_secs(60),
+        });
+
+        assert_eq!(take_next_due(&mut heap, now).unwrap().request_id, "overdue");
+        assert_eq!(take_next_due(&mut heap, now).unwrap().request_id, "due");
+        assert!(take_next_due(&mut heap, now).is_none());
+        assert_eq!(heap.len(), 1);
+        assert_eq!(
+            take_next_due(&mut heap, now + Duration::from_secs(60))
+                .unwrap()
+                .request_id,
+            "later"
+        );
+    }
+
+    #[test]
+    fn test_queued_operation_matches_client_requests() {
+        let listing = QueuedOperation::SubredditListing {
+            subreddit: "rust".to_string(),
+            sort: "new".to_string(),
+            limit: Some(500),
+            after: Some("t3_abc".to_string()),
+        };
+        assert_eq!(listing.endpoint(), "/r/rust/new");
+        assert_eq!(
+            listing.query_params().unwrap(),
+            vec![
+                ("limit".to_string(), "100".to_string()),
+                ("after".to_string(), "t3_abc".to_string()),
+            ]
+        );
+        assert_eq!(listing.subreddit(), Some("rust"));
+
+        assert_eq!(QueuedOperation::Me.endpoint(), "/api/v1/me");
+        assert_eq!(QueuedOperation::Me.operation_type(), "get_user_info");
+        assert!(QueuedOperation::Me.query_params().is_none());
+    }
+
+    #[test]
+    fn test_result_decodes_to_response_type() {
+        let result = |success: bool, body: Option<&str>| RequestResult {
+            request_id: "request".to_string(),
+            success,
+            status_code: Some(200),
+            response_time: Duration::from_millis(10),
+            error_message: (!success).then(|| "Forbidden".to_string()),
+            response_data: body.map(|body| body.to_string()),
+        };
+
+        let listing: RedditListing<RedditPostData> = result_into(result(
+            true,
+            Some(r#"{"kind":"Listing","data":{"children":[],"after":"t3_abc","before":null,"modhash":null,"dist":0}}"#),
+        ))
+        .unwrap();
+        assert_eq!(listing.data.after.as_deref(), Some("t3_abc"));
+
+        assert!(matches!(
+            result_into::<RedditUserData>(result(true, Some("not json"))),
+            Err(CoreError::RedditApi(RedditApiError::InvalidResponse { .. }))
+        ));
+        assert!(matches!(
+            result_into::<RedditUserData>(result(false, None)),
+            Err(CoreError::RequestFailed { ref message, .. }) if message == "Forbidden"
+        ));
+    }
+
+    fn offline_queue(pool: &SqlitePool) -> RequestQueue {
+        let api_client = RedditApiClient::new("likeminded-tests/1.0".to_string());
+        RequestQueue::new(Arc::new(pool.clone()), Arc::new(api_client), 100)
+    }
+
+    async fn enqueue(
+        queue: &RequestQueue,
+        endpoint: &str,
+        account_id: i64,
+        priority: i32,
+    ) -> (String, mpsc::UnboundedReceiver<RequestResult>) {
+        queue
+            .enqueue_request(
+                endpoint.to_string(),
+                "GET".to_string(),
+                account_id,
+                format!("token-{}", account_id),
+                priority,
+                None,
+                None,
+                None,
+                None,
+            )
+            .await
+            .unwrap()
+    }
+
+    fn finished(request_id: &str, error: Option<&str>) -> RequestResult {
+        RequestResult {
+            request_id: request_id.to_string(),
+            success: error.is_none(),
+            status_code: Some(if error.is_none() { 200 } else { 403 }),
+            response_time: Duration::from_millis(5),
+            error_message: error.map(|error| error.to_string()),
+            response_data: error.is_none().then(|| "{}".to_string()),
+        }
+    }
+
+    async fn stored_status(pool: &SqlitePool, request_id: &str) -> String {
+        sqlx::query_scalar("SELECT status FROM request_queue WHERE request_id = ?")
+            .bind(request_id)
+            .fetch_one(pool)
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_restore_pending_requeues_rows_with_account_tokens() {
+        let pool = crate::tests::migrated_pool().await;
+        let before = offline_queue(&pool);
+        let (due, _) = enqueue(&before, "/r/rust/new", 1, 0).await;
+        let (executing, _) = enqueue(&before, "/r/rust/hot", 2, 0).await;
+        let (later, _) = enqueue(&before, "/r/rust/top", 1, 0).await;
+        let (no_token, _) = enqueue(&before, "/r/rust/rising", 3, 0).await;
+
+        before
+            .update_request_status(&executing, "executing")
+            .await
+            .unwrap();
+        let retry_at = SystemTime::now() + Duration::from_secs(600);
+        before
+            .update_request_retry_info(&later, 1, retry_at)
+            .await
+            .unwrap();
+
+        // The next run only has tokens for accounts 1 and 2
+        let after = offline_queue(&pool);
+        let report = after
+            .restore_pending(|account_id| {
+                (account_id != 3).then(|| format!("restored-{}", account_id))
+            })
+            .await
+            .unwrap();
+        assert_eq!(
+            report,
+            RestoreReport {
+                restored: 3,
+                reset_executing: 1,
+                merged: 0,
+                skipped: 1,
+            }
+        );
+
+        {
+            let requests = after.requests.read().await;
+            assert_eq!(requests[&due].access_token, "restored-1");
+            assert_eq!(requests[&executing].access_token, "restored-2");
+            assert_eq!(requests[&later].retry_count, 1);
+            assert!(!requests.contains_key(&no_token));
+        }
+        assert_eq!(stored_status(&pool, &executing).await, "queued");
+        assert_eq!(stored_status(&pool, &no_token).await, "queued");
+
+        // The retry keeps its schedule instead of running straight away
+        let now = SystemTime::now();
+        let mut queue = after.queue.write().await;
+        let mut due_now = Vec::new();
+        while let Some(next) = take_next_due(&mut queue, now) {
+            due_now.push(next.request_id);
+        }
+        due_now.sort();
+        let mut expected = vec![due, executing];
+        expected.sort();
+        assert_eq!(due_now, expected);
+        assert_eq!(
+            take_next_due(&mut queue, retry_at).unwrap().request_id,
+            later
+        );
+    }
+
+    #[tokio::test]
+    async fn test_subscribe_returns_result_of_finished_request() {
+        let pool = crate::tests::migrated_pool().await;
+        let before = offline_queue(&pool);
+        let (completed, _) = enqueue(&before, "/r/rust/new", 1, 0).await;
+        let (failed, _) = enqueue(&before, "/r/rust/hot", 1, 0).await;
+
+        let request = before.requests.read().await[&completed].clone();
+        before
+            .complete_request(&request, &finished(&completed, None))
+            .await
+            .unwrap();
+        let request = before.requests.read().await[&failed].clone();
+        before
+            .fail_request(&request, &finished(&failed, Some("Forbidden")))
+            .await
+            .unwrap();
+
+        // A new process only has the stored rows to go on
+        let after = offline_queue(&pool);
+        let result = after
+            .subscribe(&completed)
+            .await
+            .unwrap()
+            .unwrap()
+            .recv()
+            .await
+            .unwrap();
+        assert!(result.success);
+        assert_eq!(result.response_data.as_deref(), Some("{}"));
+
+        let result = after
+            .subscribe(&failed)
+            .await
+            .unwrap()
+            .unwrap()
+            .recv()
+            .await
+            .unwrap();
+        assert!(!result.success);
+        assert_eq!(result.error_message.as_deref(), Some("Forbidden"));
+
+        assert!(after.subscribe("unknown").await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_finished_requests_are_purged_after_retention() {
+        let pool = crate::tests::migrated_pool().await;
+        let queue = offline_queue(&pool);
+        let (completed, _) = enqueue(&queue, "/r/rust/new", 1, 0).await;
+        let (cancelled, _) = enqueue(&queue, "/r/rust/hot", 1, 0).await;
+        let (queued, _) = enqueue(&queue, "/r/rust/top", 1, 0).await;
+
+        let request = queue.requests.read().await[&completed].clone();
+        queue
+            .complete_request(&request, &finished(&completed, None))
+            .await
+            .unwrap();
+        queue.cancel_request(&cancelled).await.unwrap();
+
+        // Still within the default retention
+        assert_eq!(queue.purge_finished_requests().await.unwrap(), 0);
+        assert!(queue.subscribe(&completed).await.unwrap().is_some());
+
+        let queue = offline_queue(&pool).with_result_retention(Duration::ZERO);
+        assert_eq!(queue.purge_finished_requests().await.unwrap(), 2);
+        assert!(queue.subscribe(&completed).await.unwrap().is_none());
+        assert_eq!(stored_status(&pool, &queued).await, "queued");
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_identical_requests_are_coalesced() {
+        let pool = crate::tests::migrated_pool().await;
+        let queue = offline_queue(&pool);
+
+        let ((first, mut first_rx), (second, mut second_rx), (other_account, _)) = tokio::join!(
+            enqueue(&queue, "/r/rust/new", 1, 0),
+            enqueue(&queue, "/r/rust/new", 1, 1),
+            enqueue(&queue, "/r/rust/new", 2, 0),
+        );
+        assert_eq!(first, second);
+        assert_ne!(first, other_account);
+
+        let stored: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM request_queue")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(stored, 2);
+        let priority: i64 =
+            sqlx::query_scalar("SELECT priority FROM request_queue WHERE request_id = ?")
+                .bind(&first)
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+        assert_eq!(priority, 1);
+
+        let request = queue.requests.read().await[&first].clone();
+        queue
+            .complete_request(&request, &finished(&first, None))
+            .await
+            .unwrap();
+        assert_eq!(first_rx.recv().await.unwrap().request_id, first);
+        assert_eq!(second_rx.recv().await.unwrap().request_id, first);
+    }
+
+    #[tokio::test]
+    async fn test_typed_requests_are_sent_through_api_client() {
+        let server = MockRedditServer::start().await.unwrap();
+        server.set_user(MockRedditServer::sample_user("alice"));
+        for i in 0..3 {
+            server.add_post(MockRedditServer::sample_post(
+                "rust",
+                &format!("p{}", i),
+                1_700_000_000 + i * 60,
+            ));
+        }
+
+        let pool = crate::tests::migrated_pool().await;
+        let api_client = RedditApiClient::new("likeminded-tests/1.0".to_string())
+            .with_base_url(server.base_url());
+        let queue = RequestQueue::new(Arc::new(pool.clone()), Arc::new(api_client), 100);
+
+        let listing = queue
+            .enqueue_listing(
+                1,
+                MOCK_ACCESS_TOKEN.to_string(),
+                "rust",
+                "new",
+                Some(2),
+                None,
+                0,
+            )
+            .await
+            .unwrap();
+        let user = queue
+            .enqueue_me(1, MOCK_ACCESS_TOKEN.to_string(), 1)
+            .await
+            .unwrap();
+        let listing_id = listing.request_id().to_string();
+
+        queue.process_next_request().await.unwrap();
+        queue.process_next_request().await.unwrap();
+
+        assert_eq!(user.wait().await.unwrap().name, "alice");
+        assert_eq!(listing.wait().await.unwrap().data.children.len(), 2);
+        assert_eq!(server.request_count("/r/rust/new"), 1);
+        assert_eq!(stored_status(&pool, &listing_id).await, "completed");
+    }
+
+    #[tokio::test]
+    async fn test_unmapped_error_statuses_fail_the_request() {
+        let server = MockRedditServer::start().await.unwrap();
+        server.enqueue_response(
+            ScriptedResponse::new(400, r#"{"message": "Bad Request", "error": 400}"#)
+                .for_path("/api/v1/me"),
+        );
+
+        let pool = crate::tests::migrated_pool().await;
+        let api_client = RedditApiClient::new("likeminded-tests/1.0".to_string())
+            .with_base_url(server.base_url());
+        let queue = RequestQueue::new(Arc::new(pool.clone()), Arc::new(api_client), 100);
+
+        let user = queue
+            .enqueue_me(1, MOCK_ACCESS_TOKEN.to_string(), 0)
+            .await
+            .unwrap();
+        let request_id = user.request_id().to_string();
+        queue.process_next_request().await.unwrap();
+
+        match user.wait().await {
+            Err(CoreError::RequestFailed { status_code, .. }) => {
+                assert_eq!(status_code, Some(400))
+            }
+            other => panic!("expected a failed request, got {:?}", other),
+        }
+        assert_eq!(stored_status(&pool, &request_id).await, "failed");
+    }
+
+    #[tokio::test]
+    async fn test_transient_api_errors_are_requeued() {
+        let server = MockRedditServer::start().await.unwrap();
+        server.enqueue_response(ScriptedResponse::server_error(503).for_path("/api/v1/me"));
+        server.enqueue_response(ScriptedResponse::rate_limited(900).for_path("/r/rust/about"));
+
+        // One attempt per execution, so the queue sees each error right away
+        let retry_config = RetryConfig {
+            max_attempts: 1,
+            ..RetryConfig::default()
+        };
+        let api_client =
+            RedditApiClient::with_retry_config("likeminded-tests/1.0".to_string(), retry_config)
+                .with_base_url(server.base_url());
+        let pool = crate::tests::migrated_pool().await;
+        let queue = RequestQueue::new(Arc::new(pool.clone()), Arc::new(api_client), 100);
+
+        let mut user = queue
+            .enqueue_me(1, MOCK_ACCESS_TOKEN.to_string(), 1)
+            .await
+            .unwrap();
+        let mut about = queue
+            .enqueue_subreddit_about(1, MOCK_ACCESS_TOKEN.to_string(), "rust", 0)
+            .await
+            .unwrap();
+        let before = SystemTime::now();
+        queue.process_next_request().await.unwrap();
+        queue.process_next_request().await.unwrap();
+
+        // 503 backs off for the first retry, the 429 for as long as asked
+        for (request_id, receiver, min_delay) in [
+            (user.request_id.clone(), &mut user.receiver, 120),
+            (about.request_id.clone(), &mut about.receiver, 900),
+        ] {
+            assert!(receiver.try_recv().is_err());
+            let request = queue.requests.read().await[&request_id].clone();
+            assert_eq!(request.retry_count, 1);
+            assert!(request.scheduled_for.unwrap() >= before + Duration::from_secs(min_delay));
+            assert_eq!(stored_status(&pool, &request_id).await, "queued");
+        }
+        assert_eq!(queue.queue.read().await.len(), 2);
+    }
 }
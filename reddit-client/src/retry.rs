@@ -1,4 +1,7 @@
-use likeminded_core::{CoreError, RedditApiError};
+use likeminded_core::{
+    Backoff, CoreError, RedditApiError, RetryPolicy, RetryState, StandardRetryPolicy,
+};
+use std::collections::{BTreeMap, HashMap};
 use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
 use tokio::time::sleep;
@@ -38,6 +41,23 @@ impl Default for RetryConfig {
 }
 
 impl RetryConfig {
+    /// The exponential backoff these settings describe
+    pub fn backoff(&self) -> Backoff {
+        Backoff::Exponential {
+            base: Duration::from_millis(self.base_delay_ms),
+            max: Duration::from_millis(self.max_delay_ms),
+            multiplier: self.backoff_multiplier,
+            jitter_factor: self.jitter_factor,
+        }
+    }
+
+    /// A policy with these attempts and backoff that classifies errors with
+    /// [`get_retry_strategy`]
+    pub fn policy(&self) -> StandardRetryPolicy {
+        StandardRetryPolicy::new(self.max_attempts, self.backoff())
+            .with_classifier(get_retry_strategy)
+    }
+
     /// Create retry config optimized for Reddit API
     pub fn reddit() -> Self {
         Self {
@@ -145,19 +165,42 @@ impl CircuitBreaker {
     pub fn get_state(&self) -> CircuitBreakerState {
         self.state.clone()
     }
+
+    /// Whether [`Self::allow_request`] would let a request through, without
+    /// moving an open breaker to half-open
+    pub fn is_available(&self) -> bool {
+        match self.state {
+            CircuitBreakerState::Open => self.last_failure_time.is_some_and(|last_failure| {
+                last_failure.elapsed() >= Duration::from_secs(self.config.recovery_timeout_s)
+            }),
+            _ => true,
+        }
+    }
+
+    /// Closed with no failures counted, so a new breaker would behave the same
+    fn is_idle(&self) -> bool {
+        self.state == CircuitBreakerState::Closed && self.failure_count == 0
+    }
 }
 
-/// Retry strategy based on error type
-#[derive(Debug, Clone, PartialEq)]
-pub enum RetryStrategy {
-    /// Retry with exponential backoff
-    Retry,
-    /// Retry immediately (for rate limits with specific retry-after)
-    RetryWithDelay(Duration),
-    /// Don't retry (for permanent failures)
-    NoRetry,
+/// Breaker used by [`RetryExecutor::execute`] and other calls that do not name
+/// a source
+pub const DEFAULT_CIRCUIT: &str = "default";
+
+/// Breakers a [`RetryExecutor`] keeps before it starts evicting idle ones
+pub const DEFAULT_MAX_CIRCUIT_BREAKERS: usize = 1024;
+
+/// Circuit breaker key for an endpoint pattern, optionally narrowed to one
+/// subreddit, e.g. `/r/*/new r/rust`
+pub fn circuit_key(endpoint_pattern: &str, subreddit: Option<&str>) -> String {
+    match subreddit {
+        Some(subreddit) => format!("{} r/{}", endpoint_pattern, subreddit),
+        None => endpoint_pattern.to_string(),
+    }
 }
 
+pub use likeminded_core::RetryStrategy;
+
 /// Determine retry strategy based on error type
 pub fn get_retry_strategy(error: &CoreError) -> RetryStrategy {
     match error {
@@ -175,6 +218,7 @@ pub fn get_retry_strategy(error: &CoreError) -> RetryStrategy {
             // Authentication and permission errors are permanent
             RedditApiError::AuthenticationFailed { .. } => RetryStrategy::NoRetry,
             RedditApiError::InvalidToken => RetryStrategy::NoRetry,
+            RedditApiError::RefreshTokenRevoked => RetryStrategy::NoRetry,
             RedditApiError::Forbidden { .. } => RetryStrategy::NoRetry,
             // Not found errors are permanent
             RedditApiError::SubredditNotFound { .. } => RetryStrategy::NoRetry,
@@ -196,25 +240,7 @@ pub fn get_retry_strategy(error: &CoreError) -> RetryStrategy {
 
 /// Calculate delay with exponential backoff and jitter
 pub fn calculate_delay(attempt: u32, config: &RetryConfig) -> Duration {
-    let base_delay = Duration::from_millis(config.base_delay_ms);
-    let max_delay = Duration::from_millis(config.max_delay_ms);
-
-    // Calculate exponential backoff
-    let exponential_delay = if attempt == 0 {
-        base_delay
-    } else {
-        let multiplier = config.backoff_multiplier.powi(attempt as i32);
-        let delay_ms = (config.base_delay_ms as f64 * multiplier) as u64;
-        Duration::from_millis(delay_ms.min(config.max_delay_ms))
-    };
-
-    // Add jitter to prevent thundering herd
-    let jitter_range = (exponential_delay.as_millis() as f64 * config.jitter_factor) as u64;
-    let jitter = fastrand::u64(0..=jitter_range);
-    let final_delay = exponential_delay + Duration::from_millis(jitter);
-
-    // Ensure we don't exceed max delay
-    final_delay.min(max_delay)
+    config.backoff().delay(attempt, None)
 }
 
 /// Retry metrics for monitoring
@@ -239,26 +265,77 @@ impl Default for RetryMetrics {
     }
 }
 
-/// Retry executor that wraps operations with retry logic
+/// Retry executor that wraps operations with retry logic.
+///
+/// Whether and when to retry is up to its [`RetryPolicy`], by default
+/// [`RetryConfig::policy`]. Each source (see [`circuit_key`]) gets its own
+/// circuit breaker, created on first use, so one failing endpoint or
+/// subreddit does not block the rest. Once there are more than
+/// `max_circuit_breakers`, idle breakers are dropped to make room; open ones
+/// and ones counting failures are kept.
 #[derive(Debug)]
 pub struct RetryExecutor {
     config: RetryConfig,
-    circuit_breaker: Arc<Mutex<CircuitBreaker>>,
+    policy: Arc<dyn RetryPolicy>,
+    circuit_breakers: Arc<Mutex<HashMap<String, CircuitBreaker>>>,
+    max_circuit_breakers: usize,
     metrics: Arc<Mutex<RetryMetrics>>,
 }
 
 impl RetryExecutor {
     pub fn new(config: RetryConfig) -> Self {
-        let circuit_breaker = Arc::new(Mutex::new(CircuitBreaker::new(config.clone())));
+        let circuit_breakers = Arc::new(Mutex::new(HashMap::new()));
         let metrics = Arc::new(Mutex::new(RetryMetrics::default()));
 
         Self {
+            policy: Arc::new(config.policy()),
             config,
-            circuit_breaker,
+            circuit_breakers,
+            max_circuit_breakers: DEFAULT_MAX_CIRCUIT_BREAKERS,
             metrics,
         }
     }
 
+    /// Start evicting idle breakers once more than `max` are tracked
+    pub fn with_max_circuit_breakers(mut self, max: usize) -> Self {
+        self.max_circuit_breakers = max.max(1);
+        self
+    }
+
+    /// Decide retries with `policy` instead of the config's backoff. The
+    /// config still sets up the circuit breakers.
+    pub fn with_policy(mut self, policy: Arc<dyn RetryPolicy>) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    pub fn config(&self) -> &RetryConfig {
+        &self.config
+    }
+
+    pub fn policy(&self) -> Arc<dyn RetryPolicy> {
+        self.policy.clone()
+    }
+
+    /// Run `f` on the breaker for `source`, creating it if needed
+    fn with_breaker<R>(&self, source: &str, f: impl FnOnce(&mut CircuitBreaker) -> R) -> R {
+        let mut breakers = self.circuit_breakers.lock().unwrap();
+        if !breakers.contains_key(source) && breakers.len() >= self.max_circuit_breakers {
+            breakers.retain(|_, breaker| !breaker.is_idle());
+        }
+        let breaker = breakers
+            .entry(source.to_string())
+            .or_insert_with(|| CircuitBreaker::new(self.config.clone()));
+        f(breaker)
+    }
+
+    /// Run `f` on the breaker of every source in turn
+    fn with_breakers(&self, sources: &[String], mut f: impl FnMut(&mut CircuitBreaker)) {
+        for source in sources {
+            self.with_breaker(source, &mut f);
+        }
+    }
+
     /// Execute an operation with retry logic
     pub async fn execute<F, Fut, T>(
         &self,
@@ -269,29 +346,89 @@ impl RetryExecutor {
         F: Fn() -> Fut,
         Fut: std::future::Future<Output = Result<T, CoreError>>,
     {
-        // Check circuit breaker first
-        {
-            let mut breaker = self.circuit_breaker.lock().unwrap();
-            if !breaker.allow_request() {
-                let mut metrics = self.metrics.lock().unwrap();
-                metrics.circuit_breaker_trips += 1;
-                drop(metrics);
-                drop(breaker);
-
-                warn!(
-                    "Circuit breaker is open, blocking request for {}",
-                    operation_name
-                );
-                return Err(CoreError::Internal {
-                    message: "Circuit breaker is open".to_string(),
-                });
-            }
+        self.execute_with_max_attempts(operation_name, self.policy.max_attempts(), operation)
+            .await
+    }
+
+    /// Execute an operation with retry logic, overriding the configured
+    /// number of attempts
+    pub async fn execute_with_max_attempts<F, Fut, T>(
+        &self,
+        operation_name: &str,
+        max_attempts: u32,
+        operation: F,
+    ) -> Result<T, CoreError>
+    where
+        F: Fn() -> Fut,
+        Fut: std::future::Future<Output = Result<T, CoreError>>,
+    {
+        self.execute_for_source(operation_name, DEFAULT_CIRCUIT, max_attempts, operation)
+            .await
+    }
+
+    /// Execute an operation with retry logic behind the circuit breaker for
+    /// `source`
+    pub async fn execute_for_source<F, Fut, T>(
+        &self,
+        operation_name: &str,
+        source: &str,
+        max_attempts: u32,
+        operation: F,
+    ) -> Result<T, CoreError>
+    where
+        F: Fn() -> Fut,
+        Fut: std::future::Future<Output = Result<T, CoreError>>,
+    {
+        self.execute_for_sources(
+            operation_name,
+            &[source.to_string()],
+            max_attempts,
+            operation,
+        )
+        .await
+    }
+
+    /// Execute an operation that serves several sources at once, such as a
+    /// multireddit listing. It only runs if every source's breaker allows it,
+    /// and its outcome is recorded against each of them.
+    pub async fn execute_for_sources<F, Fut, T>(
+        &self,
+        operation_name: &str,
+        sources: &[String],
+        max_attempts: u32,
+        operation: F,
+    ) -> Result<T, CoreError>
+    where
+        F: Fn() -> Fut,
+        Fut: std::future::Future<Output = Result<T, CoreError>>,
+    {
+        let max_attempts = max_attempts.max(1);
+
+        // Check circuit breakers first
+        let blocked = sources
+            .iter()
+            .find(|source| !self.with_breaker(source, |breaker| breaker.allow_request()));
+        if let Some(source) = blocked {
+            let mut metrics = self.metrics.lock().unwrap();
+            metrics.circuit_breaker_trips += 1;
+            drop(metrics);
+
+            warn!(
+                "Circuit breaker for {} is open, blocking request for {}",
+                source, operation_name
+            );
+            return Err(CoreError::Internal {
+                message: format!("Circuit breaker is open for {}", source),
+            });
         }
 
-        let mut last_error: Option<String> = None;
+        let mut last_error: Option<CoreError> = None;
         let mut total_delay_ms = 0u64;
 
-        for attempt in 0..self.config.max_attempts {
+        let operation_start = Instant::now();
+        let mut previous_delay = None;
+
+        for attempt in 0..max_attempts {
             if attempt > 0 {
                 debug!("Retry attempt {} for {}", attempt, operation_name);
             }
@@ -299,11 +436,9 @@ impl RetryExecutor {
             let start_time = Instant::now();
             match operation().await {
                 Ok(result) => {
-                    // Success - record in circuit breaker and metrics
-                    {
-                        let mut breaker = self.circuit_breaker.lock().unwrap();
-                        breaker.record_success();
-                    }
+                    // Success - record in circuit breakers, policy and metrics
+                    self.with_breakers(sources, |breaker| breaker.record_success());
+                    self.policy.record_success();
 
                     if attempt > 0 {
                         let mut metrics = self.metrics.lock().unwrap();
@@ -333,60 +468,39 @@ impl RetryExecutor {
                         error
                     );
 
-                    // Determine if we should retry
-                    let strategy = get_retry_strategy(&error);
-                    let should_retry = attempt + 1 < self.config.max_attempts;
-
-                    match strategy {
-                        RetryStrategy::NoRetry => {
-                            debug!(
-                                "Not retrying {} due to error type: {}",
-                                operation_name, error
-                            );
-                            last_error = Some(error.to_string());
-                            break;
-                        }
-                        RetryStrategy::Retry if should_retry => {
-                            let delay = calculate_delay(attempt, &self.config);
-                            total_delay_ms += delay.as_millis() as u64;
-
-                            info!(
-                                "Retrying {} in {:?} due to: {}",
-                                operation_name, delay, error
-                            );
-
-                            last_error = Some(error.to_string());
-                            sleep(delay).await;
-                        }
-                        RetryStrategy::RetryWithDelay(delay) if should_retry => {
-                            total_delay_ms += delay.as_millis() as u64;
-
-                            info!(
-                                "Retrying {} after specified delay of {:?} due to: {}",
-                                operation_name, delay, error
-                            );
-
-                            last_error = Some(error.to_string());
-                            sleep(delay).await;
-                        }
-                        _ => {
-                            // Max attempts reached or no retry strategy
-                            if should_retry {
-                                debug!("Max retry attempts reached for {}", operation_name);
-                            }
-                            last_error = Some(error.to_string());
-                            break;
-                        }
+                    if attempt + 1 >= max_attempts {
+                        debug!("Max retry attempts reached for {}", operation_name);
+                        last_error = Some(error);
+                        break;
                     }
+
+                    // Let the policy decide whether to retry
+                    let state = RetryState {
+                        attempt: attempt + 1,
+                        elapsed: operation_start.elapsed(),
+                        previous_delay,
+                    };
+                    let Some(delay) = self.policy.next_delay(&state, &error) else {
+                        debug!("Not retrying {} after: {}", operation_name, error);
+                        last_error = Some(error);
+                        break;
+                    };
+
+                    total_delay_ms += delay.as_millis() as u64;
+                    previous_delay = Some(delay);
+
+                    info!(
+                        "Retrying {} in {:?} due to: {}",
+                        operation_name, delay, error
+                    );
+                    last_error = Some(error);
+                    sleep(delay).await;
                 }
             }
         }
 
-        // All retries failed - record failure in circuit breaker and metrics
-        {
-            let mut breaker = self.circuit_breaker.lock().unwrap();
-            breaker.record_failure();
-        }
+        // All retries failed - record failure in circuit breakers and metrics
+        self.with_breakers(sources, |breaker| breaker.record_failure());
 
         {
             let mut metrics = self.metrics.lock().unwrap();
@@ -395,13 +509,19 @@ impl RetryExecutor {
 
         error!(
             "Operation {} failed after {} attempts with total delay of {}ms",
-            operation_name, self.config.max_attempts, total_delay_ms
+            operation_name, max_attempts, total_delay_ms
         );
 
-        Err(CoreError::Internal {
-            message: last_error
-                .unwrap_or_else(|| "Unknown error during retry execution".to_string()),
-        })
+        // Hand back the error itself so callers can still tell a rate limit
+        // or server error from a permanent failure
+        Err(last_error.unwrap_or_else(|| CoreError::Internal {
+            message: "Unknown error during retry execution".to_string(),
+        }))
+    }
+
+    /// Attempts made per operation unless overridden
+    pub fn max_attempts(&self) -> u32 {
+        self.policy.max_attempts()
     }
 
     /// Get current retry metrics
@@ -409,9 +529,38 @@ impl RetryExecutor {
         self.metrics.lock().unwrap().clone()
     }
 
-    /// Get current circuit breaker state
+    /// Get the state of the [`DEFAULT_CIRCUIT`] breaker
     pub fn get_circuit_breaker_state(&self) -> CircuitBreakerState {
-        self.circuit_breaker.lock().unwrap().get_state()
+        self.get_source_state(DEFAULT_CIRCUIT)
+    }
+
+    /// Get the breaker state for `source`; sources never seen are closed
+    pub fn get_source_state(&self, source: &str) -> CircuitBreakerState {
+        self.circuit_breakers
+            .lock()
+            .unwrap()
+            .get(source)
+            .map(|breaker| breaker.get_state())
+            .unwrap_or(CircuitBreakerState::Closed)
+    }
+
+    /// Get the state of every breaker currently tracked, by source
+    pub fn get_circuit_breaker_states(&self) -> BTreeMap<String, CircuitBreakerState> {
+        self.circuit_breakers
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(source, breaker)| (source.clone(), breaker.get_state()))
+            .collect()
+    }
+
+    /// Whether a request for `source` would get past its circuit breaker
+    pub fn is_source_available(&self, source: &str) -> bool {
+        self.circuit_breakers
+            .lock()
+            .unwrap()
+            .get(source)
+            .is_none_or(|breaker| breaker.is_available())
     }
 
     /// Reset metrics (useful for testing or periodic cleanup)
@@ -488,6 +637,137 @@ mod tests {
         assert_eq!(breaker.get_state(), CircuitBreakerState::Closed);
     }
 
+    #[tokio::test]
+    async fn test_circuit_breakers_are_keyed_by_source() {
+        let config = RetryConfig {
+            max_attempts: 1,
+            failure_threshold: 1,
+            ..Default::default()
+        };
+        let executor = RetryExecutor::new(config);
+        let banned = circuit_key("/r/*/new", Some("banned"));
+        let healthy = circuit_key("/r/*/new", Some("rust"));
+
+        let result = executor
+            .execute_for_source("fetch banned", &banned, 1, || async {
+                Err::<i32, CoreError>(CoreError::RedditApi(RedditApiError::Forbidden {
+                    resource: "/r/banned/new".to_string(),
+                }))
+            })
+            .await;
+        assert!(result.is_err());
+        assert_eq!(
+            executor.get_source_state(&banned),
+            CircuitBreakerState::Open
+        );
+        assert!(!executor.is_source_available(&banned));
+
+        // Other sources and the default breaker are unaffected
+        let result = executor
+            .execute_for_source("fetch rust", &healthy, 1, || async {
+                Ok::<i32, CoreError>(1)
+            })
+            .await;
+        assert_eq!(result.unwrap(), 1);
+        assert!(executor.is_source_available(&healthy));
+        assert_eq!(
+            executor.get_circuit_breaker_state(),
+            CircuitBreakerState::Closed
+        );
+        assert_eq!(executor.get_circuit_breaker_states().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_shared_request_counts_for_every_source() {
+        let config = RetryConfig {
+            max_attempts: 1,
+            failure_threshold: 1,
+            ..Default::default()
+        };
+        let executor = RetryExecutor::new(config);
+        let members = vec![
+            circuit_key("/r/*/new", Some("rust")),
+            circuit_key("/r/*/new", Some("golang")),
+        ];
+
+        let result = executor
+            .execute_for_sources("fetch batch", &members, 1, || async {
+                Err::<i32, CoreError>(CoreError::RedditApi(RedditApiError::RequestTimeout))
+            })
+            .await;
+        assert!(result.is_err());
+        assert!(members
+            .iter()
+            .all(|source| !executor.is_source_available(source)));
+
+        // One open member blocks the shared request without running it
+        let result = executor
+            .execute_for_sources("fetch batch", &members[..1], 1, || async {
+                Ok::<i32, CoreError>(1)
+            })
+            .await;
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_idle_circuit_breakers_are_evicted() {
+        let config = RetryConfig {
+            max_attempts: 1,
+            failure_threshold: 1,
+            ..Default::default()
+        };
+        let executor = RetryExecutor::new(config).with_max_circuit_breakers(2);
+
+        let result = executor
+            .execute_for_source("fetch banned", "banned", 1, || async {
+                Err::<i32, CoreError>(CoreError::RedditApi(RedditApiError::RequestTimeout))
+            })
+            .await;
+        assert!(result.is_err());
+        for source in ["a", "b", "c"] {
+            let result = executor
+                .execute_for_source(source, source, 1, || async { Ok::<i32, CoreError>(1) })
+                .await;
+            assert!(result.is_ok());
+        }
+
+        // Healthy breakers made way; the open one is still tracked
+        let states = executor.get_circuit_breaker_states();
+        assert_eq!(states.len(), 2);
+        assert_eq!(states["banned"], CircuitBreakerState::Open);
+        assert_eq!(states["c"], CircuitBreakerState::Closed);
+    }
+
+    #[tokio::test]
+    async fn test_retry_executor_uses_policy() {
+        let config = RetryConfig {
+            max_attempts: 5,
+            base_delay_ms: 1,
+            ..Default::default()
+        };
+        let budget = Arc::new(likeminded_core::RetryBudget::new(1, 0.0));
+        let executor = RetryExecutor::new(config.clone())
+            .with_policy(Arc::new(config.policy().with_budget(budget)));
+
+        let attempt_count = Arc::new(std::sync::Mutex::new(0));
+        let attempt_count_clone = attempt_count.clone();
+        let result = executor
+            .execute("test_operation", move || {
+                let attempt_count = attempt_count_clone.clone();
+                async move {
+                    *attempt_count.lock().unwrap() += 1;
+                    Err::<i32, CoreError>(CoreError::RedditApi(RedditApiError::ServerError {
+                        status_code: 503,
+                    }))
+                }
+            })
+            .await;
+
+        // The budget only covers one retry
+        assert!(result.is_err());
+        assert_eq!(*attempt_count.lock().unwrap(), 2);
+    }
+
     #[test]
     fn test_retry_strategy_for_errors() {
         let rate_limit_error =
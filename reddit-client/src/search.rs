@@ -0,0 +1,191 @@
+//! Reddit search queries.
+//!
+//! [`SearchQuery`] describes a `/search` or `/r/{sub}/search` request. Results
+//! are always restricted to posts (`type=link`) so they can go through the
+//! same matching path as subreddit listings.
+
+use likeminded_core::{CoreError, RedditApiError};
+
+pub const SEARCH_SORTS: &[&str] = &["relevance", "hot", "top", "new", "comments"];
+
+pub const SEARCH_TIME_FILTERS: &[&str] = &["hour", "day", "week", "month", "year", "all"];
+
+/// Longest query Reddit accepts
+pub const MAX_QUERY_LENGTH: usize = 512;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchQuery {
+    pub query: String,
+    /// Search within this subreddit's endpoint instead of `/search`
+    pub subreddit: Option<String>,
+    /// Only meaningful with `subreddit`: when false, the subreddit endpoint
+    /// still searches all of Reddit
+    pub restrict_sr: bool,
+    pub sort: Option<String>,
+    pub time_filter: Option<String>,
+    pub limit: Option<u32>,
+    pub after: Option<String>,
+}
+
+impl SearchQuery {
+    /// Search all of Reddit
+    pub fn new(query: impl Into<String>) -> Self {
+        Self {
+            query: query.into(),
+            subreddit: None,
+            restrict_sr: false,
+            sort: None,
+            time_filter: None,
+            limit: None,
+            after: None,
+        }
+    }
+
+    /// Search within a single subreddit
+    pub fn in_subreddit(mut self, subreddit: impl Into<String>) -> Self {
+        self.subreddit = Some(subreddit.into());
+        self.restrict_sr = true;
+        self
+    }
+
+    pub fn with_restrict_sr(mut self, restrict_sr: bool) -> Self {
+        self.restrict_sr = restrict_sr;
+        self
+    }
+
+    pub fn with_sort(mut self, sort: impl Into<String>) -> Self {
+        self.sort = Some(sort.into());
+        self
+    }
+
+    pub fn with_time_filter(mut self, time_filter: impl Into<String>) -> Self {
+        self.time_filter = Some(time_filter.into());
+        self
+    }
+
+    pub fn with_limit(mut self, limit: u32) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    pub fn with_after(mut self, after: impl Into<String>) -> Self {
+        self.after = Some(after.into());
+        self
+    }
+
+    pub fn endpoint(&self) -> String {
+        match &self.subreddit {
+            Some(subreddit) => format!("/r/{}/search", subreddit),
+            None => "/search".to_string(),
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), CoreError> {
+        let query = self.query.trim();
+        if query.is_empty() || query.len() > MAX_QUERY_LENGTH {
+            return Err(CoreError::RedditApi(RedditApiError::InvalidResponse {
+                details: format!(
+                    "Search query must be between 1 and {} characters",
+                    MAX_QUERY_LENGTH
+                ),
+            }));
+        }
+
+        if let Some(sort) = &self.sort {
+            if !SEARCH_SORTS.contains(&sort.as_str()) {
+                return Err(CoreError::RedditApi(RedditApiError::InvalidResponse {
+                    details: format!(
+                        "Invalid search sort: {}. Valid options: {}",
+                        sort,
+                        SEARCH_SORTS.join(", ")
+                    ),
+                }));
+            }
+        }
+
+        if let Some(time) = &self.time_filter {
+            if !SEARCH_TIME_FILTERS.contains(&time.as_str()) {
+                return Err(CoreError::RedditApi(RedditApiError::InvalidResponse {
+                    details: format!(
+                        "Invalid time filter: {}. Valid options: {}",
+                        time,
+                        SEARCH_TIME_FILTERS.join(", ")
+                    ),
+                }));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Query string parameters for the request
+    pub fn query_params(&self) -> Vec<(&'static str, String)> {
+        let mut params = vec![
+            ("q", self.query.trim().to_string()),
+            ("type", "link".to_string()),
+            ("limit", self.limit.unwrap_or(25).min(100).to_string()),
+        ];
+
+        if self.subreddit.is_some() && self.restrict_sr {
+            params.push(("restrict_sr", "on".to_string()));
+        }
+        if let Some(sort) = &self.sort {
+            params.push(("sort", sort.clone()));
+        }
+        if let Some(time) = &self.time_filter {
+            params.push(("t", time.clone()));
+        }
+        if let Some(after) = &self.after {
+            params.push(("after", after.clone()));
+        }
+
+        params
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_query_params() {
+        let query = SearchQuery::new(" rust async ")
+            .in_subreddit("rust")
+            .with_sort("new")
+            .with_time_filter("week")
+            .with_limit(500)
+            .with_after("t3_abc");
+
+        assert_eq!(query.endpoint(), "/r/rust/search");
+        assert!(query.validate().is_ok());
+
+        let params = query.query_params();
+        assert!(params.contains(&("q", "rust async".to_string())));
+        assert!(params.contains(&("limit", "100".to_string())));
+        assert!(params.contains(&("restrict_sr", "on".to_string())));
+        assert!(params.contains(&("t", "week".to_string())));
+        assert!(params.contains(&("after", "t3_abc".to_string())));
+
+        let global = SearchQuery::new("rust")
+            .in_subreddit("rust")
+            .with_restrict_sr(false);
+        assert!(!global
+            .query_params()
+            .iter()
+            .any(|(key, _)| *key == "restrict_sr"));
+        assert_eq!(SearchQuery::new("rust").endpoint(), "/search");
+    }
+
+    #[test]
+    fn test_validation() {
+        assert!(SearchQuery::new("  ").validate().is_err());
+        assert!(SearchQuery::new("rust")
+            .with_sort("best")
+            .validate()
+            .is_err());
+        assert!(SearchQuery::new("rust")
+            .with_time_filter("decade")
+            .validate()
+            .is_err());
+    }
+}
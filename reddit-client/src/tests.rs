@@ -3,7 +3,8 @@ mod tests {
     // Comprehensive tests integrated into this file
 
     use crate::{
-        api, metrics, rate_limiter, AuthState, RedditClient, RedditOAuth2Config, RedditToken,
+        api, metrics, rate_limiter, AuthState, RedditAppType, RedditClient, RedditOAuth2Config,
+        RedditToken,
     };
     use likeminded_core::{CoreError, RedditApiError, RedditPost};
     use std::time::{Duration, SystemTime};
@@ -41,6 +42,24 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn test_client_secret_required_unless_installed() {
+        let mut config = create_test_config();
+        config.client_secret = String::new();
+        assert!(matches!(
+            RedditClient::new(config),
+            Err(CoreError::Config(_))
+        ));
+
+        let installed = RedditOAuth2Config::installed(
+            "test_client_id".to_string(),
+            "http://localhost:8080/callback".to_string(),
+            "likeminded/1.0 by test_user".to_string(),
+        );
+        assert_eq!(installed.app_type, RedditAppType::Installed);
+        assert!(RedditClient::new(installed).is_ok());
+    }
+
     #[test]
     fn test_auth_url_generation() {
         let config = create_test_config();
@@ -343,6 +362,34 @@ mod tests {
         assert!(status.available_tokens > 0);
     }
 
+    #[cfg(feature = "database")]
+    #[tokio::test]
+    async fn test_api_tracker_attaches_to_existing_api_client() {
+        use crate::api_tracker::ApiTracker;
+        use std::sync::Arc;
+
+        let pool = Arc::new(sqlx::SqlitePool::connect_lazy("sqlite::memory:").unwrap());
+        let tracker = || {
+            Arc::new(ApiTracker::new(
+                pool.clone(),
+                Arc::new(metrics::MetricsCollector::new()),
+            ))
+        };
+        let shared = Arc::new(
+            api::RedditApiClient::new("likeminded-tests/1.0".to_string())
+                .with_base_url("http://127.0.0.1:9".to_string()),
+        );
+
+        let client = RedditClient::new(create_test_config())
+            .unwrap()
+            .with_api_client(shared.clone())
+            .with_api_tracker(tracker());
+
+        // The shared client was kept and now has the tracker
+        assert!(Arc::ptr_eq(&client.api_client(), &shared));
+        assert!(!shared.attach_api_tracker(tracker()));
+    }
+
     // Tests for new post fetching functionality
     #[test]
     fn test_post_sorting_validation() {
@@ -565,3 +612,19 @@ mod tests {
         assert_eq!(metrics.rate_limited_requests, 1);
     }
 }
+
+/// An in-memory database with the schema from the database crate's migrations
+#[cfg(feature = "database")]
+pub(crate) async fn migrated_pool() -> sqlx::SqlitePool {
+    // Every connection to `sqlite::memory:` opens its own database
+    let pool = sqlx::sqlite::SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect("sqlite::memory:")
+        .await
+        .unwrap();
+    sqlx::migrate!("../database/migrations")
+        .run(&pool)
+        .await
+        .unwrap();
+    pool
+}
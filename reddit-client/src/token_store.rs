@@ -0,0 +1,321 @@
+//! Persistence for OAuth tokens.
+//!
+//! A [`TokenStore`] lets [`crate::RedditClient`] survive restarts without
+//! sending the user through the OAuth flow again: tokens are saved after
+//! every code exchange or refresh and loaded back at startup.
+
+use crate::RedditToken;
+use likeminded_core::CoreError;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Mutex;
+
+/// Boxed future returned by [`TokenStore`] methods
+pub type TokenStoreFuture<'a, T> = Pin<Box<dyn Future<Output = Result<T, CoreError>> + Send + 'a>>;
+
+pub trait TokenStore: Send + Sync + std::fmt::Debug {
+    /// The stored token, if any
+    fn load(&self) -> TokenStoreFuture<'_, Option<RedditToken>>;
+
+    /// Replace the stored token
+    fn save<'a>(&'a self, token: &'a RedditToken) -> TokenStoreFuture<'a, ()>;
+
+    /// Forget the stored token
+    fn clear(&self) -> TokenStoreFuture<'_, ()>;
+}
+
+/// Keeps the token in memory only; useful for tests and short-lived tools
+#[derive(Debug, Default)]
+pub struct MemoryTokenStore {
+    token: Mutex<Option<RedditToken>>,
+}
+
+impl MemoryTokenStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl TokenStore for MemoryTokenStore {
+    fn load(&self) -> TokenStoreFuture<'_, Option<RedditToken>> {
+        let token = self.token.lock().unwrap().clone();
+        Box::pin(async move { Ok(token) })
+    }
+
+    fn save<'a>(&'a self, token: &'a RedditToken) -> TokenStoreFuture<'a, ()> {
+        *self.token.lock().unwrap() = Some(token.clone());
+        Box::pin(async { Ok(()) })
+    }
+
+    fn clear(&self) -> TokenStoreFuture<'_, ()> {
+        *self.token.lock().unwrap() = None;
+        Box::pin(async { Ok(()) })
+    }
+}
+
+#[cfg(feature = "database")]
+pub use sqlite::SqliteTokenStore;
+
+#[cfg(feature = "database")]
+mod sqlite {
+    use super::{TokenStore, TokenStoreFuture};
+    use crate::RedditToken;
+    use likeminded_core::{CoreError, DatabaseError};
+    use sqlx::{Row, SqlitePool};
+    use std::sync::Arc;
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+    /// Default prefix for the rows this store owns
+    const DEFAULT_KEY_PREFIX: &str = "reddit";
+
+    /// Stores tokens in the application database. The access and refresh
+    /// tokens go in `api_keys` next to the LLM provider keys; expiry and
+    /// scopes are plain `settings` rows.
+    #[derive(Debug, Clone)]
+    pub struct SqliteTokenStore {
+        pool: Arc<SqlitePool>,
+        key_prefix: String,
+    }
+
+    impl SqliteTokenStore {
+        pub fn new(pool: Arc<SqlitePool>) -> Self {
+            Self {
+                pool,
+                key_prefix: DEFAULT_KEY_PREFIX.to_string(),
+            }
+        }
+
+        /// Store for one of several connected accounts, keeping each
+        /// account's rows apart from the others
+        pub fn for_account(pool: Arc<SqlitePool>, account_id: i64) -> Self {
+            Self {
+                pool,
+                key_prefix: format!("{}_account_{}", DEFAULT_KEY_PREFIX, account_id),
+            }
+        }
+
+        fn key(&self, name: &str) -> String {
+            format!("{}_{}", self.key_prefix, name)
+        }
+
+        async fn load_token(&self) -> Result<Option<RedditToken>, CoreError> {
+            let access_token = match self.get_api_key("access_token").await? {
+                Some(access_token) => access_token,
+                None => return Ok(None),
+            };
+            let refresh_token = self.get_api_key("refresh_token").await?;
+
+            // A token without a known expiry is treated as expired so the
+            // client refreshes it before use
+            let expires_at = self
+                .get_setting("token_expires_at")
+                .await?
+                .and_then(|value| value.parse::<u64>().ok())
+                .map(|secs| UNIX_EPOCH + Duration::from_secs(secs))
+                .unwrap_or(UNIX_EPOCH);
+
+            let scope = self
+                .get_setting("token_scope")
+                .await?
+                .map(|value| value.split_whitespace().map(str::to_string).collect())
+                .unwrap_or_default();
+
+            Ok(Some(RedditToken {
+                access_token,
+                refresh_token,
+                expires_at,
+                scope,
+            }))
+        }
+
+        async fn save_token(&self, token: &RedditToken) -> Result<(), CoreError> {
+            let now = unix_now();
+            let expires_at = token
+                .expires_at
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs()
+                .to_string();
+
+            let mut tx = self.pool.begin().await.map_err(sql_error)?;
+
+            let secrets = [
+                (self.key("access_token"), Some(&token.access_token)),
+                (self.key("refresh_token"), token.refresh_token.as_ref()),
+            ];
+            for (provider, secret) in secrets {
+                match secret {
+                    Some(secret) => {
+                        sqlx::query(
+                            "INSERT OR REPLACE INTO api_keys (provider, encrypted_key, created_at, updated_at)
+                             VALUES (?, ?, COALESCE((SELECT created_at FROM api_keys WHERE provider = ?), ?), ?)",
+                        )
+                        .bind(&provider)
+                        .bind(secret.as_bytes())
+                        .bind(&provider)
+                        .bind(now)
+                        .bind(now)
+                        .execute(&mut *tx)
+                        .await
+                        .map_err(sql_error)?;
+                    }
+                    None => {
+                        sqlx::query("DELETE FROM api_keys WHERE provider = ?")
+                            .bind(&provider)
+                            .execute(&mut *tx)
+                            .await
+                            .map_err(sql_error)?;
+                    }
+                }
+            }
+
+            let settings = [
+                (self.key("token_expires_at"), expires_at),
+                (self.key("token_scope"), token.scope.join(" ")),
+            ];
+            for (key, value) in settings {
+                sqlx::query(
+                    "INSERT OR REPLACE INTO settings (key, value, created_at, updated_at)
+                     VALUES (?, ?, COALESCE((SELECT created_at FROM settings WHERE key = ?), ?), ?)",
+                )
+                .bind(&key)
+                .bind(&value)
+                .bind(&key)
+                .bind(now)
+                .bind(now)
+                .execute(&mut *tx)
+                .await
+                .map_err(sql_error)?;
+            }
+
+            tx.commit().await.map_err(sql_error)
+        }
+
+        async fn clear_token(&self) -> Result<(), CoreError> {
+            let mut tx = self.pool.begin().await.map_err(sql_error)?;
+
+            sqlx::query("DELETE FROM api_keys WHERE provider IN (?, ?)")
+                .bind(self.key("access_token"))
+                .bind(self.key("refresh_token"))
+                .execute(&mut *tx)
+                .await
+                .map_err(sql_error)?;
+            sqlx::query("DELETE FROM settings WHERE key IN (?, ?)")
+                .bind(self.key("token_expires_at"))
+                .bind(self.key("token_scope"))
+                .execute(&mut *tx)
+                .await
+                .map_err(sql_error)?;
+
+            tx.commit().await.map_err(sql_error)
+        }
+
+        async fn get_api_key(&self, name: &str) -> Result<Option<String>, CoreError> {
+            let row = sqlx::query(
+                "SELECT encrypted_key FROM api_keys WHERE provider = ? AND is_active = TRUE",
+            )
+            .bind(self.key(name))
+            .fetch_optional(&*self.pool)
+            .await
+            .map_err(sql_error)?;
+
+            Ok(row.map(|row| String::from_utf8_lossy(&row.get::<Vec<u8>, _>(0)).to_string()))
+        }
+
+        async fn get_setting(&self, name: &str) -> Result<Option<String>, CoreError> {
+            let row = sqlx::query("SELECT value FROM settings WHERE key = ?")
+                .bind(self.key(name))
+                .fetch_optional(&*self.pool)
+                .await
+                .map_err(sql_error)?;
+
+            Ok(row.map(|row| row.get::<String, _>(0)))
+        }
+    }
+
+    impl TokenStore for SqliteTokenStore {
+        fn load(&self) -> TokenStoreFuture<'_, Option<RedditToken>> {
+            Box::pin(self.load_token())
+        }
+
+        fn save<'a>(&'a self, token: &'a RedditToken) -> TokenStoreFuture<'a, ()> {
+            Box::pin(self.save_token(token))
+        }
+
+        fn clear(&self) -> TokenStoreFuture<'_, ()> {
+            Box::pin(self.clear_token())
+        }
+    }
+
+    fn unix_now() -> i64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs() as i64
+    }
+
+    fn sql_error(e: sqlx::Error) -> CoreError {
+        CoreError::Database(DatabaseError::Sql(e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, SystemTime};
+
+    fn token() -> RedditToken {
+        RedditToken {
+            access_token: "access".to_string(),
+            refresh_token: Some("refresh".to_string()),
+            expires_at: SystemTime::now() + Duration::from_secs(3600),
+            scope: vec!["identity".to_string(), "read".to_string()],
+        }
+    }
+
+    #[tokio::test]
+    async fn test_memory_token_store() {
+        let store = MemoryTokenStore::new();
+        assert!(store.load().await.unwrap().is_none());
+
+        store.save(&token()).await.unwrap();
+        let loaded = store.load().await.unwrap().unwrap();
+        assert_eq!(loaded.access_token, "access");
+
+        store.clear().await.unwrap();
+        assert!(store.load().await.unwrap().is_none());
+    }
+
+    #[cfg(feature = "database")]
+    #[tokio::test]
+    async fn test_sqlite_token_store_round_trip() {
+        use std::sync::Arc;
+        use std::time::UNIX_EPOCH;
+
+        let pool = crate::tests::migrated_pool().await;
+        let store = SqliteTokenStore::new(Arc::new(pool));
+        assert!(store.load().await.unwrap().is_none());
+
+        let token = token();
+        store.save(&token).await.unwrap();
+        let loaded = store.load().await.unwrap().unwrap();
+        assert_eq!(loaded.access_token, token.access_token);
+        assert_eq!(loaded.refresh_token, token.refresh_token);
+        assert_eq!(loaded.scope, token.scope);
+        assert_eq!(
+            loaded
+                .expires_at
+                .duration_since(UNIX_EPOCH)
+                .unwrap()
+                .as_secs(),
+            token
+                .expires_at
+                .duration_since(UNIX_EPOCH)
+                .unwrap()
+                .as_secs()
+        );
+
+        store.clear().await.unwrap();
+        assert!(store.load().await.unwrap().is_none());
+    }
+}
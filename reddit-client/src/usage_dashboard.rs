@@ -1,5 +1,6 @@
-use crate::api_tracker::{ApiTracker, ApiUsageStats};
-use crate::request_queue::{QueueStats, RequestQueue};
+use crate::api_tracker::ApiTracker;
+use crate::metrics::{EndpointMetrics, MetricsCollector, HOURLY_RETENTION};
+use crate::request_queue::RequestQueue;
 use likeminded_core::CoreError;
 use serde::{Deserialize, Serialize};
 use sqlx::SqlitePool;
@@ -7,7 +8,7 @@ use std::collections::HashMap;
 use std::sync::Arc;
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use tokio::sync::RwLock;
-use tracing::{debug, error};
+use tracing::debug;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DashboardData {
@@ -88,6 +89,8 @@ pub struct QueueInfo {
     pub longest_waiting_request: Option<Duration>,
 }
 
+/// Response times, endpoint rankings and throughput over the same last
+/// [`HOURLY_RETENTION`] of requests. Endpoints are grouped by pattern.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PerformanceMetrics {
     pub p50_response_time: Duration,
@@ -96,6 +99,7 @@ pub struct PerformanceMetrics {
     pub slowest_endpoints: Vec<(String, Duration)>,
     pub fastest_endpoints: Vec<(String, Duration)>,
     pub error_rate_by_endpoint: Vec<(String, f64)>,
+    /// Requests per hour
     pub throughput_trend: Vec<(SystemTime, f64)>,
 }
 
@@ -112,16 +116,20 @@ pub struct UsageDashboard {
     pool: Arc<SqlitePool>,
     api_tracker: Option<Arc<ApiTracker>>,
     request_queue: Option<Arc<RequestQueue>>,
+    metrics: Arc<MetricsCollector>,
     cache: Arc<RwLock<Option<(DashboardData, SystemTime)>>>,
     cache_ttl: Duration,
 }
 
 impl UsageDashboard {
-    pub fn new(pool: Arc<SqlitePool>) -> Self {
+    /// Performance metrics are built from `metrics`, usually the client's
+    /// [`crate::api::RedditApiClient::metrics_collector`]
+    pub fn new(pool: Arc<SqlitePool>, metrics: Arc<MetricsCollector>) -> Self {
         Self {
             pool,
             api_tracker: None,
             request_queue: None,
+            metrics,
             cache: Arc::new(RwLock::new(None)),
             cache_ttl: Duration::from_secs(30), // Cache for 30 seconds
         }
@@ -194,17 +202,17 @@ impl UsageDashboard {
         let today_start = SystemTime::now()
             .duration_since(UNIX_EPOCH)
             .unwrap_or_default()
-            .as_secs()
+            .as_secs() as i64
             - (24 * 3600);
 
         let stats_row = sqlx::query!(
             r#"
             SELECT 
                 COUNT(*) as total_requests,
-                SUM(CASE WHEN status_code IS NOT NULL AND status_code < 400 THEN 1 ELSE 0 END) as successful_requests,
-                SUM(CASE WHEN status_code IS NOT NULL AND status_code >= 400 THEN 1 ELSE 0 END) as failed_requests,
-                SUM(CASE WHEN rate_limited THEN 1 ELSE 0 END) as rate_limited_requests,
-                AVG(response_time_ms) as avg_response_time_ms,
+                SUM(CASE WHEN status_code IS NOT NULL AND status_code < 400 THEN 1 ELSE 0 END) as "successful_requests?: i64",
+                SUM(CASE WHEN status_code IS NOT NULL AND status_code >= 400 THEN 1 ELSE 0 END) as "failed_requests?: i64",
+                SUM(CASE WHEN rate_limited THEN 1 ELSE 0 END) as "rate_limited_requests?: i64",
+                AVG(response_time_ms) as "avg_response_time_ms?: f64",
                 MIN(timestamp) as earliest_request
             FROM api_call_tracking 
             WHERE timestamp > ?
@@ -228,11 +236,11 @@ impl UsageDashboard {
         // Calculate uptime based on earliest request
         let uptime = if let Some(earliest) = stats_row.earliest_request {
             Duration::from_secs(
-                (SystemTime::now()
+                SystemTime::now()
                     .duration_since(UNIX_EPOCH)
                     .unwrap_or_default()
                     .as_secs()
-                    - earliest as u64),
+                    - earliest as u64,
             )
         } else {
             Duration::from_secs(0)
@@ -260,7 +268,7 @@ impl UsageDashboard {
         let one_hour_ago = SystemTime::now()
             .duration_since(UNIX_EPOCH)
             .unwrap_or_default()
-            .as_secs()
+            .as_secs() as i64
             - 3600;
 
         let peak_row = sqlx::query!(
@@ -308,7 +316,7 @@ impl UsageDashboard {
             .duration_since(UNIX_EPOCH)
             .unwrap_or_default()
             .as_secs();
-        let window_start = (now / 60) * 60; // Current minute window
+        let window_start = ((now / 60) * 60) as i64; // Current minute window
 
         let window_row = sqlx::query!(
             r#"
@@ -336,21 +344,21 @@ impl UsageDashboard {
         let one_day_ago = SystemTime::now()
             .duration_since(UNIX_EPOCH)
             .unwrap_or_default()
-            .as_secs()
+            .as_secs() as i64
             - (24 * 3600);
 
         let endpoint_rows = sqlx::query!(
             r#"
             SELECT 
-                endpoint,
+                endpoint as "endpoint!",
                 COUNT(*) as total_requests,
-                SUM(CASE WHEN status_code IS NOT NULL AND status_code < 400 THEN 1 ELSE 0 END) as successful_requests,
-                SUM(CASE WHEN status_code IS NOT NULL AND status_code >= 400 THEN 1 ELSE 0 END) as failed_requests,
-                SUM(CASE WHEN rate_limited THEN 1 ELSE 0 END) as rate_limited_requests,
-                AVG(response_time_ms) as avg_response_time_ms,
+                SUM(CASE WHEN status_code IS NOT NULL AND status_code < 400 THEN 1 ELSE 0 END) as "successful_requests?: i64",
+                SUM(CASE WHEN status_code IS NOT NULL AND status_code >= 400 THEN 1 ELSE 0 END) as "failed_requests?: i64",
+                SUM(CASE WHEN rate_limited THEN 1 ELSE 0 END) as "rate_limited_requests?: i64",
+                AVG(response_time_ms) as "avg_response_time_ms?: f64",
                 MIN(response_time_ms) as min_response_time_ms,
                 MAX(response_time_ms) as max_response_time_ms,
-                MAX(timestamp) as last_request_timestamp
+                MAX(timestamp) as "last_request_timestamp?: i64"
             FROM api_call_tracking 
             WHERE timestamp > ?
             GROUP BY endpoint
@@ -403,20 +411,21 @@ impl UsageDashboard {
     }
 
     async fn generate_alert_info(&self) -> Result<Vec<AlertInfo>, CoreError> {
+        let since = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs() as i64
+            - (7 * 24 * 3600); // Last 7 days
         let alert_rows = sqlx::query!(
             r#"
-            SELECT id, alert_type, severity, message, endpoint, triggered_at,
+            SELECT id as "id!", alert_type, severity, message, endpoint, triggered_at,
                    acknowledged_at, resolved_at
             FROM api_usage_alerts 
             WHERE triggered_at > ? OR resolved_at IS NULL
             ORDER BY triggered_at DESC
             LIMIT 50
             "#,
-            SystemTime::now()
-                .duration_since(UNIX_EPOCH)
-                .unwrap_or_default()
-                .as_secs()
-                - (7 * 24 * 3600) // Last 7 days
+            since
         )
         .fetch_all(&*self.pool)
         .await
@@ -503,162 +512,32 @@ impl UsageDashboard {
     }
 
     async fn generate_performance_metrics(&self) -> Result<PerformanceMetrics, CoreError> {
-        // Get percentile response times
-        let percentile_rows = sqlx::query!(
-            r#"
-            SELECT response_time_ms
-            FROM api_call_tracking
-            WHERE timestamp > ? AND status_code IS NOT NULL
-            ORDER BY response_time_ms
-            "#,
-            SystemTime::now()
-                .duration_since(UNIX_EPOCH)
-                .unwrap_or_default()
-                .as_secs()
-                - (24 * 3600)
-        )
-        .fetch_all(&*self.pool)
-        .await
-        .map_err(|e| CoreError::Database(likeminded_core::DatabaseError::Sql(e)))?;
-
-        let response_times: Vec<u64> = percentile_rows
-            .into_iter()
-            .map(|row| row.response_time_ms as u64)
+        // Everything comes from the collector's hourly buckets, so each
+        // figure covers the same hours without scanning api_call_tracking
+        let metrics = self.metrics.get_metrics().await;
+        let since = SystemTime::now() - HOURLY_RETENTION;
+        let requests = metrics.requests_by_pattern_since(since);
+        let latency = metrics.latency_since(since);
+        let (slowest, fastest) = endpoint_speed_rankings(&requests);
+
+        let throughput_trend = metrics
+            .hourly_request_counts
+            .iter()
+            .filter(|hour| hour.timestamp >= since)
+            .map(|hour| (hour.timestamp, hour.request_count as f64))
             .collect();
 
-        let (p50, p95, p99) = calculate_percentiles(&response_times);
-
-        // Get slowest and fastest endpoints
-        let (slowest, fastest) = self.get_endpoint_speed_rankings().await?;
-
-        // Get error rates by endpoint
-        let error_rates = self.get_error_rates_by_endpoint().await?;
-
-        // Get throughput trend (simplified)
-        let throughput_trend = self.get_throughput_trend().await?;
-
         Ok(PerformanceMetrics {
-            p50_response_time: Duration::from_millis(p50),
-            p95_response_time: Duration::from_millis(p95),
-            p99_response_time: Duration::from_millis(p99),
+            p50_response_time: latency.p50(),
+            p95_response_time: latency.p95(),
+            p99_response_time: latency.p99(),
             slowest_endpoints: slowest,
             fastest_endpoints: fastest,
-            error_rate_by_endpoint: error_rates,
+            error_rate_by_endpoint: endpoint_error_rates(&requests),
             throughput_trend,
         })
     }
 
-    async fn get_endpoint_speed_rankings(
-        &self,
-    ) -> Result<(Vec<(String, Duration)>, Vec<(String, Duration)>), CoreError> {
-        let speed_rows = sqlx::query!(
-            r#"
-            SELECT endpoint, AVG(response_time_ms) as avg_response_time
-            FROM api_call_tracking
-            WHERE timestamp > ? AND status_code IS NOT NULL
-            GROUP BY endpoint
-            HAVING COUNT(*) >= 5
-            ORDER BY avg_response_time DESC
-            "#,
-            SystemTime::now()
-                .duration_since(UNIX_EPOCH)
-                .unwrap_or_default()
-                .as_secs()
-                - (24 * 3600)
-        )
-        .fetch_all(&*self.pool)
-        .await
-        .map_err(|e| CoreError::Database(likeminded_core::DatabaseError::Sql(e)))?;
-
-        let slowest: Vec<(String, Duration)> = speed_rows
-            .iter()
-            .take(5)
-            .map(|row| {
-                (
-                    row.endpoint.clone(),
-                    Duration::from_millis(row.avg_response_time.unwrap_or(0.0) as u64),
-                )
-            })
-            .collect();
-
-        let fastest: Vec<(String, Duration)> = speed_rows
-            .iter()
-            .rev()
-            .take(5)
-            .map(|row| {
-                (
-                    row.endpoint.clone(),
-                    Duration::from_millis(row.avg_response_time.unwrap_or(0.0) as u64),
-                )
-            })
-            .collect();
-
-        Ok((slowest, fastest))
-    }
-
-    async fn get_error_rates_by_endpoint(&self) -> Result<Vec<(String, f64)>, CoreError> {
-        let error_rows = sqlx::query!(
-            r#"
-            SELECT 
-                endpoint,
-                COUNT(*) as total_requests,
-                SUM(CASE WHEN status_code >= 400 THEN 1 ELSE 0 END) as error_requests
-            FROM api_call_tracking
-            WHERE timestamp > ? AND status_code IS NOT NULL
-            GROUP BY endpoint
-            HAVING total_requests >= 10
-            ORDER BY (error_requests * 1.0 / total_requests) DESC
-            LIMIT 10
-            "#,
-            SystemTime::now()
-                .duration_since(UNIX_EPOCH)
-                .unwrap_or_default()
-                .as_secs()
-                - (24 * 3600)
-        )
-        .fetch_all(&*self.pool)
-        .await
-        .map_err(|e| CoreError::Database(likeminded_core::DatabaseError::Sql(e)))?;
-
-        Ok(error_rows
-            .into_iter()
-            .map(|row| {
-                let error_rate =
-                    (row.error_requests.unwrap_or(0) as f64 / row.total_requests as f64) * 100.0;
-                (row.endpoint, error_rate)
-            })
-            .collect())
-    }
-
-    async fn get_throughput_trend(&self) -> Result<Vec<(SystemTime, f64)>, CoreError> {
-        let trend_rows = sqlx::query!(
-            r#"
-            SELECT window_start, request_count
-            FROM rate_limit_windows
-            WHERE window_start > ? AND window_duration_seconds = 60
-            ORDER BY window_start ASC
-            "#,
-            SystemTime::now()
-                .duration_since(UNIX_EPOCH)
-                .unwrap_or_default()
-                .as_secs()
-                - (3 * 3600) // Last 3 hours
-        )
-        .fetch_all(&*self.pool)
-        .await
-        .map_err(|e| CoreError::Database(likeminded_core::DatabaseError::Sql(e)))?;
-
-        Ok(trend_rows
-            .into_iter()
-            .map(|row| {
-                (
-                    SystemTime::UNIX_EPOCH + Duration::from_secs(row.window_start as u64),
-                    row.request_count as f64,
-                )
-            })
-            .collect())
-    }
-
     async fn generate_usage_trends(&self) -> Result<UsageTrends, CoreError> {
         // Get hourly counts for last 24 hours
         let hourly_counts = self.get_hourly_request_counts(24).await?;
@@ -687,8 +566,8 @@ impl UsageDashboard {
         let cutoff = SystemTime::now()
             .duration_since(UNIX_EPOCH)
             .unwrap_or_default()
-            .as_secs()
-            - (hours * 3600);
+            .as_secs() as i64
+            - (hours * 3600) as i64;
 
         let hourly_rows = sqlx::query!(
             r#"
@@ -724,8 +603,8 @@ impl UsageDashboard {
         let cutoff = SystemTime::now()
             .duration_since(UNIX_EPOCH)
             .unwrap_or_default()
-            .as_secs()
-            - (days * 24 * 3600);
+            .as_secs() as i64
+            - (days * 24 * 3600) as i64;
 
         let daily_rows = sqlx::query!(
             r#"
@@ -755,23 +634,24 @@ impl UsageDashboard {
     }
 
     async fn get_success_rate_trend(&self) -> Result<Vec<(SystemTime, f64)>, CoreError> {
+        let since = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs() as i64
+            - (24 * 3600);
         let trend_rows = sqlx::query!(
             r#"
             SELECT 
                 (timestamp / 3600) * 3600 as hour_start,
                 COUNT(*) as total_requests,
-                SUM(CASE WHEN status_code < 400 THEN 1 ELSE 0 END) as successful_requests
+                SUM(CASE WHEN status_code < 400 THEN 1 ELSE 0 END) as "successful_requests?: i64"
             FROM api_call_tracking
             WHERE timestamp > ? AND status_code IS NOT NULL
             GROUP BY hour_start
             HAVING total_requests >= 5
             ORDER BY hour_start ASC
             "#,
-            SystemTime::now()
-                .duration_since(UNIX_EPOCH)
-                .unwrap_or_default()
-                .as_secs()
-                - (24 * 3600)
+            since
         )
         .fetch_all(&*self.pool)
         .await
@@ -792,21 +672,22 @@ impl UsageDashboard {
     }
 
     async fn get_response_time_trend(&self) -> Result<Vec<(SystemTime, Duration)>, CoreError> {
+        let since = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs() as i64
+            - (24 * 3600);
         let trend_rows = sqlx::query!(
             r#"
             SELECT 
                 (timestamp / 3600) * 3600 as hour_start,
-                AVG(response_time_ms) as avg_response_time
+                AVG(response_time_ms) as "avg_response_time?: f64"
             FROM api_call_tracking
             WHERE timestamp > ? AND status_code IS NOT NULL
             GROUP BY hour_start
             ORDER BY hour_start ASC
             "#,
-            SystemTime::now()
-                .duration_since(UNIX_EPOCH)
-                .unwrap_or_default()
-                .as_secs()
-                - (24 * 3600)
+            since
         )
         .fetch_all(&*self.pool)
         .await
@@ -835,44 +716,89 @@ struct CurrentWindowStats {
     time_until_reset: Duration,
 }
 
-fn calculate_percentiles(values: &[u64]) -> (u64, u64, u64) {
-    if values.is_empty() {
-        return (0, 0, 0);
-    }
-
-    let len = values.len();
-    let p50_idx = (len as f64 * 0.5) as usize;
-    let p95_idx = (len as f64 * 0.95) as usize;
-    let p99_idx = (len as f64 * 0.99) as usize;
-
-    let p50 = values.get(p50_idx).copied().unwrap_or(0);
-    let p95 = values.get(p95_idx).copied().unwrap_or(0);
-    let p99 = values.get(p99_idx).copied().unwrap_or(0);
+/// Endpoints paired with their average response time
+type EndpointSpeeds = Vec<(String, Duration)>;
+
+/// Five slowest and five fastest endpoints by average response time, among
+/// endpoints with at least 5 requests
+fn endpoint_speed_rankings(
+    requests: &HashMap<String, EndpointMetrics>,
+) -> (EndpointSpeeds, EndpointSpeeds) {
+    let mut speeds: Vec<(String, Duration)> = requests
+        .iter()
+        .filter(|(_, endpoint)| endpoint.request_count >= 5)
+        .map(|(name, endpoint)| (name.clone(), endpoint.average_response_time()))
+        .collect();
+    speeds.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+    let slowest = speeds.iter().take(5).cloned().collect();
+    let fastest = speeds.iter().rev().take(5).cloned().collect();
+    (slowest, fastest)
+}
 
-    (p50, p95, p99)
+/// Ten endpoints with the highest error percentage, among endpoints with at
+/// least 10 requests
+fn endpoint_error_rates(requests: &HashMap<String, EndpointMetrics>) -> Vec<(String, f64)> {
+    let mut error_rates: Vec<(String, f64)> = requests
+        .iter()
+        .filter(|(_, endpoint)| endpoint.request_count >= 10)
+        .map(|(name, endpoint)| {
+            let error_rate = endpoint.error_count as f64 / endpoint.request_count as f64 * 100.0;
+            (name.clone(), error_rate)
+        })
+        .collect();
+    error_rates.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    error_rates.truncate(10);
+    error_rates
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
-    #[test]
-    fn test_percentile_calculation() {
-        let values = vec![100, 200, 300, 400, 500, 600, 700, 800, 900, 1000];
-        let (p50, p95, p99) = calculate_percentiles(&values);
-
-        assert_eq!(p50, 500);
-        assert_eq!(p95, 900);
-        assert_eq!(p99, 1000);
-    }
+    use crate::metrics::RequestMetrics;
+
+    #[tokio::test]
+    async fn test_performance_rankings_from_collector() {
+        let collector = MetricsCollector::new();
+        for (endpoint, millis, failures) in [
+            ("/r/rust/new", 400, 0),
+            ("/r/rust/hot", 100, 5),
+            ("/api/v1/me", 20, 1),
+        ] {
+            for i in 0..10 {
+                collector
+                    .record_request(RequestMetrics {
+                        endpoint: endpoint.to_string(),
+                        method: "GET".to_string(),
+                        status_code: Some(200),
+                        response_time: Duration::from_millis(millis),
+                        success: i >= failures,
+                        rate_limited: false,
+                        error_type: None,
+                    })
+                    .await;
+            }
+        }
+        let metrics = collector.get_metrics().await;
+        let since = SystemTime::now() - HOURLY_RETENTION;
+        let requests = metrics.requests_by_pattern_since(since);
+
+        let (slowest, fastest) = endpoint_speed_rankings(&requests);
+        assert_eq!(
+            slowest[0],
+            ("/r/*/new".to_string(), Duration::from_millis(400))
+        );
+        assert_eq!(
+            fastest[0],
+            ("/api/v1/me".to_string(), Duration::from_millis(20))
+        );
 
-    #[test]
-    fn test_empty_percentiles() {
-        let values = vec![];
-        let (p50, p95, p99) = calculate_percentiles(&values);
+        let error_rates = endpoint_error_rates(&requests);
+        assert_eq!(error_rates[0], ("/r/*/hot".to_string(), 50.0));
+        assert_eq!(error_rates[2], ("/r/*/new".to_string(), 0.0));
 
-        assert_eq!(p50, 0);
-        assert_eq!(p95, 0);
-        assert_eq!(p99, 0);
+        let p50 = metrics.latency_since(since).p50().as_millis();
+        assert!((97..=103).contains(&p50), "p50 was {}ms", p50);
     }
 }
@@ -0,0 +1,1334 @@
+//! End-to-end tests against the bundled mock Reddit server.
+
+use futures::{StreamExt, TryStreamExt};
+use likeminded_core::{CoreError, Keyword, RedditApiError, SubredditHighWaterMark};
+use reddit_client::accounts::{AccountPollOutcome, AccountPool};
+use reddit_client::api::RedditApiClient;
+use reddit_client::cache::{CachePolicy, ResponseCache};
+use reddit_client::comments::CommentFetchOptions;
+use reddit_client::endpoint_router::{EndpointLimits, EndpointRouter};
+use reddit_client::mock_server::{
+    MockRedditServer, ScriptedResponse, MOCK_ACCESS_TOKEN, MOCK_PASSWORD, MOCK_REFRESH_TOKEN,
+    MOCK_USERNAME,
+};
+use reddit_client::openmetrics::{self, MetricsExporter};
+use reddit_client::pagination::PaginationOptions;
+use reddit_client::retry::{CircuitBreakerState, RetryConfig};
+use reddit_client::search::SearchQuery;
+use reddit_client::token_store::{MemoryTokenStore, TokenStore};
+use reddit_client::{AuthState, RedditAppType, RedditClient, RedditOAuth2Config, RedditToken};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+fn fast_retry_config() -> RetryConfig {
+    RetryConfig {
+        max_attempts: 3,
+        base_delay_ms: 5,
+        max_delay_ms: 20,
+        backoff_multiplier: 2.0,
+        jitter_factor: 0.0,
+        failure_threshold: 10,
+        recovery_timeout_s: 1,
+    }
+}
+
+fn test_config(server: &MockRedditServer) -> RedditOAuth2Config {
+    RedditOAuth2Config::new(
+        "test_client_id".to_string(),
+        "test_client_secret".to_string(),
+        "http://localhost:8080/callback".to_string(),
+        "likeminded-tests/1.0".to_string(),
+    )
+    .with_endpoints(server.endpoints())
+}
+
+fn test_token() -> RedditToken {
+    RedditToken {
+        access_token: MOCK_ACCESS_TOKEN.to_string(),
+        refresh_token: Some(MOCK_REFRESH_TOKEN.to_string()),
+        expires_at: SystemTime::now() + Duration::from_secs(3600),
+        scope: vec!["read".to_string()],
+    }
+}
+
+fn fast_api_client(server: &MockRedditServer) -> RedditApiClient {
+    RedditApiClient::with_retry_config("likeminded-tests/1.0".to_string(), fast_retry_config())
+        .with_base_url(server.base_url())
+}
+
+#[tokio::test]
+async fn test_fetch_posts_from_mock_server() {
+    let server = MockRedditServer::start().await.unwrap();
+    server.add_post(MockRedditServer::sample_post("rust", "a1", 1_700_000_000));
+    server.add_post(MockRedditServer::sample_post("rust", "a2", 1_700_000_100));
+
+    let mut client = RedditClient::new(test_config(&server)).unwrap();
+    client.set_token(test_token());
+
+    let posts = client
+        .fetch_posts_with_options("rust", Some("new"), None, Some(10), None)
+        .await
+        .unwrap();
+
+    assert_eq!(posts.len(), 2);
+    assert_eq!(posts[0].id, "a2");
+    assert_eq!(posts[0].subreddit, "rust");
+
+    let requests = server.requests();
+    assert_eq!(requests[0].path, "/r/rust/new");
+    assert_eq!(requests[0].query_param("limit"), Some("10"));
+    assert_eq!(
+        requests[0].headers.get("authorization").map(String::as_str),
+        Some("Bearer mock-access-token")
+    );
+}
+
+#[tokio::test]
+async fn test_oauth_code_exchange_against_mock_server() {
+    let server = MockRedditServer::start().await.unwrap();
+    let mut client = RedditClient::new(test_config(&server)).unwrap();
+
+    let (auth_url, csrf_token) = client
+        .generate_auth_url(&RedditClient::get_required_scopes())
+        .unwrap();
+    assert!(auth_url.starts_with(&format!("{}/api/v1/authorize", server.base_url())));
+
+    let callback_url = format!(
+        "http://localhost:8080/callback?state={}&code=test-code",
+        csrf_token.secret()
+    );
+    let token = client
+        .handle_callback(&callback_url, &csrf_token)
+        .await
+        .unwrap();
+
+    assert_eq!(token.access_token, MOCK_ACCESS_TOKEN);
+    assert_eq!(token.refresh_token.as_deref(), Some(MOCK_REFRESH_TOKEN));
+    assert!(client.is_authenticated());
+    assert_eq!(server.request_count("/api/v1/access_token"), 1);
+}
+
+#[tokio::test]
+async fn test_loopback_authorization_catches_redirect() {
+    let server = MockRedditServer::start().await.unwrap();
+    let port = std::net::TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port();
+    let redirect_uri = format!("http://127.0.0.1:{}/callback", port);
+    let mut config = test_config(&server);
+    config.redirect_uri = redirect_uri.clone();
+    let mut client = RedditClient::new(config).unwrap();
+
+    // Stand in for the browser: follow Reddit's redirect back to the listener
+    let mut browser = None;
+    let token = client
+        .authorize_with_loopback(
+            &RedditClient::get_required_scopes(),
+            Duration::from_secs(5),
+            |auth_url| {
+                let state = url::Url::parse(auth_url)
+                    .unwrap()
+                    .query_pairs()
+                    .find(|(key, _)| key == "state")
+                    .map(|(_, value)| value.into_owned())
+                    .unwrap();
+                let callback_url = format!("{}?state={}&code=test-code", redirect_uri, state);
+                browser = Some(tokio::spawn(async move {
+                    let response = reqwest::get(callback_url).await.unwrap();
+                    (response.status().as_u16(), response.text().await.unwrap())
+                }));
+            },
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(token.access_token, MOCK_ACCESS_TOKEN);
+    assert!(client.is_authenticated());
+
+    let (status, body) = browser.unwrap().await.unwrap();
+    assert_eq!(status, 200);
+    assert!(body.contains("Reddit connected"));
+}
+
+#[tokio::test]
+async fn test_user_and_subreddit_info() {
+    let server = MockRedditServer::start().await.unwrap();
+    server.set_user(MockRedditServer::sample_user("alice"));
+    server.add_subreddit(MockRedditServer::sample_subreddit("programming"));
+
+    let mut client = RedditClient::new(test_config(&server)).unwrap();
+    client.set_token(test_token());
+
+    let user = client.get_user_info().await.unwrap();
+    assert_eq!(user.name, "alice");
+
+    let info = client.get_subreddit_info("programming").await.unwrap();
+    assert_eq!(info.display_name, "programming");
+}
+
+#[tokio::test]
+async fn test_retries_after_scripted_rate_limit() {
+    let server = MockRedditServer::start().await.unwrap();
+    server.add_post(MockRedditServer::sample_post("rust", "a1", 1_700_000_000));
+    server.enqueue_response(ScriptedResponse::rate_limited(0).for_path("/r/rust"));
+
+    let api_client = fast_api_client(&server);
+    let listing = api_client
+        .get_subreddit_posts(MOCK_ACCESS_TOKEN, "rust", Some("new"), None, None)
+        .await
+        .unwrap();
+
+    assert_eq!(listing.data.children.len(), 1);
+    assert_eq!(server.request_count("/r/rust/new"), 2);
+    assert_eq!(api_client.get_retry_metrics().successful_retries, 1);
+}
+
+#[tokio::test]
+async fn test_retries_after_scripted_server_error() {
+    let server = MockRedditServer::start().await.unwrap();
+    server.enqueue_response(ScriptedResponse::server_error(503));
+    server.enqueue_response(ScriptedResponse::server_error(502));
+
+    let api_client = fast_api_client(&server);
+    let user = api_client.get_user_info(MOCK_ACCESS_TOKEN).await.unwrap();
+
+    assert_eq!(user.name, "mock_user");
+    assert_eq!(server.request_count("/api/v1/me"), 3);
+}
+
+#[tokio::test]
+async fn test_failed_attempts_are_recorded_in_metrics() {
+    let server = MockRedditServer::start().await.unwrap();
+    server.enqueue_response(ScriptedResponse::server_error(503));
+
+    let api_client = Arc::new(fast_api_client(&server));
+    api_client.get_user_info(MOCK_ACCESS_TOKEN).await.unwrap();
+
+    let metrics = api_client.get_metrics().await;
+    assert_eq!(metrics.total_requests, 2);
+    assert_eq!(metrics.failed_requests, 1);
+    assert_eq!(metrics.successful_requests, 1);
+
+    let body = openmetrics::render(&MetricsExporter::new(api_client).snapshot().await);
+    assert!(body.contains("reddit_requests_total{pattern=\"/api/v1/me\",outcome=\"error\"} 1\n"));
+    assert!(body.contains("reddit_requests_total{pattern=\"/api/v1/me\",outcome=\"success\"} 1\n"));
+}
+
+#[tokio::test]
+async fn test_persistent_server_errors_fail() {
+    let server = MockRedditServer::start().await.unwrap();
+    for _ in 0..3 {
+        server.enqueue_response(ScriptedResponse::server_error(500));
+    }
+
+    let api_client = fast_api_client(&server);
+    let result = api_client.get_user_info(MOCK_ACCESS_TOKEN).await;
+
+    assert!(result.is_err());
+    assert_eq!(server.request_count("/api/v1/me"), 3);
+}
+
+#[tokio::test]
+async fn test_client_state_persists_across_calls() {
+    let server = MockRedditServer::start().await.unwrap();
+    server.add_post(MockRedditServer::sample_post("rust", "a1", 1_700_000_000));
+
+    let mut client = RedditClient::new(test_config(&server)).unwrap();
+    client.set_token(test_token());
+
+    client.fetch_posts("rust").await.unwrap();
+    client.get_user_info().await.unwrap();
+
+    let metrics = client.get_api_metrics().await;
+    assert_eq!(metrics.total_requests, 2);
+    assert_eq!(metrics.successful_requests, 2);
+
+    let status = client.get_rate_limit_status().await;
+    assert!(status.current_window_requests >= 2);
+    assert!(status.available_tokens < status.max_tokens);
+}
+
+#[tokio::test]
+async fn test_shared_api_client() {
+    let server = MockRedditServer::start().await.unwrap();
+    let api_client = std::sync::Arc::new(fast_api_client(&server));
+
+    let mut client = RedditClient::new(test_config(&server))
+        .unwrap()
+        .with_api_client(api_client.clone());
+    client.set_token(test_token());
+
+    client.get_user_info().await.unwrap();
+
+    assert_eq!(api_client.get_metrics().await.total_requests, 1);
+    assert!(std::sync::Arc::ptr_eq(&client.api_client(), &api_client));
+}
+
+#[tokio::test]
+async fn test_rate_limiter_tracks_server_headers() {
+    let server = MockRedditServer::start().await.unwrap();
+    server.set_rate_limit_budget(5, 120);
+
+    let api_client = fast_api_client(&server);
+    api_client.get_user_info(MOCK_ACCESS_TOKEN).await.unwrap();
+
+    let status = api_client.get_rate_limit_status().await;
+    let server_limit = status.server_limit.clone().unwrap();
+    assert_eq!(server_limit.used, 1);
+    assert_eq!(server_limit.remaining, 4.0);
+    assert_eq!(server_limit.reset_in, Duration::from_secs(120));
+    assert!(status.available_tokens <= 4);
+    assert_eq!(status.effective_remaining(), 4);
+}
+
+fn seed_posts(server: &MockRedditServer, subreddit: &str, count: i64) {
+    for i in 0..count {
+        server.add_post(MockRedditServer::sample_post(
+            subreddit,
+            &format!("p{}", i),
+            1_700_000_000 + i * 60,
+        ));
+    }
+}
+
+#[tokio::test]
+async fn test_stream_stops_at_max_posts() {
+    let server = MockRedditServer::start().await.unwrap();
+    seed_posts(&server, "rust", 7);
+
+    let mut client = RedditClient::new(test_config(&server)).unwrap();
+    client.set_token(test_token());
+
+    let options = PaginationOptions::new().with_page_size(3).with_max_posts(5);
+    let posts: Vec<_> = client
+        .stream_subreddit_posts("rust", Some("new"), None, options)
+        .await
+        .unwrap()
+        .try_collect()
+        .await
+        .unwrap();
+
+    let ids: Vec<_> = posts.iter().map(|post| post.id.as_str()).collect();
+    assert_eq!(ids, vec!["p6", "p5", "p4", "p3", "p2"]);
+
+    // The second page only asks for what is still needed
+    let requests = server.requests();
+    assert_eq!(requests.len(), 2);
+    assert_eq!(requests[1].query_param("limit"), Some("2"));
+    assert_eq!(requests[1].query_param("after"), Some("t3_p4"));
+}
+
+#[tokio::test]
+async fn test_stream_stops_at_seen_post_and_cutoff() {
+    let server = MockRedditServer::start().await.unwrap();
+    seed_posts(&server, "rust", 10);
+
+    let mut client = RedditClient::new(test_config(&server)).unwrap();
+    client.set_token(test_token());
+
+    let options = PaginationOptions::new()
+        .with_page_size(4)
+        .with_seen_post_id("p6");
+    let posts: Vec<_> = client
+        .stream_subreddit_posts("rust", Some("new"), None, options)
+        .await
+        .unwrap()
+        .try_collect()
+        .await
+        .unwrap();
+    assert_eq!(posts.len(), 3);
+
+    let options = PaginationOptions::new()
+        .with_page_size(4)
+        .with_created_after(1_700_000_000 + 4 * 60);
+    let posts: Vec<_> = client
+        .stream_subreddit_posts("rust", Some("new"), None, options)
+        .await
+        .unwrap()
+        .try_collect()
+        .await
+        .unwrap();
+    let ids: Vec<_> = posts.iter().map(|post| post.id.as_str()).collect();
+    assert_eq!(ids, vec!["p9", "p8", "p7", "p6", "p5"]);
+}
+
+#[tokio::test]
+async fn test_stream_walks_whole_listing_and_reports_errors() {
+    let server = MockRedditServer::start().await.unwrap();
+    seed_posts(&server, "rust", 5);
+
+    let mut client = RedditClient::new(test_config(&server))
+        .unwrap()
+        .with_api_client(std::sync::Arc::new(fast_api_client(&server)));
+    client.set_token(test_token());
+
+    let options = PaginationOptions::new().with_page_size(2);
+    let posts: Vec<_> = client
+        .stream_subreddit_posts("rust", Some("new"), None, options.clone())
+        .await
+        .unwrap()
+        .try_collect()
+        .await
+        .unwrap();
+    assert_eq!(posts.len(), 5);
+    assert_eq!(server.request_count("/r/rust/new"), 3);
+
+    let results: Vec<_> = client
+        .stream_subreddit_posts("doesnotexist", Some("new"), None, options)
+        .await
+        .unwrap()
+        .collect()
+        .await;
+    assert_eq!(results.len(), 1);
+    assert!(results[0].is_err());
+}
+
+#[tokio::test]
+async fn test_fetch_new_posts_since_high_water_mark() {
+    let server = MockRedditServer::start().await.unwrap();
+    seed_posts(&server, "rust", 3);
+
+    let mut client = RedditClient::new(test_config(&server)).unwrap();
+    client.set_token(test_token());
+
+    // First poll: no mark yet, take the first page and remember the newest post
+    let (posts, mark) = client
+        .fetch_new_posts_since("rust", &SubredditHighWaterMark::default(), None)
+        .await
+        .unwrap();
+    assert_eq!(posts.len(), 3);
+    assert_eq!(mark.post_id.as_deref(), Some("p2"));
+    assert_eq!(mark.created_utc, Some(1_700_000_000 + 2 * 60));
+
+    // Nothing new since then
+    let (posts, unchanged) = client
+        .fetch_new_posts_since("rust", &mark, None)
+        .await
+        .unwrap();
+    assert!(posts.is_empty());
+    assert_eq!(unchanged, mark);
+
+    // Two new posts arrive; only those are returned
+    server.add_post(MockRedditServer::sample_post("rust", "n1", 1_700_001_000));
+    server.add_post(MockRedditServer::sample_post("rust", "n2", 1_700_002_000));
+    let (posts, mark) = client
+        .fetch_new_posts_since("rust", &mark, None)
+        .await
+        .unwrap();
+    let ids: Vec<_> = posts.iter().map(|post| post.id.as_str()).collect();
+    assert_eq!(ids, vec!["n2", "n1"]);
+    assert_eq!(mark.post_id.as_deref(), Some("n2"));
+}
+
+#[tokio::test]
+async fn test_fetch_new_posts_since_resumes_when_cut_short() {
+    let server = MockRedditServer::start().await.unwrap();
+    seed_posts(&server, "rust", 7);
+
+    let mut client = RedditClient::new(test_config(&server)).unwrap();
+    client.set_token(test_token());
+
+    // p6 down to p2 are new, but each poll only takes two of them
+    let mut mark = SubredditHighWaterMark::new("p1".to_string(), 1_700_000_000 + 60);
+    let mut polls = Vec::new();
+    for _ in 0..3 {
+        let (posts, next) = client
+            .fetch_new_posts_since("rust", &mark, Some(2))
+            .await
+            .unwrap();
+        polls.push(posts.iter().map(|post| post.id.clone()).collect::<Vec<_>>());
+        mark = next;
+
+        if polls.len() == 1 {
+            // The mark stays put until the catch-up reaches it
+            assert!(mark.is_catching_up());
+            assert_eq!(mark.post_id.as_deref(), Some("p1"));
+            server.add_post(MockRedditServer::sample_post("rust", "n1", 1_700_001_000));
+        }
+    }
+    assert_eq!(polls, vec![vec!["p6", "p5"], vec!["p4", "p3"], vec!["p2"]]);
+    assert!(!mark.is_catching_up());
+    assert_eq!(mark.post_id.as_deref(), Some("p6"));
+
+    // A post that arrived during the catch-up is picked up by the next poll
+    let (posts, mark) = client
+        .fetch_new_posts_since("rust", &mark, Some(2))
+        .await
+        .unwrap();
+    let ids: Vec<_> = posts.iter().map(|post| post.id.as_str()).collect();
+    assert_eq!(ids, vec!["n1"]);
+    assert_eq!(mark.post_id.as_deref(), Some("n1"));
+}
+
+#[tokio::test]
+async fn test_batched_fetch_uses_one_request_per_batch() {
+    let server = MockRedditServer::start().await.unwrap();
+    seed_posts(&server, "rust", 3);
+    seed_posts(&server, "golang", 2);
+    server.add_subreddit(MockRedditServer::sample_subreddit("quiet"));
+
+    let mut client = RedditClient::new(test_config(&server)).unwrap();
+    client.set_token(test_token());
+
+    let results = client
+        .fetch_batched_subreddit_posts(&["rust", "golang", "quiet"], Some("new"), None, Some(100))
+        .await
+        .unwrap();
+
+    assert_eq!(server.request_count("/r/"), 1);
+    assert_eq!(server.request_count("/r/rust+golang+quiet/new"), 1);
+
+    let counts: Vec<_> = results
+        .iter()
+        .map(|(subreddit, posts)| (subreddit.as_str(), posts.as_ref().unwrap().len()))
+        .collect();
+    assert_eq!(counts, vec![("rust", 3), ("golang", 2), ("quiet", 0)]);
+    assert!(results[0]
+        .1
+        .as_ref()
+        .unwrap()
+        .iter()
+        .all(|post| post.subreddit == "rust"));
+}
+
+#[tokio::test]
+async fn test_batched_fetch_reports_batch_errors_per_subreddit() {
+    let server = MockRedditServer::start().await.unwrap();
+    server.enqueue_response(ScriptedResponse::new(403, "{}").for_path("/r/"));
+
+    let mut client = RedditClient::new(test_config(&server)).unwrap();
+    client.set_token(test_token());
+
+    let results = client
+        .fetch_batched_subreddit_posts(&["rust", "golang"], Some("new"), None, None)
+        .await
+        .unwrap();
+
+    assert_eq!(results.len(), 2);
+    assert!(results.iter().all(|(_, posts)| posts.is_err()));
+}
+
+#[tokio::test]
+async fn test_user_subreddits_walks_every_page() {
+    let server = MockRedditServer::start().await.unwrap();
+    for i in 0..230 {
+        server.add_subreddit(MockRedditServer::sample_subreddit(&format!("sub{:03}", i)));
+    }
+
+    let mut client = RedditClient::new(test_config(&server)).unwrap();
+    client.set_token(test_token());
+
+    let subreddits = client.get_user_subreddits().await.unwrap();
+    assert_eq!(subreddits.len(), 230);
+    assert_eq!(subreddits[0].display_name, "sub000");
+    assert_eq!(subreddits[229].display_name, "sub229");
+    assert_eq!(server.request_count("/subreddits/mine/subscriber"), 3);
+}
+
+fn seed_comment_thread(server: &MockRedditServer) {
+    server.add_post(MockRedditServer::sample_post(
+        "rust",
+        "post1",
+        1_700_000_000,
+    ));
+    for i in 0..5 {
+        let id = format!("c{}", i);
+        server.add_comment(MockRedditServer::sample_comment("post1", &id, None));
+        server.add_comment(MockRedditServer::sample_comment(
+            "post1",
+            &format!("{}r", id),
+            Some(&id),
+        ));
+    }
+    server.add_comment(MockRedditServer::sample_comment(
+        "post1",
+        "c0rr",
+        Some("c0r"),
+    ));
+}
+
+#[tokio::test]
+async fn test_fetch_comment_tree_expands_more_stubs() {
+    let server = MockRedditServer::start().await.unwrap();
+    seed_comment_thread(&server);
+
+    let mut client = RedditClient::new(test_config(&server)).unwrap();
+    client.set_token(test_token());
+
+    let options = CommentFetchOptions::new().with_limit(2);
+    let thread = client.fetch_post_comments("post1", &options).await.unwrap();
+
+    assert_eq!(thread.post.id, "post1");
+    assert_eq!(thread.comments.len(), 5);
+    assert_eq!(thread.comment_count(), 11);
+    assert_eq!(thread.unexpanded_count, 0);
+    assert_eq!(thread.comments[0].replies[0].replies[0].id, "c0rr");
+    assert_eq!(thread.comments[0].replies[0].replies[0].depth, 2);
+    assert_eq!(thread.comments[4].replies[0].id, "c4r");
+    assert_eq!(server.request_count("/api/morechildren"), 1);
+}
+
+#[tokio::test]
+async fn test_fetch_comment_tree_respects_limits() {
+    let server = MockRedditServer::start().await.unwrap();
+    seed_comment_thread(&server);
+
+    let mut client = RedditClient::new(test_config(&server)).unwrap();
+    client.set_token(test_token());
+
+    // Top-level only, no expansion calls allowed
+    let options = CommentFetchOptions::new()
+        .with_limit(2)
+        .with_max_depth(0)
+        .with_max_more_requests(0);
+    let thread = client.fetch_post_comments("post1", &options).await.unwrap();
+
+    assert_eq!(thread.comment_count(), 2);
+    assert!(thread.comments.iter().all(|c| c.replies.is_empty()));
+    assert!(thread.unexpanded_count > 0);
+    assert_eq!(server.request_count("/api/morechildren"), 0);
+
+    let options = CommentFetchOptions::new().with_max_comments(3);
+    let thread = client.fetch_post_comments("post1", &options).await.unwrap();
+    assert_eq!(thread.comment_count(), 3);
+}
+
+#[tokio::test]
+async fn test_search_posts_global_and_restricted() {
+    let server = MockRedditServer::start().await.unwrap();
+    let mut matching = MockRedditServer::sample_post("rust", "r1", 1_700_000_000);
+    matching.title = "Async Rust in practice".to_string();
+    server.add_post(matching);
+    let mut elsewhere = MockRedditServer::sample_post("programming", "p1", 1_700_000_100);
+    elsewhere.selftext = "Thoughts on async rust".to_string();
+    server.add_post(elsewhere);
+    server.add_post(MockRedditServer::sample_post("rust", "r2", 1_700_000_200));
+
+    let mut client = RedditClient::new(test_config(&server)).unwrap();
+    client.set_token(test_token());
+
+    let (posts, after) = client
+        .search_posts(&SearchQuery::new("async rust").with_sort("new"))
+        .await
+        .unwrap();
+    let ids: Vec<_> = posts.iter().map(|post| post.id.as_str()).collect();
+    assert_eq!(ids, vec!["p1", "r1"]);
+    assert!(after.is_none());
+
+    let (posts, _) = client
+        .search_posts(&SearchQuery::new("async rust").in_subreddit("rust"))
+        .await
+        .unwrap();
+    assert_eq!(posts.len(), 1);
+    assert_eq!(posts[0].id, "r1");
+
+    let request = server.requests().pop().unwrap();
+    assert_eq!(request.path, "/r/rust/search");
+    assert_eq!(request.query_param("type"), Some("link"));
+    assert_eq!(request.query_param("restrict_sr"), Some("on"));
+}
+
+#[tokio::test]
+async fn test_search_keyword_pages_through_results() {
+    let server = MockRedditServer::start().await.unwrap();
+    for i in 0..150 {
+        let mut post = MockRedditServer::sample_post("rust", &format!("s{}", i), 1_700_000_000 + i);
+        post.title = format!("tokio question {}", i);
+        server.add_post(post);
+    }
+
+    let mut client = RedditClient::new(test_config(&server)).unwrap();
+    client.set_token(test_token());
+
+    let mut keyword = Keyword {
+        id: None,
+        text: "tokio".to_string(),
+        embedding: None,
+        search_reddit: false,
+        created_at: 0,
+    };
+    assert!(client
+        .search_keyword(&keyword, 120)
+        .await
+        .unwrap()
+        .is_empty());
+    assert_eq!(server.request_count("/search"), 0);
+
+    keyword.search_reddit = true;
+    let posts = client.search_keyword(&keyword, 120).await.unwrap();
+    assert_eq!(posts.len(), 120);
+    assert_eq!(posts[0].id, "s149");
+    assert_eq!(server.request_count("/search"), 2);
+}
+
+fn current_access_token(client: &RedditClient) -> String {
+    match client.get_auth_state() {
+        AuthState::Authenticated { token } | AuthState::TokenExpired { token } => {
+            token.access_token.clone()
+        }
+        _ => panic!("client has no token"),
+    }
+}
+
+#[tokio::test]
+async fn test_token_is_persisted_and_restored() {
+    let server = MockRedditServer::start().await.unwrap();
+    server.add_post(MockRedditServer::sample_post("rust", "a1", 1_700_000_000));
+    let store = Arc::new(MemoryTokenStore::new());
+
+    let mut client = RedditClient::new(test_config(&server))
+        .unwrap()
+        .with_token_store(store.clone());
+    let (_, csrf_token) = client
+        .generate_auth_url(&RedditClient::get_required_scopes())
+        .unwrap();
+    let callback_url = format!(
+        "http://localhost:8080/callback?state={}&code=test-code",
+        csrf_token.secret()
+    );
+    client
+        .handle_callback(&callback_url, &csrf_token)
+        .await
+        .unwrap();
+
+    let saved = store.load().await.unwrap().unwrap();
+    assert_eq!(saved.access_token, MOCK_ACCESS_TOKEN);
+
+    // A fresh client picks the token up without another OAuth round trip
+    let mut restarted = RedditClient::new(test_config(&server))
+        .unwrap()
+        .with_token_store(store.clone());
+    assert!(restarted.load_persisted_token().await.unwrap());
+    assert!(restarted.is_authenticated());
+    restarted.fetch_posts("rust").await.unwrap();
+    assert_eq!(server.request_count("/api/v1/access_token"), 1);
+}
+
+#[tokio::test]
+async fn test_expired_token_is_refreshed_and_saved() {
+    let server = MockRedditServer::start().await.unwrap();
+    server.add_post(MockRedditServer::sample_post("rust", "a1", 1_700_000_000));
+    let store = Arc::new(MemoryTokenStore::new());
+
+    let mut client = RedditClient::new(test_config(&server))
+        .unwrap()
+        .with_token_store(store.clone());
+    client.set_token(RedditToken {
+        access_token: "expired-token".to_string(),
+        expires_at: SystemTime::now() - Duration::from_secs(10),
+        ..test_token()
+    });
+
+    client.fetch_posts("rust").await.unwrap();
+
+    assert_eq!(server.request_count("/api/v1/access_token"), 1);
+    assert_eq!(current_access_token(&client), MOCK_ACCESS_TOKEN);
+    let saved = store.load().await.unwrap().unwrap();
+    assert_eq!(saved.access_token, MOCK_ACCESS_TOKEN);
+}
+
+#[tokio::test]
+async fn test_token_near_expiry_is_refreshed_in_background() {
+    let server = MockRedditServer::start().await.unwrap();
+    server.add_post(MockRedditServer::sample_post("rust", "a1", 1_700_000_000));
+
+    let mut client = RedditClient::new(test_config(&server)).unwrap();
+    client.set_token(RedditToken {
+        access_token: "old-token".to_string(),
+        expires_at: SystemTime::now() + Duration::from_secs(60),
+        ..test_token()
+    });
+
+    // The still-valid token is used while the refresh runs
+    client.fetch_posts("rust").await.unwrap();
+    let first_listing = server
+        .requests()
+        .into_iter()
+        .find(|request| request.path == "/r/rust/hot")
+        .unwrap();
+    assert_eq!(
+        first_listing
+            .headers
+            .get("authorization")
+            .map(String::as_str),
+        Some("Bearer old-token")
+    );
+
+    // A later call adopts the refreshed token once the task has finished
+    for _ in 0..100 {
+        client.ensure_authenticated().await.unwrap();
+        if current_access_token(&client) == MOCK_ACCESS_TOKEN {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    assert_eq!(current_access_token(&client), MOCK_ACCESS_TOKEN);
+    assert_eq!(server.request_count("/api/v1/access_token"), 1);
+    assert!(!client.needs_refresh());
+}
+
+#[tokio::test]
+async fn test_installed_app_without_secret() {
+    let server = MockRedditServer::start().await.unwrap();
+    server.add_post(MockRedditServer::sample_post("rust", "a1", 1_700_000_000));
+    let config = RedditOAuth2Config::installed(
+        "installed_client_id".to_string(),
+        "http://localhost:8080/callback".to_string(),
+        "likeminded-tests/1.0".to_string(),
+    )
+    .with_endpoints(server.endpoints());
+
+    // The code flow still works with an empty secret
+    let mut client = RedditClient::new(config.clone()).unwrap();
+    let (_, csrf_token) = client
+        .generate_auth_url(&RedditClient::get_required_scopes())
+        .unwrap();
+    let callback_url = format!(
+        "http://localhost:8080/callback?state={}&code=test-code",
+        csrf_token.secret()
+    );
+    client
+        .handle_callback(&callback_url, &csrf_token)
+        .await
+        .unwrap();
+
+    // App-only access uses the installed_client grant
+    let mut app_only = RedditClient::new(config).unwrap();
+    let token = app_only.authenticate_app_only(None).await.unwrap();
+    assert!(token.refresh_token.is_none());
+    assert_eq!(app_only.fetch_posts("rust").await.unwrap().len(), 1);
+
+    let grants: Vec<String> = server
+        .requests()
+        .into_iter()
+        .filter(|request| request.path == "/api/v1/access_token")
+        .map(|request| request.body)
+        .collect();
+    assert!(grants[1].contains("installed_client"));
+    assert!(grants[1].contains("device_id=DO_NOT_TRACK_THIS_DEVICE"));
+}
+
+#[tokio::test]
+async fn test_web_app_client_credentials() {
+    let server = MockRedditServer::start().await.unwrap();
+    let mut client = RedditClient::new(test_config(&server)).unwrap();
+
+    client.authenticate_app_only(None).await.unwrap();
+    assert!(client.is_authenticated());
+
+    let request = server.requests().pop().unwrap();
+    assert!(request.body.contains("grant_type=client_credentials"));
+}
+
+#[tokio::test]
+async fn test_script_app_password_grant_logs_in_again_on_expiry() {
+    let server = MockRedditServer::start().await.unwrap();
+    server.add_post(MockRedditServer::sample_post("rust", "a1", 1_700_000_000));
+    let config = test_config(&server).with_app_type(RedditAppType::Script);
+
+    // Only script apps may use the password grant
+    let mut web_client = RedditClient::new(test_config(&server)).unwrap();
+    assert!(web_client
+        .authenticate_with_password(MOCK_USERNAME, MOCK_PASSWORD)
+        .await
+        .is_err());
+
+    let mut client = RedditClient::new(config).unwrap();
+    assert!(client
+        .authenticate_with_password(MOCK_USERNAME, "wrong")
+        .await
+        .is_err());
+    assert!(!client.is_authenticated());
+
+    let token = client
+        .authenticate_with_password(MOCK_USERNAME, MOCK_PASSWORD)
+        .await
+        .unwrap();
+    assert!(token.refresh_token.is_none());
+
+    // With no refresh token, an expired token is replaced by logging in again
+    client.set_token(RedditToken {
+        access_token: "expired-token".to_string(),
+        expires_at: SystemTime::now() - Duration::from_secs(10),
+        ..token
+    });
+    client.fetch_posts("rust").await.unwrap();
+    assert_eq!(current_access_token(&client), MOCK_ACCESS_TOKEN);
+    assert_eq!(server.request_count("/api/v1/access_token"), 3);
+}
+
+#[tokio::test]
+async fn test_logout_revokes_and_forgets_tokens() {
+    let server = MockRedditServer::start().await.unwrap();
+    let store = Arc::new(MemoryTokenStore::new());
+    let mut client = RedditClient::new(test_config(&server))
+        .unwrap()
+        .with_token_store(store.clone());
+    client.set_token(test_token());
+    store.save(&test_token()).await.unwrap();
+
+    client.logout().await.unwrap();
+
+    assert!(matches!(
+        client.get_auth_state(),
+        AuthState::NotAuthenticated
+    ));
+    assert!(store.load().await.unwrap().is_none());
+    assert_eq!(server.request_count("/api/v1/revoke_token"), 2);
+    assert!(server.is_token_revoked(MOCK_REFRESH_TOKEN));
+    assert!(server.is_token_revoked(MOCK_ACCESS_TOKEN));
+
+    // Logging out twice is harmless
+    client.logout().await.unwrap();
+    assert_eq!(server.request_count("/api/v1/revoke_token"), 2);
+}
+
+#[tokio::test]
+async fn test_revoked_refresh_token_requires_reconnect() {
+    let server = MockRedditServer::start().await.unwrap();
+    server.add_post(MockRedditServer::sample_post("rust", "a1", 1_700_000_000));
+    let store = Arc::new(MemoryTokenStore::new());
+    let mut client = RedditClient::new(test_config(&server))
+        .unwrap()
+        .with_token_store(store.clone());
+    let expired = RedditToken {
+        access_token: "expired-token".to_string(),
+        expires_at: SystemTime::now() - Duration::from_secs(10),
+        ..test_token()
+    };
+    store.save(&expired).await.unwrap();
+    client.set_token(expired);
+
+    // The user removed the app from their Reddit preferences
+    server.revoke_token(MOCK_REFRESH_TOKEN);
+
+    let result = client.fetch_posts("rust").await;
+    assert!(matches!(
+        result,
+        Err(CoreError::RedditApi(RedditApiError::RefreshTokenRevoked))
+    ));
+    assert!(matches!(
+        client.get_auth_state(),
+        AuthState::NotAuthenticated
+    ));
+    assert!(store.load().await.unwrap().is_none());
+}
+
+#[tokio::test]
+async fn test_revoked_token_in_background_refresh_signs_out() {
+    let server = MockRedditServer::start().await.unwrap();
+    server.add_post(MockRedditServer::sample_post("rust", "a1", 1_700_000_000));
+    let store = Arc::new(MemoryTokenStore::new());
+    let mut client = RedditClient::new(test_config(&server))
+        .unwrap()
+        .with_token_store(store.clone());
+    let expiring = RedditToken {
+        access_token: "old-token".to_string(),
+        expires_at: SystemTime::now() + Duration::from_secs(60),
+        ..test_token()
+    };
+    store.save(&expiring).await.unwrap();
+    client.set_token(expiring);
+    server.revoke_token(MOCK_REFRESH_TOKEN);
+
+    // The still-valid token starts a background refresh that Reddit refuses
+    client.fetch_posts("rust").await.unwrap();
+    let mut result = Ok(());
+    for _ in 0..100 {
+        result = client.ensure_authenticated().await;
+        if result.is_err() {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+
+    assert!(matches!(
+        result,
+        Err(CoreError::RedditApi(RedditApiError::RefreshTokenRevoked))
+    ));
+    assert!(matches!(
+        client.get_auth_state(),
+        AuthState::NotAuthenticated
+    ));
+    assert!(store.load().await.unwrap().is_none());
+}
+
+#[tokio::test]
+async fn test_account_pool_polls_within_each_budget() {
+    let server = MockRedditServer::start().await.unwrap();
+    server.add_post(MockRedditServer::sample_post("rust", "a1", 1_700_000_000));
+
+    let mut pool = AccountPool::new();
+    for account_id in [1, 2] {
+        let mut client = RedditClient::new(test_config(&server)).unwrap();
+        client.set_token(test_token());
+        pool.insert(account_id, client).unwrap();
+    }
+
+    // Sharing an API client would merge two budgets
+    let shared = RedditClient::new(test_config(&server))
+        .unwrap()
+        .with_api_client(pool.get_mut(1).unwrap().api_client());
+    assert!(pool.insert(3, shared).is_err());
+
+    // Reddit reports account 1 as out of budget for the next minute
+    let user = serde_json::to_string(&MockRedditServer::sample_user("first")).unwrap();
+    server.enqueue_response(
+        ScriptedResponse::new(200, &user)
+            .with_header("X-Ratelimit-Used", "600")
+            .with_header("X-Ratelimit-Remaining", "0.0")
+            .with_header("X-Ratelimit-Reset", "60")
+            .for_path("/api/v1/me"),
+    );
+    pool.get_mut(1).unwrap().get_user_info().await.unwrap();
+
+    let outcomes = pool
+        .poll(|_, client| Box::pin(async move { client.fetch_posts("rust").await }))
+        .await;
+
+    assert_eq!(outcomes.len(), 2);
+    assert!(matches!(
+        outcomes[0],
+        (1, AccountPollOutcome::Skipped { retry_after }) if retry_after > Duration::from_secs(30)
+    ));
+    match &outcomes[1] {
+        (2, AccountPollOutcome::Polled(Ok(posts))) => assert_eq!(posts.len(), 1),
+        other => panic!("unexpected outcome: {:?}", other),
+    }
+    assert_eq!(server.request_count("/r/rust"), 1);
+}
+
+#[tokio::test]
+async fn test_response_cache_serves_fresh_hits_without_requests() {
+    let server = MockRedditServer::start().await.unwrap();
+    server.add_subreddit(MockRedditServer::sample_subreddit("rust"));
+    seed_posts(&server, "rust", 3);
+
+    let api_client = fast_api_client(&server)
+        .with_response_cache(ResponseCache::in_memory(CachePolicy::reddit_defaults()));
+
+    let info = api_client
+        .get_subreddit_info(MOCK_ACCESS_TOKEN, "rust")
+        .await
+        .unwrap();
+    assert_eq!(info.display_name, "rust");
+    assert!(api_client
+        .check_subreddit_access(MOCK_ACCESS_TOKEN, "rust")
+        .await
+        .unwrap());
+    api_client
+        .get_subreddit_info(MOCK_ACCESS_TOKEN, "rust")
+        .await
+        .unwrap();
+    assert_eq!(server.request_count("/r/rust/about"), 1);
+
+    for _ in 0..2 {
+        let listing = api_client
+            .get_subreddit_posts(MOCK_ACCESS_TOKEN, "rust", Some("new"), Some(2), None)
+            .await
+            .unwrap();
+        assert_eq!(listing.data.children.len(), 2);
+    }
+    assert_eq!(server.request_count("/r/rust/new"), 1);
+
+    // A different page is a different entry
+    api_client
+        .get_subreddit_posts(MOCK_ACCESS_TOKEN, "rust", Some("new"), Some(3), None)
+        .await
+        .unwrap();
+    assert_eq!(server.request_count("/r/rust/new"), 2);
+
+    // Cache hits never reach the rate limiter or the request metrics
+    let metrics = api_client.get_metrics().await;
+    assert_eq!(metrics.total_requests, 3);
+    assert_eq!(metrics.cache_hits, 3);
+    let server_limit = api_client
+        .get_rate_limit_status()
+        .await
+        .server_limit
+        .unwrap();
+    assert_eq!(server_limit.used, 3);
+
+    // Uncached endpoints always go to the server
+    api_client.get_user_info(MOCK_ACCESS_TOKEN).await.unwrap();
+    api_client.get_user_info(MOCK_ACCESS_TOKEN).await.unwrap();
+    assert_eq!(server.request_count("/api/v1/me"), 2);
+}
+
+#[tokio::test]
+async fn test_response_cache_revalidates_stale_entries() {
+    let server = MockRedditServer::start().await.unwrap();
+    seed_posts(&server, "rust", 3);
+
+    let policy = CachePolicy::new().with_ttl("/r/*/new", Duration::from_millis(20));
+    let api_client = fast_api_client(&server).with_response_cache(ResponseCache::in_memory(policy));
+
+    let first = api_client
+        .get_subreddit_posts(MOCK_ACCESS_TOKEN, "rust", Some("new"), None, None)
+        .await
+        .unwrap();
+    assert!(!server.requests()[0].headers.contains_key("if-none-match"));
+
+    tokio::time::sleep(Duration::from_millis(40)).await;
+    let second = api_client
+        .get_subreddit_posts(MOCK_ACCESS_TOKEN, "rust", Some("new"), None, None)
+        .await
+        .unwrap();
+
+    // The stale entry was revalidated and the cached body reused
+    let requests = server.requests();
+    assert_eq!(requests.len(), 2);
+    assert!(requests[1].headers.contains_key("if-none-match"));
+    assert_eq!(second.data.children.len(), first.data.children.len());
+    let metrics = api_client.get_metrics().await;
+    assert_eq!(metrics.successful_requests, 2);
+    assert_eq!(metrics.failed_requests, 0);
+
+    // A changed listing gets a new ETag and replaces the entry
+    tokio::time::sleep(Duration::from_millis(40)).await;
+    server.add_post(MockRedditServer::sample_post(
+        "rust",
+        "fresh",
+        1_800_000_000,
+    ));
+    let third = api_client
+        .get_subreddit_posts(MOCK_ACCESS_TOKEN, "rust", Some("new"), None, None)
+        .await
+        .unwrap();
+    assert_eq!(third.data.children.len(), first.data.children.len() + 1);
+
+    api_client.clear_response_cache().await.unwrap();
+    api_client
+        .get_subreddit_posts(MOCK_ACCESS_TOKEN, "rust", Some("new"), None, None)
+        .await
+        .unwrap();
+    assert!(!server.requests()[3].headers.contains_key("if-none-match"));
+}
+
+#[tokio::test]
+async fn test_endpoint_router_applies_pattern_limits() {
+    let server = MockRedditServer::start().await.unwrap();
+    seed_posts(&server, "rust", 3);
+
+    let router = EndpointRouter::new()
+        .with_endpoint(EndpointLimits::new("/r/*/new", 5).with_max_retries(0))
+        .with_endpoint(EndpointLimits::new("/r/*/hot", 5));
+    let api_client = fast_api_client(&server).with_endpoint_router(router);
+
+    for _ in 0..2 {
+        api_client
+            .get_subreddit_posts(MOCK_ACCESS_TOKEN, "rust", Some("new"), None, None)
+            .await
+            .unwrap();
+    }
+
+    // Each pattern draws on its own budget
+    let router = api_client.endpoint_router().unwrap();
+    let new_route = router.route("/r/golang/new").unwrap();
+    assert_eq!(new_route.available_tokens().await, 3);
+    let hot_route = router.route("/r/rust/hot").unwrap();
+    assert_eq!(hot_route.available_tokens().await, 5);
+
+    // The pattern's retry count overrides the client's three attempts
+    server.enqueue_response(ScriptedResponse::server_error(503).for_path("/r/rust/new"));
+    let result = api_client
+        .get_subreddit_posts(MOCK_ACCESS_TOKEN, "rust", Some("new"), None, None)
+        .await;
+    assert!(result.is_err());
+    assert_eq!(server.request_count("/r/rust/new"), 3);
+
+    server.enqueue_response(ScriptedResponse::server_error(503).for_path("/r/rust/hot"));
+    api_client
+        .get_subreddit_posts(MOCK_ACCESS_TOKEN, "rust", Some("hot"), None, None)
+        .await
+        .unwrap();
+    assert_eq!(server.request_count("/r/rust/hot"), 2);
+}
+
+#[tokio::test]
+async fn test_circuit_breakers_are_per_source() {
+    let server = MockRedditServer::start().await.unwrap();
+    seed_posts(&server, "rust", 2);
+
+    let retry_config = RetryConfig {
+        failure_threshold: 2,
+        recovery_timeout_s: 60,
+        ..fast_retry_config()
+    };
+    let api_client =
+        RedditApiClient::with_retry_config("likeminded-tests/1.0".to_string(), retry_config)
+            .with_base_url(server.base_url());
+
+    for _ in 0..2 {
+        server.enqueue_response(ScriptedResponse::new(403, "{}").for_path("/r/banned/"));
+        let results = api_client
+            .get_multiple_subreddit_posts(
+                MOCK_ACCESS_TOKEN,
+                &["banned"],
+                Some("new"),
+                None,
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+        assert!(results[0].1.is_err());
+    }
+
+    let states = api_client.get_circuit_breaker_state();
+    assert_eq!(states["/r/*/new r/banned"], CircuitBreakerState::Open);
+    assert!(!api_client.is_source_available("/r/banned/new", Some("banned")));
+    assert!(api_client.is_source_available("/r/banned/hot", Some("banned")));
+    assert!(api_client.is_source_available("/r/rust/new", Some("rust")));
+
+    // The broken subreddit is skipped without a request; the rest still poll
+    let results = api_client
+        .get_multiple_subreddit_posts(
+            MOCK_ACCESS_TOKEN,
+            &["banned", "rust"],
+            Some("new"),
+            None,
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+    assert!(matches!(
+        results[0].1,
+        Err(CoreError::RedditApi(
+            RedditApiError::EndpointUnavailable { .. }
+        ))
+    ));
+    assert_eq!(results[1].1.as_ref().unwrap().data.children.len(), 2);
+    assert_eq!(server.request_count("/r/banned/"), 2);
+    assert_eq!(
+        api_client.get_circuit_breaker_state()["/r/*/new r/rust"],
+        CircuitBreakerState::Closed
+    );
+}
+
+#[tokio::test]
+async fn test_metrics_server_serves_openmetrics() {
+    let server = MockRedditServer::start().await.unwrap();
+    seed_posts(&server, "rust", 2);
+
+    let api_client = Arc::new(fast_api_client(&server).with_endpoint_router(
+        EndpointRouter::new().with_endpoint(EndpointLimits::new("/r/*/new", 30)),
+    ));
+    api_client
+        .get_subreddit_posts(MOCK_ACCESS_TOKEN, "rust", Some("new"), None, None)
+        .await
+        .unwrap();
+
+    let exporter = MetricsExporter::new(api_client.clone());
+    assert!(exporter
+        .clone()
+        .serve("0.0.0.0:0".parse().unwrap())
+        .await
+        .is_err());
+
+    let metrics_server = exporter
+        .serve("127.0.0.1:0".parse().unwrap())
+        .await
+        .unwrap();
+    let response = reqwest::get(metrics_server.url()).await.unwrap();
+    assert_eq!(
+        response.headers()["content-type"],
+        "application/openmetrics-text; version=1.0.0; charset=utf-8"
+    );
+    let body = response.text().await.unwrap();
+
+    assert!(body.contains("reddit_requests_total{pattern=\"/r/*/new\",outcome=\"success\"} 1\n"));
+    assert!(body.contains("reddit_request_duration_seconds_count{pattern=\"/r/*/new\"} 1\n"));
+    assert!(body.contains("reddit_endpoint_rate_limit_tokens{pattern=\"/r/*/new\"} 29\n"));
+    assert!(body.contains(
+        "reddit_circuit_breaker_state{source=\"/r/*/new r/rust\",reddit_circuit_breaker_state=\"closed\"} 1\n"
+    ));
+    assert!(body.ends_with("# EOF\n"));
+
+    let not_found = reqwest::get(metrics_server.url().replace("/metrics", "/other"))
+        .await
+        .unwrap();
+    assert_eq!(not_found.status(), 404);
+}
+
+#[tokio::test]
+async fn test_batch_failure_only_trips_broken_subreddit() {
+    let server = MockRedditServer::start().await.unwrap();
+    seed_posts(&server, "rust", 3);
+    seed_posts(&server, "golang", 2);
+    server.enqueue_response(ScriptedResponse::new(403, "{}").for_path("/r/rust+banned+golang/"));
+
+    let retry_config = RetryConfig {
+        failure_threshold: 2,
+        recovery_timeout_s: 60,
+        ..fast_retry_config()
+    };
+    let api_client =
+        RedditApiClient::with_retry_config("likeminded-tests/1.0".to_string(), retry_config)
+            .with_base_url(server.base_url());
+    let subreddits = ["rust", "banned", "golang"];
+
+    // The failed batch is fetched again one subreddit at a time
+    let results = api_client
+        .get_batched_subreddit_posts(
+            MOCK_ACCESS_TOKEN,
+            &subreddits,
+            Some("new"),
+            None,
+            None,
+            2000,
+        )
+        .await
+        .unwrap();
+    let names: Vec<_> = results.iter().map(|(name, _)| name.as_str()).collect();
+    assert_eq!(names, subreddits);
+    assert_eq!(results[0].1.as_ref().unwrap().data.children.len(), 3);
+    assert!(results[1].1.is_err());
+    assert_eq!(results[2].1.as_ref().unwrap().data.children.len(), 2);
+
+    assert!(!api_client.is_source_available("/r/banned/new", Some("banned")));
+    assert!(api_client.is_source_available("/r/rust/new", Some("rust")));
+    assert!(api_client.is_source_available("/r/golang/new", Some("golang")));
+
+    // The next poll leaves the broken subreddit out of the batch
+    let results = api_client
+        .get_batched_subreddit_posts(
+            MOCK_ACCESS_TOKEN,
+            &subreddits,
+            Some("new"),
+            None,
+            None,
+            2000,
+        )
+        .await
+        .unwrap();
+    assert!(matches!(
+        results[1].1,
+        Err(CoreError::RedditApi(
+            RedditApiError::EndpointUnavailable { .. }
+        ))
+    ));
+    assert_eq!(server.request_count("/r/rust+golang/new"), 1);
+    assert_eq!(results[0].1.as_ref().unwrap().data.children.len(), 3);
+}
+
+#[tokio::test]
+async fn test_circuit_breakers_group_posts_by_pattern() {
+    let server = MockRedditServer::start().await.unwrap();
+    seed_comment_thread(&server);
+
+    let api_client = fast_api_client(&server);
+    let options = CommentFetchOptions::new();
+    for post_id in ["post1", "missing"] {
+        let _ = api_client
+            .get_post_comments(MOCK_ACCESS_TOKEN, post_id, &options)
+            .await;
+    }
+
+    let states = api_client.get_circuit_breaker_state();
+    assert!(states.contains_key("/comments/*"));
+    assert!(states.keys().all(|source| !source.contains("post1")));
+}